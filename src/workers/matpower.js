@@ -1,4 +1,4 @@
-import init, { parse_case } from 'wasm_matpower'
+import init, { ac_power_flow, dc_power_flow, parse_file, to_xlsx } from 'wasm_matpower'
 
 async function init_wasm_matpower() {
   await init()
@@ -7,9 +7,39 @@ async function init_wasm_matpower() {
   self.addEventListener(
     'message',
     function (event) {
-      const { data } = event.data
+      if (event.data.type === 'xlsx') {
+        let xlsx = null
+        try {
+          xlsx = to_xlsx(event.data.case)
+        } catch (e) {
+          console.error(e)
+        }
+        self.postMessage({ type: 'xlsx', xlsx, name: event.data.case.name })
+        return
+      }
+      if (event.data.type === 'dc_power_flow' || event.data.type === 'ac_power_flow') {
+        let power_flow = null
+        try {
+          power_flow =
+            event.data.type === 'dc_power_flow'
+              ? dc_power_flow(event.data.case)
+              : ac_power_flow(event.data.case, event.data.options)
+        } catch (e) {
+          console.error(e)
+        }
+        self.postMessage({ type: event.data.type, power_flow, name: event.data.case.name })
+        return
+      }
+      const { data, name } = event.data
+      let c = null
+      try {
+        c = parse_file(new Uint8Array(data), name)
+      } catch (e) {
+        console.error(e)
+      }
       self.postMessage({
-        data: parse_case(data),
+        type: 'parse',
+        data: c,
       })
     },
     false,
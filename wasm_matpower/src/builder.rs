@@ -0,0 +1,149 @@
+// Building cases in code
+
+// `CaseBuilder` collects rows in the order they are added and checks on `build` that the tables fit together: bus
+// numbers are unique, generators and branches connect existing buses, and there is one cost row per generator, or
+// two with reactive power costs.
+
+use anyhow::{anyhow, Result};
+
+use crate::case::{Branch, Bus, BusId, Case, CostModel, DcLine, Gen, GenCost, Version};
+
+/// Builds a `Case` row by row.
+///
+/// ```
+/// use wasm_matpower::{builder::CaseBuilder, case::{Branch, Bus, BusId, BusType, Gen}};
+///
+/// let case = CaseBuilder::new("case2")
+///   .bus(Bus { idx: BusId(1), bus_type: BusType::Ref, ..Bus::default() })
+///   .bus(Bus { idx: BusId(2), pd: 90.0, qd: 30.0, ..Bus::default() })
+///   .gen(Gen { gen: BusId(1), pmax: 250.0, ..Gen::default() })
+///   .branch(Branch { f_bus: BusId(1), t_bus: BusId(2), br_x: 0.0576, ..Branch::default() })
+///   .build()
+///   .unwrap();
+/// assert_eq!(case.gens_at(1).count(), 1);
+/// ```
+#[derive(Debug, Clone)]
+pub struct CaseBuilder {
+  case: Case,
+  names: Vec<Option<String>>,
+}
+
+impl CaseBuilder {
+  /// An empty version 2 case on a 100 MVA base.
+  pub fn new(name: &str) -> CaseBuilder {
+    CaseBuilder {
+      case: Case {
+        name: name.to_string(),
+        version: Version::Version2,
+        base_mva: 100.0,
+        bus: vec![],
+        gen: vec![],
+        gencost: vec![],
+        branch: vec![],
+        dcline: vec![],
+        bus_name: vec![],
+        extra: Default::default(),
+      },
+      names: vec![],
+    }
+  }
+
+  pub fn base_mva(mut self, base_mva: f64) -> CaseBuilder {
+    self.case.base_mva = base_mva;
+    self
+  }
+
+  pub fn version(mut self, version: Version) -> CaseBuilder {
+    self.case.version = version;
+    self
+  }
+
+  pub fn bus(mut self, bus: Bus) -> CaseBuilder {
+    self.case.bus.push(bus);
+    self.names.push(None);
+    self
+  }
+
+  /// Add a bus with a name. Buses added without one are named by number if any bus has a name.
+  pub fn named_bus(mut self, bus: Bus, name: &str) -> CaseBuilder {
+    self.case.bus.push(bus);
+    self.names.push(Some(name.to_string()));
+    self
+  }
+
+  pub fn gen(mut self, gen: Gen) -> CaseBuilder {
+    self.case.gen.push(gen);
+    self
+  }
+
+  pub fn branch(mut self, branch: Branch) -> CaseBuilder {
+    self.case.branch.push(branch);
+    self
+  }
+
+  /// Add the cost of the next generator, in generator order. Reactive power costs follow all active power costs.
+  pub fn gencost(mut self, gencost: GenCost) -> CaseBuilder {
+    self.case.gencost.push(gencost);
+    self
+  }
+
+  pub fn dcline(mut self, dcline: DcLine) -> CaseBuilder {
+    self.case.dcline.push(dcline);
+    self
+  }
+
+  /// The case, or the first inconsistency found in it.
+  pub fn build(self) -> Result<Case> {
+    let CaseBuilder { mut case, names } = self;
+    if case.base_mva <= 0.0 {
+      return Err(anyhow!("Base MVA must be positive, found {}", case.base_mva));
+    }
+    if let Some(d) = case.bus_index()?.dangling(&case).first() {
+      return Err(anyhow!("{}", d));
+    }
+    case.check_gencost_rows()?;
+    for (i, c) in case.gencost.iter().enumerate() {
+      let n = match c.model {
+        CostModel::PiecewiseLinear => c.ncost * 2,
+        CostModel::Polynomial => c.ncost,
+      };
+      if n != c.cost.len() {
+        return Err(anyhow!("Cost row {} has NCOST {} but {} values", i + 1, c.ncost, c.cost.len()));
+      }
+    }
+    if names.iter().any(Option::is_some) {
+      case.bus_name =
+        names.into_iter().zip(case.bus.iter()).map(|(n, b)| n.unwrap_or_else(|| b.idx.to_string())).collect();
+    }
+    Ok(case)
+  }
+}
+
+#[test]
+fn test_build() {
+  use crate::case::BusType;
+
+  let builder = CaseBuilder::new("case3")
+    .named_bus(Bus { idx: BusId(1), bus_type: BusType::Ref, ..Bus::default() }, "One")
+    .bus(Bus { idx: BusId(2), pd: 90.0, ..Bus::default() })
+    .gen(Gen { gen: BusId(1), pmax: 250.0, ..Gen::default() })
+    .branch(Branch { f_bus: BusId(1), t_bus: BusId(2), br_x: 0.1, ..Branch::default() });
+  let c = builder.clone().build().unwrap();
+  assert_eq!((c.name.as_str(), c.base_mva, c.bus.len(), c.branch.len()), ("case3", 100.0, 2, 1));
+  assert_eq!(c.bus_name, vec!["One", "2"]);
+
+  let cost = GenCost { model: CostModel::Polynomial, startup: 0.0, shutdown: 0.0, ncost: 2, cost: vec![20.0, 0.0] };
+  assert!(builder.clone().gencost(cost.clone()).build().is_ok());
+  assert_eq!(
+    builder.clone().gencost(cost.clone()).gencost(cost.clone()).gencost(cost).build().unwrap_err().to_string(),
+    "Expected 1 or 2 cost rows for 1 generators, found 3"
+  );
+  assert_eq!(
+    builder.clone().bus(Bus { idx: BusId(2), ..Bus::default() }).build().unwrap_err().to_string(),
+    "Duplicate bus 2 in mpc.bus rows 2 and 3"
+  );
+  assert_eq!(
+    builder.branch(Branch { f_bus: BusId(2), t_bus: BusId(5), ..Branch::default() }).build().unwrap_err().to_string(),
+    "mpc.branch row 2 refers to bus 5, which is not in mpc.bus"
+  );
+}
@@ -2,7 +2,7 @@
 
 // https://codeandbitters.com/lets-build-a-parser/
 
-use std::fmt;
+use std::{collections::BTreeMap, fmt};
 
 use anyhow::{anyhow, Result};
 use escape8259::unescape;
@@ -11,7 +11,7 @@ use nom::{
   branch::alt,
   bytes::complete::{is_not, take_till, take_until, take_until1, take_while1},
   character::complete::{alpha1, alphanumeric1, char, multispace0, one_of},
-  combinator::{fail, map, map_res, opt, recognize, value},
+  combinator::{consumed, fail, map, map_res, opt, recognize, value},
   error::{ContextError, ErrorKind, ParseError, VerboseError},
   multi::{fold_many1, many0, many1, separated_list0, separated_list1},
   sequence::{delimited, pair, preceded, terminated, tuple},
@@ -25,9 +25,16 @@ use nom_supreme::{
   ParserExt,
 };
 use serde::{Deserialize, Serialize};
-use typescript_definitions::{TypeScriptify, TypescriptDefinition};
+use typescript_definitions::TypeScriptify;
+// `TypescriptDefinition` emits a `wasm_bindgen` custom section, so it is only derived for the wasm build.
+#[cfg(feature = "wasm")]
+use typescript_definitions::TypescriptDefinition;
+#[cfg(feature = "wasm")]
 use wasm_bindgen::prelude::*;
 
+pub use crate::diff::{diff, CaseDiff};
+use crate::index::{BusIndex, DanglingReference, RowSpans, SourceSpan};
+
 type Span<'a> = LocatedSpan<&'a str>;
 type PError<'a> = ErrorTree<Span<'a>>;
 type PResult<'a, O> = nom::IResult<Span<'a>, O, PError<'a>>;
@@ -155,11 +162,40 @@ where
   delimited(multispace0, f, multispace0)
 }
 
-#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Copy, TypeScriptify, TypescriptDefinition)]
-enum BusType {
+/// A bus number, `BUS_I`, as used by generators, branches and DC lines to refer to buses. Bus numbers need not be
+/// consecutive; `BusIndex` maps them to positions in `Case::bus`.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy, Default, TypeScriptify)]
+#[cfg_attr(feature = "wasm", derive(TypescriptDefinition))]
+#[serde(transparent)]
+pub struct BusId(pub usize);
+
+impl fmt::Display for BusId {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "{}", self.0)
+  }
+}
+
+impl From<usize> for BusId {
+  fn from(v: usize) -> BusId {
+    BusId(v)
+  }
+}
+
+fn bus_id(i: Span) -> PResult<BusId> {
+  map(usize, BusId).context("bus_id").parse(i)
+}
+
+/// Bus type, the `BUS_TYPE` column.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Copy, TypeScriptify)]
+#[cfg_attr(feature = "wasm", derive(TypescriptDefinition))]
+pub enum BusType {
+  /// load bus
   PQ = 1,
+  /// generator bus with a voltage setpoint
   PV = 2,
+  /// reference (slack) bus
   Ref = 3,
+  /// isolated bus
   Isolated = 4,
 }
 
@@ -183,31 +219,53 @@ fn test_bus_type() {
   assert!(bus_type("5".into()).is_err());
 }
 
-#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy, TypeScriptify, TypescriptDefinition)]
+/// A row of `mpc.bus`.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy, TypeScriptify)]
+#[cfg_attr(feature = "wasm", derive(TypescriptDefinition))]
 pub struct Bus {
-  idx: usize,             // bus number
-  bus_type: BusType,      // BusType
-  pd: f64,                // real power demand (MW)
-  qd: f64,                // reactive power demand (MVAr)
-  shunt_conductance: f64, // MW demanded at V = 1.0 p.u.
-  shunt_susceptance: f64, // MVar injected at V = 1.0 p.u.
-  area: usize,            // area
-  voltage_mag: f64,       // p.u.
-  voltage_ang: f64,       // degrees
-  base_kv: f64,           // kV
-  zone: usize,            // loss zone
-  v_max: f64,             // p.u.
-  v_min: f64,             // p.u.
-  lam_p: Option<f64>,     // Lagrange multiplier u/MW
-  lam_q: Option<f64>,     // Lagrange multiplier u/MVAr
-  mu_vmax: Option<f64>,   // Kuhn Tucker multiplier u/p.u.
-  mu_vmin: Option<f64>,   // Kuhn Tucker multiplier u/p.u.
+  /// bus number
+  pub idx: BusId,
+  /// BusType
+  pub bus_type: BusType,
+  /// real power demand (MW)
+  pub pd: f64,
+  /// reactive power demand (MVAr)
+  pub qd: f64,
+  /// MW demanded at V = 1.0 p.u.
+  pub shunt_conductance: f64,
+  /// MVar injected at V = 1.0 p.u.
+  pub shunt_susceptance: f64,
+  /// area
+  pub area: usize,
+  /// p.u.
+  pub voltage_mag: f64,
+  /// degrees
+  pub voltage_ang: f64,
+  /// kV
+  pub base_kv: f64,
+  /// loss zone
+  pub zone: usize,
+  /// p.u.
+  pub v_max: f64,
+  /// p.u.
+  pub v_min: f64,
+  /// Lagrange multiplier u/MW
+  pub lam_p: Option<f64>,
+  /// Lagrange multiplier u/MVAr
+  pub lam_q: Option<f64>,
+  /// Kuhn Tucker multiplier u/p.u.
+  pub mu_vmax: Option<f64>,
+  /// Kuhn Tucker multiplier u/p.u.
+  pub mu_vmin: Option<f64>,
+  /// (lat, lon) degrees, from a side file
+  #[serde(default)]
+  pub coords: Option<(f64, f64)>,
 }
 
 fn bus(i: Span) -> PResult<Bus> {
   let parser = terminated(
     tuple((
-      ws(usize),      // bus_i
+      ws(bus_id),     // bus_i
       ws(bus_type),   // type
       ws(float),      // Pd
       ws(float),      // Qd
@@ -246,6 +304,7 @@ fn bus(i: Span) -> PResult<Bus> {
       lam_q: bus.14,
       mu_vmax: bus.15,
       mu_vmin: bus.16,
+      coords: None,
     }
   })
   .context("bus")
@@ -255,7 +314,7 @@ fn bus(i: Span) -> PResult<Bus> {
 #[test]
 fn test_bus() {
   assert_eq!(bus("	1	2	51	27	0	0	1	0.955	10.67	138	1	1.06	0.94;".into()).unwrap().1, Bus {
-    idx: 1,
+    idx: BusId(1),
     bus_type: BusType::PV,
     pd: 51.0,
     qd: 27.0,
@@ -272,43 +331,71 @@ fn test_bus() {
     lam_q: None,
     mu_vmax: None,
     mu_vmin: None,
+    coords: None,
   });
 }
 
-#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy, TypeScriptify, TypescriptDefinition)]
+/// A row of `mpc.gen`.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy, TypeScriptify)]
+#[cfg_attr(feature = "wasm", derive(TypescriptDefinition))]
 pub struct Gen {
-  gen: usize,           // bus 1 bus number
-  pg: f64,              // 2 real power output (mw)
-  qg: f64,              // 3 reactive power output (mvar)
-  qmax: f64,            // 4 maximum reactive power output (mvar)
-  qmin: f64,            // 5 minimum reactive power output (mvar)
-  vg: f64,              // 6 voltage magnitude setpoint (p.u.)
-  mbase: f64,           // 7 total mva base of machine, defaults to basemva
-  gen_status: usize,    // status 8 machine status, > 0 = machine in-service ≤0 = machine out-of-service
-  pmax: f64,            // 9 maximum real power output (mw)
-  pmin: f64,            // 10 minimum real power output (mw)
-  pc1: f64,             // 11 lower real power output of pq capability curve (mw)
-  pc2: f64,             // 12 upper real power output of pq capability curve (mw)
-  qc1min: f64,          // 13 minimum reactive power output at pc1 (mvar)
-  qc1max: f64,          // 14 maximum reactive power output at pc1 (mvar)
-  qc2min: f64,          // 15 minimum reactive power output at pc2 (mvar)
-  qc2max: f64,          // 16 maximum reactive power output at pc2 (mvar)
-  ramp_agc: f64,        // 17 ramp rate for load following/agc (mw/min)
-  ramp_10: f64,         // 18 ramp rate for 10 minute reserves (mw)
-  ramp_30: f64,         // 19 ramp rate for 30 minute reserves (mw)
-  ramp_q: f64,          // 20 ramp rate for reactive power (2 sec timescale) (mvar/min)
-  apf: f64,             // 21 area participation factor
-  mu_pmax: Option<f64>, // 22 kuhn-tucker multiplier on upper pg limit (u/mw)
-  mu_pmin: Option<f64>, // 23 kuhn-tucker multiplier on lower pg limit (u/mw)
-  mu_qmax: Option<f64>, // 24 kuhn-tucker multiplier on upper qg limit (u/mvar)
-  mu_qmin: Option<f64>, // 25 kuhn-tucker multiplier on lower qg limit (u/mvar)
+  /// bus 1 bus number
+  pub gen: BusId,
+  /// 2 real power output (mw)
+  pub pg: f64,
+  /// 3 reactive power output (mvar)
+  pub qg: f64,
+  /// 4 maximum reactive power output (mvar)
+  pub qmax: f64,
+  /// 5 minimum reactive power output (mvar)
+  pub qmin: f64,
+  /// 6 voltage magnitude setpoint (p.u.)
+  pub vg: f64,
+  /// 7 total mva base of machine, defaults to basemva
+  pub mbase: f64,
+  /// status 8 machine status, > 0 = machine in-service ≤0 = machine out-of-service
+  pub gen_status: usize,
+  /// 9 maximum real power output (mw)
+  pub pmax: f64,
+  /// 10 minimum real power output (mw)
+  pub pmin: f64,
+  /// 11 lower real power output of pq capability curve (mw)
+  pub pc1: f64,
+  /// 12 upper real power output of pq capability curve (mw)
+  pub pc2: f64,
+  /// 13 minimum reactive power output at pc1 (mvar)
+  pub qc1min: f64,
+  /// 14 maximum reactive power output at pc1 (mvar)
+  pub qc1max: f64,
+  /// 15 minimum reactive power output at pc2 (mvar)
+  pub qc2min: f64,
+  /// 16 maximum reactive power output at pc2 (mvar)
+  pub qc2max: f64,
+  /// 17 ramp rate for load following/agc (mw/min)
+  pub ramp_agc: f64,
+  /// 18 ramp rate for 10 minute reserves (mw)
+  pub ramp_10: f64,
+  /// 19 ramp rate for 30 minute reserves (mw)
+  pub ramp_30: f64,
+  /// 20 ramp rate for reactive power (2 sec timescale) (mvar/min)
+  pub ramp_q: f64,
+  /// 21 area participation factor
+  pub apf: f64,
+  /// 22 kuhn-tucker multiplier on upper pg limit (u/mw)
+  pub mu_pmax: Option<f64>,
+  /// 23 kuhn-tucker multiplier on lower pg limit (u/mw)
+  pub mu_pmin: Option<f64>,
+  /// 24 kuhn-tucker multiplier on upper qg limit (u/mvar)
+  pub mu_qmax: Option<f64>,
+  /// 25 kuhn-tucker multiplier on lower qg limit (u/mvar)
+  pub mu_qmin: Option<f64>,
 }
 
 fn gen(i: Span) -> PResult<Gen> {
   let parser = terminated(
     tuple((
       tuple((
-        ws(usize), // gen
+        ws(bus_id), // gen
         ws(float), // pg
         ws(float), // qg
         ws(float), // qmax
@@ -375,7 +462,7 @@ fn gen(i: Span) -> PResult<Gen> {
 #[test]
 fn test_gen() {
   assert_eq!(gen("	1	0	0	15	-5	0.955	100	1	100	0	0	0	0	0	0	0	0	0	0	0	0;".into()).unwrap().1, Gen {
-    gen: 1,
+    gen: BusId(1),
     pg: 0.0,
     qg: 0.0,
     qmax: 15.0,
@@ -403,36 +490,59 @@ fn test_gen() {
   });
 }
 
-#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy, TypeScriptify, TypescriptDefinition)]
+/// A row of `mpc.branch`, a line or a transformer.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy, TypeScriptify)]
+#[cfg_attr(feature = "wasm", derive(TypescriptDefinition))]
 pub struct Branch {
-  f_bus: f64,             // 1 “from” bus number
-  t_bus: f64,             // 2 “to” bus number
-  br_r: f64,              // 3 resistance (p.u.)
-  br_x: f64,              // 4 reactance (p.u.)
-  br_b: f64,              // 5 total line charging susceptance (p.u.)
-  rate_a: f64,            // 6 mva rating a (long term rating), set to 0 for unlimited
-  rate_b: f64,            // 7 mva rating b (short term rating), set to 0 for unlimited
-  rate_c: f64,            // 8 mva rating c (emergency rating), set to 0 for unlimited
-  tap: f64,               // 9 transformer off nominal turns ratio
-  shift: f64,             // 10 transformer phase shift angle (degrees), positive ⇒ delay
-  br_status: f64,         // 11 initial branch status, 1 = in-service, 0 = out-of-service
-  angmin: f64,            // 12 minimum angle difference, θf −θt (degrees)
-  angmax: f64,            // 13 maximum angle difference, θf −θt (degrees)
-  pf: Option<f64>,        // 14 real power injected at “from” bus end (mw)
-  qf: Option<f64>,        // 15 reactive power injected at “from” bus end (mvar)
-  pt: Option<f64>,        // 16 real power injected at “to” bus end (mw)
-  qt: Option<f64>,        // 17 reactive power injected at “to” bus end (mvar)
-  mu_sf: Option<f64>,     // 18 kuhn-tucker multiplier on mva limit at “from” bus (u/mva)
-  mu_st: Option<f64>,     // 19 kuhn-tucker multiplier on mva limit at “to” bus (u/mva)
-  mu_angmin: Option<f64>, // 20 kuhn-tucker multiplier lower angle difference limit (u/degree)
-  mu_angmax: Option<f64>, // 21 kuhn-tucker multiplier upper angle difference limit (u/degree)
+  /// 1 “from” bus number
+  pub f_bus: BusId,
+  /// 2 “to” bus number
+  pub t_bus: BusId,
+  /// 3 resistance (p.u.)
+  pub br_r: f64,
+  /// 4 reactance (p.u.)
+  pub br_x: f64,
+  /// 5 total line charging susceptance (p.u.)
+  pub br_b: f64,
+  /// 6 mva rating a (long term rating), set to 0 for unlimited
+  pub rate_a: f64,
+  /// 7 mva rating b (short term rating), set to 0 for unlimited
+  pub rate_b: f64,
+  /// 8 mva rating c (emergency rating), set to 0 for unlimited
+  pub rate_c: f64,
+  /// 9 transformer off nominal turns ratio
+  pub tap: f64,
+  /// 10 transformer phase shift angle (degrees), positive ⇒ delay
+  pub shift: f64,
+  /// 11 initial branch status, 1 = in-service, 0 = out-of-service
+  pub br_status: f64,
+  /// 12 minimum angle difference, θf −θt (degrees)
+  pub angmin: f64,
+  /// 13 maximum angle difference, θf −θt (degrees)
+  pub angmax: f64,
+  /// 14 real power injected at “from” bus end (mw)
+  pub pf: Option<f64>,
+  /// 15 reactive power injected at “from” bus end (mvar)
+  pub qf: Option<f64>,
+  /// 16 real power injected at “to” bus end (mw)
+  pub pt: Option<f64>,
+  /// 17 reactive power injected at “to” bus end (mvar)
+  pub qt: Option<f64>,
+  /// 18 kuhn-tucker multiplier on mva limit at “from” bus (u/mva)
+  pub mu_sf: Option<f64>,
+  /// 19 kuhn-tucker multiplier on mva limit at “to” bus (u/mva)
+  pub mu_st: Option<f64>,
+  /// 20 kuhn-tucker multiplier lower angle difference limit (u/degree)
+  pub mu_angmin: Option<f64>,
+  /// 21 kuhn-tucker multiplier upper angle difference limit (u/degree)
+  pub mu_angmax: Option<f64>,
 }
 
 fn branch(i: Span) -> PResult<Branch> {
   let parser = terminated(
     tuple((
-      ws(float),      // f_bus
-      ws(float),      // t_bus
+      ws(bus_id),     // f_bus
+      ws(bus_id),     // t_bus
       ws(float),      // br_r
       ws(float),      // br_x
       ws(float),      // br_b
@@ -487,8 +597,8 @@ fn branch(i: Span) -> PResult<Branch> {
 #[test]
 fn test_branch() {
   assert_eq!(branch("	1	2	0.0303	0.0999	0.0254	0	0	0	0	0	1	-360	360;".into()).unwrap().1, Branch {
-    f_bus: 1.0,
-    t_bus: 2.0,
+    f_bus: BusId(1),
+    t_bus: BusId(2),
     br_r: 0.0303,
     br_x: 0.0999,
     br_b: 0.0254,
@@ -511,8 +621,10 @@ fn test_branch() {
   });
 }
 
-#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Copy, TypeScriptify, TypescriptDefinition)]
-enum CostModel {
+/// Generator cost model, the `MODEL` column of `mpc.gencost`.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Copy, TypeScriptify)]
+#[cfg_attr(feature = "wasm", derive(TypescriptDefinition))]
+pub enum CostModel {
   PiecewiseLinear = 1,
   Polynomial = 2,
 }
@@ -530,14 +642,22 @@ fn test_cost_model() {
   assert!(cost_model("5".into()).is_err());
 }
 
-#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, TypeScriptify, TypescriptDefinition)]
+/// A row of `mpc.gencost`.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, TypeScriptify)]
+#[cfg_attr(feature = "wasm", derive(TypescriptDefinition))]
 pub struct GenCost {
-  model: CostModel,
-  startup: f64,
-  shutdown: f64,
-  ncost: usize, /* number N = n + 1 of data points defining an n-segment piecewise linear cost function,
-                 * or of coefficients defining an n-th order polynomial cost function */
-  cost: Vec<f64>,
+  /// cost model, piecewise linear or polynomial
+  pub model: CostModel,
+  /// startup cost in US dollars
+  pub startup: f64,
+  /// shutdown cost in US dollars
+  pub shutdown: f64,
+  /// number N = n + 1 of data points defining an n-segment piecewise linear cost function, or of coefficients
+  /// defining an n-th order polynomial cost function
+  pub ncost: usize,
+  /// (MW, $/hr) pairs p0, f0, ..., pn, fn for piecewise linear costs, or coefficients cn, ..., c1, c0 of the
+  /// polynomial, highest order first
+  pub cost: Vec<f64>,
 }
 
 fn gen_cost(i: Span) -> PResult<GenCost> {
@@ -575,8 +695,10 @@ fn test_gen_cost() {
   });
 }
 
-#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Copy, TypeScriptify, TypescriptDefinition)]
-enum ServiceStatus {
+/// Status of a DC line.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Copy, TypeScriptify)]
+#[cfg_attr(feature = "wasm", derive(TypescriptDefinition))]
+pub enum ServiceStatus {
   OutOfService = 0,
   InService = 1,
 }
@@ -594,39 +716,64 @@ fn test_service_status() {
   assert!(service_status("5".into()).is_err());
 }
 
-#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy, TypeScriptify, TypescriptDefinition)]
+/// A row of `mpc.dcline`.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy, TypeScriptify)]
+#[cfg_attr(feature = "wasm", derive(TypescriptDefinition))]
 pub struct DcLine {
-  f_bus: usize,             // 1 “from” bus number
-  t_bus: usize,             // 2 “to” bus number
-  br_status: ServiceStatus, // 3 initial branch status, 1 = in-service, 0 = out-of-service
-  pf: f64,                  // †4 real power flow at “from” bus end (mw), “from” → “to”
-  pt: f64,                  // †5 real power flow at “to” bus end (mw), “from” → “to”
-  qf: f64,                  // †6 reactive power injected into “from” bus (mvar)
-  qt: f64,                  // †7 reactive power injected into “to” bus (mvar)
-  vf: f64,                  // 8 voltage magnitude setpoint at “from” bus (p.u.)
-  vt: f64,                  // 9 voltage magnitude setpoint at “to” bus (p.u.)
-  pmin: f64,                // 10 if positive (negative), lower limit on pf (pt)
-  pmax: f64,                // 11 if positive (negative), upper limit on pf (pt)
-  qminf: f64,               // 12 lower limit on reactive power injection into “from” bus (mvar)
-  qmaxf: f64,               // 13 upper limit on reactive power injection into “from” bus (mvar)
-  qmint: f64,               // 14 lower limit on reactive power injection into “to” bus (mvar)
-  qmaxt: f64,               // 15 upper limit on reactive power injection into “to” bus (mvar)
-  loss0: f64,               // 16 coefficient l0 of constant term of linear loss function (mw)
-  loss1: f64,               // 17 coefficient l1 of linear term of linear loss function (mw/mw)
-  mu_pmin: Option<f64>,     // ‡18 kuhn-tucker multiplier on lower flow limit at “from” bus (u/mw)
-  mu_pmax: Option<f64>,     // ‡19 kuhn-tucker multiplier on upper flow limit at “from” bus (u/mw)
-  mu_qminf: Option<f64>,    // ‡20 kuhn-tucker multiplier on lower var limit at “from” bus (u/mvar)
-  mu_qmaxf: Option<f64>,    // ‡21 kuhn-tucker multiplier on upper var limit at “from” bus (u/mvar)
-  mu_qmint: Option<f64>,    // ‡22 kuhn-tucker multiplier on lower var limit at “to” bus (u/mvar)
-  mu_qmaxt: Option<f64>,    // ‡23 kuhn-tucker multiplier on upper var limit at “to” bus (u/mvar)
+  /// 1 “from” bus number
+  pub f_bus: BusId,
+  /// 2 “to” bus number
+  pub t_bus: BusId,
+  /// 3 initial branch status, 1 = in-service, 0 = out-of-service
+  pub br_status: ServiceStatus,
+  /// †4 real power flow at “from” bus end (mw), “from” → “to”
+  pub pf: f64,
+  /// †5 real power flow at “to” bus end (mw), “from” → “to”
+  pub pt: f64,
+  /// †6 reactive power injected into “from” bus (mvar)
+  pub qf: f64,
+  /// †7 reactive power injected into “to” bus (mvar)
+  pub qt: f64,
+  /// 8 voltage magnitude setpoint at “from” bus (p.u.)
+  pub vf: f64,
+  /// 9 voltage magnitude setpoint at “to” bus (p.u.)
+  pub vt: f64,
+  /// 10 if positive (negative), lower limit on pf (pt)
+  pub pmin: f64,
+  /// 11 if positive (negative), upper limit on pf (pt)
+  pub pmax: f64,
+  /// 12 lower limit on reactive power injection into “from” bus (mvar)
+  pub qminf: f64,
+  /// 13 upper limit on reactive power injection into “from” bus (mvar)
+  pub qmaxf: f64,
+  /// 14 lower limit on reactive power injection into “to” bus (mvar)
+  pub qmint: f64,
+  /// 15 upper limit on reactive power injection into “to” bus (mvar)
+  pub qmaxt: f64,
+  /// 16 coefficient l0 of constant term of linear loss function (mw)
+  pub loss0: f64,
+  /// 17 coefficient l1 of linear term of linear loss function (mw/mw)
+  pub loss1: f64,
+  /// ‡18 kuhn-tucker multiplier on lower flow limit at “from” bus (u/mw)
+  pub mu_pmin: Option<f64>,
+  /// ‡19 kuhn-tucker multiplier on upper flow limit at “from” bus (u/mw)
+  pub mu_pmax: Option<f64>,
+  /// ‡20 kuhn-tucker multiplier on lower var limit at “from” bus (u/mvar)
+  pub mu_qminf: Option<f64>,
+  /// ‡21 kuhn-tucker multiplier on upper var limit at “from” bus (u/mvar)
+  pub mu_qmaxf: Option<f64>,
+  /// ‡22 kuhn-tucker multiplier on lower var limit at “to” bus (u/mvar)
+  pub mu_qmint: Option<f64>,
+  /// ‡23 kuhn-tucker multiplier on upper var limit at “to” bus (u/mvar)
+  pub mu_qmaxt: Option<f64>,
 }
 
 fn dcline(i: Span) -> PResult<DcLine> {
   let parser = terminated(
     tuple((
       tuple((
-        ws(usize),          // f_bus
-        ws(usize),          // t_bus
+        ws(bus_id),         // f_bus
+        ws(bus_id),         // t_bus
         ws(service_status), // br_status
         ws(float),          // pf
         ws(float),          // pt
@@ -689,8 +836,8 @@ fn dcline(i: Span) -> PResult<DcLine> {
 fn test_dcline() {
   assert_eq!(dcline("	2060653	66353	1	500	465.35	-370.36	-369.49	0.96984	0.92617	500	500	-370.36	-370.36	-369.49	-369.49	0	0	0.0000	0.0000	0.0000	0.0000	0.0000	0.0000;".into()).unwrap().1,
         DcLine {
-            f_bus: 2060653,
-            t_bus: 66353,
+            f_bus: BusId(2060653),
+            t_bus: BusId(66353),
             br_status: ServiceStatus::InService,
             pf: 500.0,
             pt: 465.35,
@@ -727,7 +874,9 @@ fn test_dcline() {
         });
 }
 
-#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Copy, TypeScriptify, TypescriptDefinition)]
+/// MATPOWER case format version, `mpc.version`.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Copy, TypeScriptify)]
+#[cfg_attr(feature = "wasm", derive(TypescriptDefinition))]
 pub enum Version {
   Version1 = 1,
   Version2 = 2,
@@ -766,17 +915,32 @@ pub fn identifier(i: Span) -> PResult<Span> {
   recognize(pair(alt((alpha1, tag("_"))), many0(alt((alphanumeric1, tag("_")))))).context("identifier").parse(i)
 }
 
-#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, TypeScriptify, TypescriptDefinition)]
+/// A MATPOWER case, the `mpc` struct.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, TypeScriptify)]
+#[cfg_attr(feature = "wasm", derive(TypescriptDefinition))]
 pub struct Case {
-  name: String,
-  version: Version,
-  base_mva: f64,
-  bus: Vec<Bus>,
-  gen: Vec<Gen>,
-  gencost: Vec<GenCost>,
-  branch: Vec<Branch>,
-  dcline: Vec<DcLine>,
-  bus_name: Vec<String>,
+  /// case name, from the function name or the file name
+  pub name: String,
+  /// MATPOWER case format version
+  pub version: Version,
+  /// system MVA base used for converting power into per unit quantities
+  pub base_mva: f64,
+  /// bus data
+  pub bus: Vec<Bus>,
+  /// generator data
+  pub gen: Vec<Gen>,
+  /// generator cost data, one row per generator for active power and optionally one more for reactive power
+  pub gencost: Vec<GenCost>,
+  /// branch data
+  pub branch: Vec<Branch>,
+  /// DC line data
+  pub dcline: Vec<DcLine>,
+  /// bus names, in the order of `bus`, or empty
+  pub bus_name: Vec<String>,
+  /// Fields an importer had no column for, by object type. Each entry holds the record's key fields and its unknown
+  /// fields, e.g. {"BusNum": "1", "SubNum": "7"}.
+  #[serde(default)]
+  pub extra: BTreeMap<String, Vec<BTreeMap<String, String>>>,
 }
 
 fn get_name(i: Span) -> PResult<String> {
@@ -807,14 +971,38 @@ fn get_base_mva(i: Span) -> PResult<f64> {
   preceded(ws(tag("=")), ws(float)).context("get_base_mva").parse(i)
 }
 
-fn get_bus(i: Span) -> PResult<Vec<Bus>> {
+// A row parser that also returns where the row is, from its first value to its `;`.
+fn located<'a, O>(
+  mut f: impl FnMut(Span<'a>) -> PResult<'a, O>,
+) -> impl FnMut(Span<'a>) -> PResult<'a, (SourceSpan, O)> {
+  move |i| {
+    let (i, _) = multispace0(i)?;
+    let (i, (row, o)) = consumed(&mut f)(i)?;
+    let len = row.fragment().find(';').map_or(row.fragment().len(), |n| n + 1);
+    Ok((i, (SourceSpan { line: row.location_line(), column: row.get_utf8_column(), len }, o)))
+  }
+}
+
+#[test]
+fn test_located() {
+  let (_, rows) = many1(located(gen))(
+    "\n\t1\t0\t0\t300\t-300\t1\t100\t1\t250\t10\t0\t0\t0\t0\t0\t0\t0\t0\t0\t0\t0;\n  2\t0\t0\t300\t-300\t1\t100\t1\t250\t10\t0\t0\t0\t0\t0\t0\t0\t0\t0\t0\t0; % two\n"
+      .into(),
+  )
+  .unwrap();
+  assert_eq!(rows[0].0, SourceSpan { line: 2, column: 2, len: 52 });
+  assert_eq!(rows[1].0, SourceSpan { line: 3, column: 3, len: 52 });
+  assert_eq!(rows[1].1.gen, BusId(2));
+}
+
+fn get_bus(i: Span) -> PResult<Vec<(SourceSpan, Bus)>> {
   let (i, _) = take_until1("mpc.bus").context("get_bus").parse(i)?;
   let (i, _) = tag("mpc.bus").context("get_bus").parse(i)?;
   preceded(
     ws(tag("=")),
     delimited(
       tuple((ws(tag("[")), opt(ws(comment)))),
-      fold_many1(bus, Vec::new, |mut acc: Vec<_>, item| {
+      fold_many1(located(bus), Vec::new, |mut acc: Vec<_>, item| {
         acc.push(item);
         acc
       }),
@@ -825,14 +1013,14 @@ fn get_bus(i: Span) -> PResult<Vec<Bus>> {
   .parse(i)
 }
 
-fn get_gen(i: Span) -> PResult<Vec<Gen>> {
+fn get_gen(i: Span) -> PResult<Vec<(SourceSpan, Gen)>> {
   let (i, _) = take_until1("mpc.gen").context("get_gen").parse(i)?;
   let (i, _) = tag("mpc.gen").context("get_gen").parse(i)?;
   preceded(
     ws(tag("=")),
     delimited(
       tuple((ws(tag("[")), opt(ws(comment)))),
-      fold_many1(gen, Vec::new, |mut acc: Vec<_>, item| {
+      fold_many1(located(gen), Vec::new, |mut acc: Vec<_>, item| {
         acc.push(item);
         acc
       }),
@@ -861,14 +1049,14 @@ fn get_gencost(i: Span) -> PResult<Vec<GenCost>> {
   .parse(i)
 }
 
-fn get_branch(i: Span) -> PResult<Vec<Branch>> {
+fn get_branch(i: Span) -> PResult<Vec<(SourceSpan, Branch)>> {
   let (i, _) = take_until1("mpc.branch").context("get_branch").parse(i)?;
   let (i, _) = tag("mpc.branch").context("get_branch").parse(i)?;
   preceded(
     ws(tag("=")),
     delimited(
       tuple((ws(tag("[")), opt(many0(ws(comment))))),
-      fold_many1(branch, Vec::new, |mut acc: Vec<_>, item| {
+      fold_many1(located(branch), Vec::new, |mut acc: Vec<_>, item| {
         acc.push(item);
         acc
       }),
@@ -879,14 +1067,14 @@ fn get_branch(i: Span) -> PResult<Vec<Branch>> {
   .parse(i)
 }
 
-fn get_dcline(i: Span) -> PResult<Vec<DcLine>> {
+fn get_dcline(i: Span) -> PResult<Vec<(SourceSpan, DcLine)>> {
   let (i, _) = take_until("mpc.dcline").context("get_dcline").parse(i)?;
   let (i, _) = tag("mpc.dcline").context("get_dcline").parse(i)?;
   preceded(
     ws(tag("=")),
     delimited(
       tuple((ws(tag("[")), opt(ws(comment)))),
-      fold_many1(dcline, Vec::new, |mut acc: Vec<_>, item| {
+      fold_many1(located(dcline), Vec::new, |mut acc: Vec<_>, item| {
         acc.push(item);
         acc
       }),
@@ -928,7 +1116,616 @@ mpc.bus_name = {
   assert_eq!(get_busname(data).unwrap().1.len(), 2);
 }
 
-fn _case(i: &str) -> PResult<Case> {
+// Numeric rows in MATPOWER column order, as stored by MATLAB itself (MAT-files, `jsonencode`)
+
+fn column(row: &[f64], i: usize) -> Result<f64> {
+  row.get(i).copied().ok_or_else(|| anyhow!("Expected at least {} columns, found {}", i + 1, row.len()))
+}
+
+fn index(v: f64) -> Result<usize> {
+  if v >= 0.0 && v.fract() == 0.0 {
+    Ok(v as usize)
+  } else {
+    Err(anyhow!("Expected a non-negative integer, found {}", v))
+  }
+}
+
+impl BusType {
+  pub(crate) fn from_f64(v: f64) -> Result<BusType> {
+    match v as i64 {
+      _ if v.fract() != 0.0 => Err(anyhow!("Invalid bus type {}", v)),
+      1 => Ok(BusType::PQ),
+      2 => Ok(BusType::PV),
+      3 => Ok(BusType::Ref),
+      4 => Ok(BusType::Isolated),
+      _ => Err(anyhow!("Invalid bus type {}", v)),
+    }
+  }
+}
+
+impl CostModel {
+  pub(crate) fn from_f64(v: f64) -> Result<CostModel> {
+    match v as i64 {
+      _ if v.fract() != 0.0 => Err(anyhow!("Invalid cost model {}", v)),
+      1 => Ok(CostModel::PiecewiseLinear),
+      2 => Ok(CostModel::Polynomial),
+      _ => Err(anyhow!("Invalid cost model {}", v)),
+    }
+  }
+}
+
+impl ServiceStatus {
+  pub(crate) fn from_f64(v: f64) -> Result<ServiceStatus> {
+    match v as i64 {
+      _ if v.fract() != 0.0 => Err(anyhow!("Invalid status {}", v)),
+      0 => Ok(ServiceStatus::OutOfService),
+      1 => Ok(ServiceStatus::InService),
+      _ => Err(anyhow!("Invalid status {}", v)),
+    }
+  }
+}
+
+impl std::str::FromStr for Version {
+  type Err = anyhow::Error;
+
+  fn from_str(s: &str) -> Result<Version> {
+    match s.trim().trim_matches('\'') {
+      "1" => Ok(Version::Version1),
+      "2" => Ok(Version::Version2),
+      s => Err(anyhow!("Invalid version {:?}", s)),
+    }
+  }
+}
+
+// Defaults for elements built from other formats, following MATPOWER's own defaults where it has them.
+
+impl Default for Bus {
+  fn default() -> Bus {
+    Bus {
+      idx: BusId(0),
+      bus_type: BusType::PQ,
+      pd: 0.0,
+      qd: 0.0,
+      shunt_conductance: 0.0,
+      shunt_susceptance: 0.0,
+      area: 1,
+      voltage_mag: 1.0,
+      voltage_ang: 0.0,
+      base_kv: 0.0,
+      zone: 1,
+      v_max: 1.1,
+      v_min: 0.9,
+      lam_p: None,
+      lam_q: None,
+      mu_vmax: None,
+      mu_vmin: None,
+      coords: None,
+    }
+  }
+}
+
+impl Default for Gen {
+  fn default() -> Gen {
+    Gen {
+      gen: BusId(0),
+      pg: 0.0,
+      qg: 0.0,
+      qmax: 0.0,
+      qmin: 0.0,
+      vg: 1.0,
+      mbase: 100.0,
+      gen_status: 1,
+      pmax: 0.0,
+      pmin: 0.0,
+      pc1: 0.0,
+      pc2: 0.0,
+      qc1min: 0.0,
+      qc1max: 0.0,
+      qc2min: 0.0,
+      qc2max: 0.0,
+      ramp_agc: 0.0,
+      ramp_10: 0.0,
+      ramp_30: 0.0,
+      ramp_q: 0.0,
+      apf: 0.0,
+      mu_pmax: None,
+      mu_pmin: None,
+      mu_qmax: None,
+      mu_qmin: None,
+    }
+  }
+}
+
+// A zero cost, for generators without one.
+impl Default for GenCost {
+  fn default() -> GenCost {
+    GenCost { model: CostModel::Polynomial, startup: 0.0, shutdown: 0.0, ncost: 1, cost: vec![0.0] }
+  }
+}
+
+impl Default for Branch {
+  fn default() -> Branch {
+    Branch {
+      f_bus: BusId(0),
+      t_bus: BusId(0),
+      br_r: 0.0,
+      br_x: 0.0,
+      br_b: 0.0,
+      rate_a: 0.0,
+      rate_b: 0.0,
+      rate_c: 0.0,
+      tap: 0.0,
+      shift: 0.0,
+      br_status: 1.0,
+      angmin: -360.0,
+      angmax: 360.0,
+      pf: None,
+      qf: None,
+      pt: None,
+      qt: None,
+      mu_sf: None,
+      mu_st: None,
+      mu_angmin: None,
+      mu_angmax: None,
+    }
+  }
+}
+
+impl Bus {
+  pub(crate) fn from_row(row: &[f64]) -> Result<Bus> {
+    Ok(Bus {
+      idx: BusId(index(column(row, 0)?)?),
+      bus_type: BusType::from_f64(column(row, 1)?)?,
+      pd: column(row, 2)?,
+      qd: column(row, 3)?,
+      shunt_conductance: column(row, 4)?,
+      shunt_susceptance: column(row, 5)?,
+      area: index(column(row, 6)?)?,
+      voltage_mag: column(row, 7)?,
+      voltage_ang: column(row, 8)?,
+      base_kv: column(row, 9)?,
+      zone: index(column(row, 10)?)?,
+      v_max: column(row, 11)?,
+      v_min: column(row, 12)?,
+      lam_p: row.get(13).copied(),
+      lam_q: row.get(14).copied(),
+      mu_vmax: row.get(15).copied(),
+      mu_vmin: row.get(16).copied(),
+      coords: None,
+    })
+  }
+}
+
+impl Gen {
+  // Version 1 cases stop after `pmin`; the capability curve, ramp and apf columns default to zero.
+  pub(crate) fn from_row(row: &[f64]) -> Result<Gen> {
+    let extra = |i: usize| row.get(i).copied().unwrap_or(0.0);
+    Ok(Gen {
+      gen: BusId(index(column(row, 0)?)?),
+      pg: column(row, 1)?,
+      qg: column(row, 2)?,
+      qmax: column(row, 3)?,
+      qmin: column(row, 4)?,
+      vg: column(row, 5)?,
+      mbase: column(row, 6)?,
+      gen_status: index(column(row, 7)?)?,
+      pmax: column(row, 8)?,
+      pmin: column(row, 9)?,
+      pc1: extra(10),
+      pc2: extra(11),
+      qc1min: extra(12),
+      qc1max: extra(13),
+      qc2min: extra(14),
+      qc2max: extra(15),
+      ramp_agc: extra(16),
+      ramp_10: extra(17),
+      ramp_30: extra(18),
+      ramp_q: extra(19),
+      apf: extra(20),
+      mu_pmax: row.get(21).copied(),
+      mu_pmin: row.get(22).copied(),
+      mu_qmax: row.get(23).copied(),
+      mu_qmin: row.get(24).copied(),
+    })
+  }
+}
+
+impl Branch {
+  // Version 1 cases have no angle difference limits.
+  pub(crate) fn from_row(row: &[f64]) -> Result<Branch> {
+    Ok(Branch {
+      f_bus: BusId(index(column(row, 0)?)?),
+      t_bus: BusId(index(column(row, 1)?)?),
+      br_r: column(row, 2)?,
+      br_x: column(row, 3)?,
+      br_b: column(row, 4)?,
+      rate_a: column(row, 5)?,
+      rate_b: column(row, 6)?,
+      rate_c: column(row, 7)?,
+      tap: column(row, 8)?,
+      shift: column(row, 9)?,
+      br_status: column(row, 10)?,
+      angmin: row.get(11).copied().unwrap_or(-360.0),
+      angmax: row.get(12).copied().unwrap_or(360.0),
+      pf: row.get(13).copied(),
+      qf: row.get(14).copied(),
+      pt: row.get(15).copied(),
+      qt: row.get(16).copied(),
+      mu_sf: row.get(17).copied(),
+      mu_st: row.get(18).copied(),
+      mu_angmin: row.get(19).copied(),
+      mu_angmax: row.get(20).copied(),
+    })
+  }
+}
+
+impl GenCost {
+  // Rows of a gencost matrix are padded with zeros up to the widest cost function.
+  pub(crate) fn from_row(row: &[f64]) -> Result<GenCost> {
+    let model = CostModel::from_f64(column(row, 0)?)?;
+    let ncost = index(column(row, 3)?)?;
+    let n = match model {
+      CostModel::Polynomial => ncost,
+      CostModel::PiecewiseLinear => ncost * 2,
+    };
+    if row.len() < 4 + n {
+      return Err(anyhow!("Expected {} cost values, found {}", n, row.len() - 4));
+    }
+    Ok(GenCost { model, startup: column(row, 1)?, shutdown: column(row, 2)?, ncost, cost: row[4..4 + n].to_vec() })
+  }
+}
+
+impl DcLine {
+  pub(crate) fn from_row(row: &[f64]) -> Result<DcLine> {
+    Ok(DcLine {
+      f_bus: BusId(index(column(row, 0)?)?),
+      t_bus: BusId(index(column(row, 1)?)?),
+      br_status: ServiceStatus::from_f64(column(row, 2)?)?,
+      pf: column(row, 3)?,
+      pt: column(row, 4)?,
+      qf: column(row, 5)?,
+      qt: column(row, 6)?,
+      vf: column(row, 7)?,
+      vt: column(row, 8)?,
+      pmin: column(row, 9)?,
+      pmax: column(row, 10)?,
+      qminf: column(row, 11)?,
+      qmaxf: column(row, 12)?,
+      qmint: column(row, 13)?,
+      qmaxt: column(row, 14)?,
+      loss0: column(row, 15)?,
+      loss1: column(row, 16)?,
+      mu_pmin: row.get(17).copied(),
+      mu_pmax: row.get(18).copied(),
+      mu_qminf: row.get(19).copied(),
+      mu_qmaxf: row.get(20).copied(),
+      mu_qmint: row.get(21).copied(),
+      mu_qmaxt: row.get(22).copied(),
+    })
+  }
+}
+
+// Column names as in MATPOWER's `idx_bus`, `idx_gen`, `idx_brch`, `idx_cost` and `idx_dcline`, with units
+
+pub(crate) const BUS_COLUMNS: [(&str, &str); 17] = [
+  ("BUS_I", ""),
+  ("BUS_TYPE", ""),
+  ("PD", "MW"),
+  ("QD", "MVAr"),
+  ("GS", "MW"),
+  ("BS", "MVAr"),
+  ("BUS_AREA", ""),
+  ("VM", "p.u."),
+  ("VA", "degrees"),
+  ("BASE_KV", "kV"),
+  ("ZONE", ""),
+  ("VMAX", "p.u."),
+  ("VMIN", "p.u."),
+  ("LAM_P", "u/MW"),
+  ("LAM_Q", "u/MVAr"),
+  ("MU_VMAX", "u/p.u."),
+  ("MU_VMIN", "u/p.u."),
+];
+
+pub(crate) const GEN_COLUMNS: [(&str, &str); 25] = [
+  ("GEN_BUS", ""),
+  ("PG", "MW"),
+  ("QG", "MVAr"),
+  ("QMAX", "MVAr"),
+  ("QMIN", "MVAr"),
+  ("VG", "p.u."),
+  ("MBASE", "MVA"),
+  ("GEN_STATUS", ""),
+  ("PMAX", "MW"),
+  ("PMIN", "MW"),
+  ("PC1", "MW"),
+  ("PC2", "MW"),
+  ("QC1MIN", "MVAr"),
+  ("QC1MAX", "MVAr"),
+  ("QC2MIN", "MVAr"),
+  ("QC2MAX", "MVAr"),
+  ("RAMP_AGC", "MW/min"),
+  ("RAMP_10", "MW"),
+  ("RAMP_30", "MW"),
+  ("RAMP_Q", "MVAr/min"),
+  ("APF", ""),
+  ("MU_PMAX", "u/MW"),
+  ("MU_PMIN", "u/MW"),
+  ("MU_QMAX", "u/MVAr"),
+  ("MU_QMIN", "u/MVAr"),
+];
+
+pub(crate) const BRANCH_COLUMNS: [(&str, &str); 21] = [
+  ("F_BUS", ""),
+  ("T_BUS", ""),
+  ("BR_R", "p.u."),
+  ("BR_X", "p.u."),
+  ("BR_B", "p.u."),
+  ("RATE_A", "MVA"),
+  ("RATE_B", "MVA"),
+  ("RATE_C", "MVA"),
+  ("TAP", ""),
+  ("SHIFT", "degrees"),
+  ("BR_STATUS", ""),
+  ("ANGMIN", "degrees"),
+  ("ANGMAX", "degrees"),
+  ("PF", "MW"),
+  ("QF", "MVAr"),
+  ("PT", "MW"),
+  ("QT", "MVAr"),
+  ("MU_SF", "u/MVA"),
+  ("MU_ST", "u/MVA"),
+  ("MU_ANGMIN", "u/degree"),
+  ("MU_ANGMAX", "u/degree"),
+];
+
+// Followed by NCOST coefficients, or NCOST (MW, u/h) pairs
+pub(crate) const GENCOST_COLUMNS: [(&str, &str); 4] = [("MODEL", ""), ("STARTUP", "u"), ("SHUTDOWN", "u"), ("NCOST", "")];
+
+pub(crate) const DCLINE_COLUMNS: [(&str, &str); 23] = [
+  ("F_BUS", ""),
+  ("T_BUS", ""),
+  ("BR_STATUS", ""),
+  ("PF", "MW"),
+  ("PT", "MW"),
+  ("QF", "MVAr"),
+  ("QT", "MVAr"),
+  ("VF", "p.u."),
+  ("VT", "p.u."),
+  ("PMIN", "MW"),
+  ("PMAX", "MW"),
+  ("QMINF", "MVAr"),
+  ("QMAXF", "MVAr"),
+  ("QMINT", "MVAr"),
+  ("QMAXT", "MVAr"),
+  ("LOSS0", "MW"),
+  ("LOSS1", "MW/MW"),
+  ("MU_PMIN", "u/MW"),
+  ("MU_PMAX", "u/MW"),
+  ("MU_QMINF", "u/MVAr"),
+  ("MU_QMAXF", "u/MVAr"),
+  ("MU_QMINT", "u/MVAr"),
+  ("MU_QMAXT", "u/MVAr"),
+];
+
+// Solution columns come in groups, each written only when all of its values are present and so is every group
+// before it, so that no group lands in the columns of another.
+fn solution(row: &mut Vec<f64>, groups: &[&[Option<f64>]]) {
+  for values in groups {
+    if !values.iter().all(|v| v.is_some()) {
+      return;
+    }
+    row.extend(values.iter().flatten());
+  }
+}
+
+impl Bus {
+  pub(crate) fn to_row(self) -> Vec<f64> {
+    let mut row = vec![
+      self.idx.0 as f64,
+      self.bus_type as i64 as f64,
+      self.pd,
+      self.qd,
+      self.shunt_conductance,
+      self.shunt_susceptance,
+      self.area as f64,
+      self.voltage_mag,
+      self.voltage_ang,
+      self.base_kv,
+      self.zone as f64,
+      self.v_max,
+      self.v_min,
+    ];
+    solution(&mut row, &[&[self.lam_p, self.lam_q, self.mu_vmax, self.mu_vmin]]);
+    row
+  }
+}
+
+impl Gen {
+  pub(crate) fn to_row(self) -> Vec<f64> {
+    let mut row = vec![
+      self.gen.0 as f64,
+      self.pg,
+      self.qg,
+      self.qmax,
+      self.qmin,
+      self.vg,
+      self.mbase,
+      self.gen_status as f64,
+      self.pmax,
+      self.pmin,
+      self.pc1,
+      self.pc2,
+      self.qc1min,
+      self.qc1max,
+      self.qc2min,
+      self.qc2max,
+      self.ramp_agc,
+      self.ramp_10,
+      self.ramp_30,
+      self.ramp_q,
+      self.apf,
+    ];
+    solution(&mut row, &[&[self.mu_pmax, self.mu_pmin, self.mu_qmax, self.mu_qmin]]);
+    row
+  }
+}
+
+impl Branch {
+  pub(crate) fn to_row(self) -> Vec<f64> {
+    let mut row = vec![
+      self.f_bus.0 as f64,
+      self.t_bus.0 as f64,
+      self.br_r,
+      self.br_x,
+      self.br_b,
+      self.rate_a,
+      self.rate_b,
+      self.rate_c,
+      self.tap,
+      self.shift,
+      self.br_status,
+      self.angmin,
+      self.angmax,
+    ];
+    let flows = [self.pf, self.qf, self.pt, self.qt];
+    solution(&mut row, &[&flows, &[self.mu_sf, self.mu_st, self.mu_angmin, self.mu_angmax]]);
+    row
+  }
+}
+
+impl GenCost {
+  pub(crate) fn to_row(&self) -> Vec<f64> {
+    let mut row = vec![self.model as i64 as f64, self.startup, self.shutdown, self.ncost as f64];
+    row.extend(self.cost.iter());
+    row
+  }
+}
+
+impl DcLine {
+  pub(crate) fn to_row(self) -> Vec<f64> {
+    let mut row = vec![
+      self.f_bus.0 as f64,
+      self.t_bus.0 as f64,
+      self.br_status as i64 as f64,
+      self.pf,
+      self.pt,
+      self.qf,
+      self.qt,
+      self.vf,
+      self.vt,
+      self.pmin,
+      self.pmax,
+      self.qminf,
+      self.qmaxf,
+      self.qmint,
+      self.qmaxt,
+      self.loss0,
+      self.loss1,
+    ];
+    solution(&mut row, &[&[self.mu_pmin, self.mu_pmax, self.mu_qminf, self.mu_qmaxf, self.mu_qmint, self.mu_qmaxt]]);
+    row
+  }
+}
+
+fn rows<T>(table: &str, rows: &[Vec<f64>], f: fn(&[f64]) -> Result<T>) -> Result<Vec<T>> {
+  rows.iter().enumerate().map(|(i, row)| f(row).map_err(|e| anyhow!("Invalid {} row {}: {}", table, i + 1, e))).collect()
+}
+
+/// The numeric tables of a case, one `Vec<f64>` per row in MATPOWER column order.
+#[derive(Debug, Default, Clone)]
+pub(crate) struct Tables {
+  pub(crate) bus: Vec<Vec<f64>>,
+  pub(crate) gen: Vec<Vec<f64>>,
+  pub(crate) branch: Vec<Vec<f64>>,
+  pub(crate) gencost: Vec<Vec<f64>>,
+  pub(crate) dcline: Vec<Vec<f64>>,
+}
+
+impl Case {
+  pub(crate) fn from_tables(
+    name: String,
+    version: Version,
+    base_mva: f64,
+    tables: &Tables,
+    bus_name: Vec<String>,
+  ) -> Result<Case> {
+    let case = Case {
+      name,
+      version,
+      base_mva,
+      bus: rows("bus", &tables.bus, Bus::from_row)?,
+      gen: rows("gen", &tables.gen, Gen::from_row)?,
+      gencost: rows("gencost", &tables.gencost, GenCost::from_row)?,
+      branch: rows("branch", &tables.branch, Branch::from_row)?,
+      dcline: rows("dcline", &tables.dcline, DcLine::from_row)?,
+      bus_name,
+      extra: BTreeMap::new(),
+    };
+    Ok(case)
+  }
+
+  /// The numeric tables in MATPOWER column order, with gencost rows padded with zeros to the widest cost function.
+  pub(crate) fn to_tables(&self) -> Tables {
+    let mut gencost = self.gencost.iter().map(GenCost::to_row).collect::<Vec<_>>();
+    let width = gencost.iter().map(Vec::len).max().unwrap_or(0);
+    gencost.iter_mut().for_each(|row| row.resize(width, 0.0));
+    Tables {
+      bus: self.bus.iter().map(|r| r.to_row()).collect(),
+      gen: self.gen.iter().map(|r| r.to_row()).collect(),
+      branch: self.branch.iter().map(|r| r.to_row()).collect(),
+      gencost,
+      dcline: self.dcline.iter().map(|r| r.to_row()).collect(),
+    }
+  }
+}
+
+#[test]
+fn test_from_tables() {
+  let tables = Tables {
+    bus: vec![
+      vec![1.0, 3.0, 0.0, 0.0, 0.0, 0.0, 1.0, 1.0, 0.0, 230.0, 1.0, 1.1, 0.9],
+      vec![2.0, 1.0, 90.0, 30.0, 0.0, 0.0, 1.0, 1.0, 0.0, 230.0, 1.0, 1.1, 0.9],
+    ],
+    gen: vec![vec![1.0, 0.0, 0.0, 300.0, -300.0, 1.0, 100.0, 1.0, 250.0, 10.0]],
+    branch: vec![vec![1.0, 2.0, 0.0, 0.0576, 0.0, 250.0, 250.0, 250.0, 0.0, 0.0, 1.0]],
+    gencost: vec![vec![2.0, 0.0, 0.0, 2.0, 16.242, 880.2, 0.0], vec![1.0, 0.0, 0.0, 2.0, 0.0, 0.0, 100.0, 2000.0]],
+    dcline: vec![],
+  };
+  let c = Case::from_tables("case".to_string(), Version::Version2, 100.0, &tables, vec![]).unwrap();
+  assert_eq!(c.bus[0].bus_type, BusType::Ref);
+  assert_eq!(c.gen[0].pmin, 10.0);
+  assert_eq!(c.gen[0].apf, 0.0);
+  assert_eq!(c.branch[0].angmax, 360.0);
+  assert_eq!(c.gencost[0].cost, vec![16.242, 880.2]);
+  assert_eq!(c.gencost[1].cost, vec![0.0, 0.0, 100.0, 2000.0]);
+
+  let mut round_trip = c.to_tables();
+  round_trip.gen[0].truncate(10);
+  round_trip.branch[0].truncate(11);
+  assert_eq!(round_trip.bus, tables.bus);
+  assert_eq!(round_trip.gen, tables.gen);
+  assert_eq!(round_trip.branch, tables.branch);
+  assert_eq!(round_trip.gencost[0][..7], tables.gencost[0][..]);
+  assert_eq!(round_trip.gencost[1], tables.gencost[1]);
+
+  // Shadow prices without flows are not written where the flows would go.
+  let branch = Branch { mu_sf: Some(1.0), mu_st: Some(2.0), mu_angmin: Some(0.0), mu_angmax: Some(0.0), ..c.branch[0] };
+  assert_eq!(branch.to_row().len(), 13);
+  let branch = Branch { pf: Some(10.0), qf: Some(1.0), pt: Some(-10.0), qt: Some(-1.0), ..branch };
+  assert_eq!(Branch::from_row(&branch.to_row()).unwrap(), branch);
+
+  let tables = Tables { bus: vec![vec![1.0, 5.0]], ..Tables::default() };
+  let e = Case::from_tables("case".to_string(), Version::Version2, 100.0, &tables, vec![]).unwrap_err();
+  assert_eq!(e.to_string(), "Invalid bus row 1: Invalid bus type 5");
+
+  // Dangling references are left for the caller to report.
+  let tables = Tables { gen: vec![vec![1.0, 0.0, 0.0, 300.0, -300.0, 1.0, 100.0, 1.0, 250.0, 10.0]], ..Tables::default() };
+  let c = Case::from_tables("case".to_string(), Version::Version2, 100.0, &tables, vec![]).unwrap();
+  assert_eq!(c.dangling_references()[0].to_string(), "mpc.gen row 1 refers to bus 1, which is not in mpc.bus");
+}
+
+fn _case(i: &str) -> PResult<(Case, RowSpans)> {
   let i = Span::from(i);
   let (_, name) = get_name(i)?;
   let (_, version) = get_version(i)?;
@@ -939,10 +1736,19 @@ fn _case(i: &str) -> PResult<Case> {
   let (_, gencost) = get_gencost(i).or_else(|_| Ok(("".into(), vec![])))?;
   let (_, dcline) = get_dcline(i).or_else(|_| Ok(("".into(), vec![])))?;
   let (_, bus_name) = get_busname(i).or_else(|_| Ok(("".into(), vec![])))?;
-  Ok(("".into(), Case { name, version, base_mva, bus, gen, gencost, branch, dcline, bus_name }))
+  let (bus_spans, bus) = bus.into_iter().unzip();
+  let (gen_spans, gen) = gen.into_iter().unzip();
+  let (branch_spans, branch) = branch.into_iter().unzip();
+  let (dcline_spans, dcline) = dcline.into_iter().unzip();
+  let spans = RowSpans { bus: bus_spans, gen: gen_spans, branch: branch_spans, dcline: dcline_spans };
+  Ok((
+    "".into(),
+    (Case { name, version, base_mva, bus, gen, gencost, branch, dcline, bus_name, extra: BTreeMap::new() }, spans),
+  ))
 }
 
-pub fn case(i: &str) -> Result<Case> {
+// Parses a case without checking its bus references, with the spans of its rows.
+pub(crate) fn case_with_spans(i: &str) -> Result<(Case, RowSpans)> {
   let r = _case(i);
   if r.is_err() {
     let mut s = String::new();
@@ -958,6 +1764,18 @@ pub fn case(i: &str) -> Result<Case> {
   }
 }
 
+/// Parse a case from the text of a MATPOWER `.m` file. Bus references are not checked; see `case_with_references`.
+pub fn case(i: &str) -> Result<Case> {
+  case_with_spans(i).map(|(case, _)| case)
+}
+
+/// Parse a case as `case`, with the rows that refer to bus numbers not in `mpc.bus` and where they are in the text.
+pub fn case_with_references(i: &str) -> Result<(Case, Vec<DanglingReference>)> {
+  let (case, spans) = case_with_spans(i)?;
+  let dangling = BusIndex::first_rows(&case.bus).dangling_with_spans(&case, &spans);
+  Ok((case, dangling))
+}
+
 #[test]
 fn test_case() {
   let entries = std::fs::read_dir("../../matpower/data/").unwrap().map(|res| res.map(|e| e.path())).collect::<Vec<_>>();
@@ -973,8 +1791,149 @@ fn test_case() {
   }
 }
 
+impl Gen {
+  /// True if the generator is in service.
+  pub fn in_service(&self) -> bool {
+    self.gen_status > 0
+  }
+}
+
+impl Branch {
+  /// True if the branch is in service.
+  pub fn in_service(&self) -> bool {
+    self.br_status > 0.0
+  }
+}
+
+impl DcLine {
+  /// True if the DC line is in service.
+  pub fn in_service(&self) -> bool {
+    self.br_status == ServiceStatus::InService
+  }
+}
+
+impl Case {
+  /// Buses in table order.
+  pub fn buses(&self) -> impl Iterator<Item = &Bus> {
+    self.bus.iter()
+  }
+
+  /// Generators in table order.
+  pub fn gens(&self) -> impl Iterator<Item = &Gen> {
+    self.gen.iter()
+  }
+
+  /// Branches in table order.
+  pub fn branches(&self) -> impl Iterator<Item = &Branch> {
+    self.branch.iter()
+  }
+
+  /// The bus numbered `bus_id`, if there is one.
+  pub fn bus(&self, bus_id: impl Into<BusId>) -> Option<&Bus> {
+    let bus_id = bus_id.into();
+    self.bus.iter().find(|b| b.idx == bus_id)
+  }
+
+  /// Name of the bus numbered `bus_id`, when the case has bus names.
+  pub fn bus_name_of(&self, bus_id: impl Into<BusId>) -> Option<&str> {
+    let bus_id = bus_id.into();
+    self.bus.iter().position(|b| b.idx == bus_id).and_then(|i| self.bus_name.get(i)).map(String::as_str)
+  }
+
+  /// Generators that are in service.
+  pub fn in_service_gens(&self) -> impl Iterator<Item = &Gen> {
+    self.gen.iter().filter(|g| g.in_service())
+  }
+
+  /// Branches that are in service.
+  pub fn in_service_branches(&self) -> impl Iterator<Item = &Branch> {
+    self.branch.iter().filter(|b| b.in_service())
+  }
+
+  /// Generators connected to bus `bus_id`, in or out of service.
+  pub fn gens_at(&self, bus_id: impl Into<BusId>) -> impl Iterator<Item = &Gen> {
+    let bus_id = bus_id.into();
+    self.gen.iter().filter(move |g| g.gen == bus_id)
+  }
+
+  /// Branches with either end at bus `bus_id`, in or out of service.
+  pub fn branches_at(&self, bus_id: impl Into<BusId>) -> impl Iterator<Item = &Branch> {
+    let bus_id = bus_id.into();
+    self.branch.iter().filter(move |b| b.f_bus == bus_id || b.t_bus == bus_id)
+  }
+}
+
+#[test]
+fn test_dangling_references() {
+  let (c, dangling) = case_with_references(
+    r#"function mpc = case2
+mpc.version = '2';
+mpc.baseMVA = 100;
+mpc.bus = [
+	1	3	0	0	0	0	1	1	0	230	1	1.1	0.9;
+	2	1	90	30	0	0	1	1	0	230	1	1.1	0.9;
+];
+mpc.gen = [
+	1	0	0	300	-300	1	100	1	250	10	0	0	0	0	0	0	0	0	0	0	0;
+	7	0	0	300	-300	1	100	1	250	10	0	0	0	0	0	0	0	0	0	0	0;
+];
+mpc.branch = [
+	1	2	0.01	0.1	0.2	250	250	300	0	0	1	-360	360;
+	2	9999	0.01	0.1	0.2	250	250	300	0	0	1	-360	360;
+];
+"#,
+  )
+  .unwrap();
+  assert_eq!((c.gen.len(), c.branch.len()), (2, 2));
+  assert_eq!(
+    dangling.iter().map(ToString::to_string).collect::<Vec<_>>(),
+    vec![
+      "mpc.gen row 2 refers to bus 7, which is not in mpc.bus (line 10, column 2)",
+      "mpc.branch row 2 refers to bus 9999, which is not in mpc.bus (line 14, column 2)",
+    ]
+  );
+}
+
+#[test]
+fn test_iterators() {
+  let c = case(
+    r#"function mpc = case3
+mpc.version = '2';
+mpc.baseMVA = 100;
+mpc.bus = [
+	1	3	0	0	0	0	1	1	0	230	1	1.1	0.9;
+	2	2	90	30	0	0	1	1	0	230	1	1.1	0.9;
+	3	1	100	35	0	0	1	1	0	230	1	1.1	0.9;
+];
+mpc.gen = [
+	1	0	0	300	-300	1	100	1	250	10	0	0	0	0	0	0	0	0	0	0	0;
+	2	163	0	300	-300	1	100	1	300	10	0	0	0	0	0	0	0	0	0	0	0;
+	2	10	0	300	-300	1	100	0	300	10	0	0	0	0	0	0	0	0	0	0	0;
+];
+mpc.branch = [
+	1	2	0.01	0.1	0.2	250	250	300	0	0	1	-360	360;
+	2	3	0.01	0.1	0.2	250	250	300	0	0	0	-360	360;
+];
+mpc.bus_name = {
+	'One';
+	'Two';
+	'Three';
+};
+"#,
+  )
+  .unwrap();
+  assert_eq!(c.buses().map(|b| b.idx.0).collect::<Vec<_>>(), vec![1, 2, 3]);
+  assert_eq!(c.bus(3).map(|b| b.pd), Some(100.0));
+  assert!(c.bus(4).is_none());
+  assert_eq!(c.bus_name_of(2), Some("Two"));
+  assert_eq!(c.gens_at(2).map(|g| g.pg).collect::<Vec<_>>(), vec![163.0, 10.0]);
+  assert_eq!(c.in_service_gens().count(), 2);
+  assert_eq!(c.in_service_branches().map(|b| b.t_bus).collect::<Vec<_>>(), vec![BusId(2)]);
+  assert_eq!(c.branches_at(2).count(), 2);
+}
+
 #[test]
 fn test_typescript() {
-  use typescript_definitions::{TypeScriptify, TypeScriptifyTrait, TypescriptDefinition};
+  use typescript_definitions::{TypeScriptify, TypeScriptifyTrait};
   println!("{}", Bus::type_script_ify());
 }
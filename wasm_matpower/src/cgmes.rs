@@ -0,0 +1,586 @@
+// CGMES (CIM RDF/XML) import
+
+// Reads the equipment (EQ), topology (TP) and steady state hypothesis (SSH) profiles, and bus voltages from the
+// state variables (SV) profile when present. Profiles come as separate RDF/XML documents, usually zipped, that
+// describe the same objects by id; they are merged into one model before building the case.
+//
+// TopologicalNode    -> Bus
+// ACLineSegment      -> Branch
+// PowerTransformer   -> Branch (three winding transformers get a star bus and three branches)
+// SynchronousMachine -> Gen
+// EnergyConsumer     -> Bus pd, qd
+// LinearShuntCompensator -> Bus gs, bs
+//
+// Every other class with data of its own is counted in `Import::skipped`.
+
+use std::{
+  collections::{BTreeMap, HashMap},
+  io::{Cursor, Read},
+};
+
+use anyhow::{anyhow, Result};
+use serde::Serialize;
+
+use crate::{
+  case::{Branch, Bus, BusId, BusType, Case, Gen, Version},
+  units::Base,
+};
+
+const RDF_NS: &str = "http://www.w3.org/1999/02/22-rdf-syntax-ns#";
+
+const BASE_MVA: f64 = 100.0;
+
+// Classes that are converted, or only hold structure the conversion follows.
+const HANDLED: &[&str] = &[
+  "TopologicalNode",
+  "ConnectivityNode",
+  "Terminal",
+  "BaseVoltage",
+  "VoltageLevel",
+  "Substation",
+  "SubGeographicalRegion",
+  "GeographicalRegion",
+  "Line",
+  "ACLineSegment",
+  "PowerTransformer",
+  "PowerTransformerEnd",
+  "RatioTapChanger",
+  "SynchronousMachine",
+  "GeneratingUnit",
+  "ThermalGeneratingUnit",
+  "HydroGeneratingUnit",
+  "NuclearGeneratingUnit",
+  "WindGeneratingUnit",
+  "SolarGeneratingUnit",
+  "RegulatingControl",
+  "EnergyConsumer",
+  "ConformLoad",
+  "NonConformLoad",
+  "LinearShuntCompensator",
+  "SvVoltage",
+];
+
+const LOADS: &[&str] = &["EnergyConsumer", "ConformLoad", "NonConformLoad"];
+
+#[derive(Debug, Default)]
+struct Object {
+  class: String,
+  props: HashMap<String, String>, // property name without the class prefix -> text, or id for references
+}
+
+impl Object {
+  fn text(&self, p: &str) -> Option<&str> {
+    self.props.get(p).map(|s| s.as_str())
+  }
+
+  fn f64(&self, p: &str) -> Option<f64> {
+    self.text(p).and_then(|s| s.trim().parse().ok())
+  }
+
+  fn bool(&self, p: &str) -> Option<bool> {
+    self.text(p).map(|s| s.trim() == "true")
+  }
+}
+
+// `rdf:ID="_abc"`, `rdf:about="#_abc"` and `rdf:resource="#_abc"` all name the object `abc`; CGMES 3 uses
+// `urn:uuid:abc` instead.
+fn id(s: &str) -> String {
+  let s = s.trim_start_matches('#');
+  let s = s.strip_prefix("urn:uuid:").unwrap_or(s);
+  s.trim_start_matches('_').to_string()
+}
+
+#[test]
+fn test_id() {
+  assert_eq!(id("_a1"), "a1");
+  assert_eq!(id("#_a1"), "a1");
+  assert_eq!(id("urn:uuid:a1"), "a1");
+}
+
+#[derive(Debug, Default)]
+struct Model {
+  objects: Vec<(String, Object)>,
+  ids: HashMap<String, usize>,
+}
+
+impl Model {
+  fn add(&mut self, xml: &str) -> Result<()> {
+    let doc = roxmltree::Document::parse(xml)?;
+    for node in doc.root_element().children().filter(|n| n.is_element()) {
+      let class = node.tag_name().name();
+      let about = node.attribute((RDF_NS, "ID")).or_else(|| node.attribute((RDF_NS, "about")));
+      let about = match about {
+        Some(about) if class != "FullModel" => id(about),
+        _ => continue,
+      };
+      let i = match self.ids.get(&about) {
+        Some(i) => *i,
+        None => {
+          self.objects.push((about.clone(), Object::default()));
+          self.ids.insert(about, self.objects.len() - 1);
+          self.objects.len() - 1
+        },
+      };
+      let object = &mut self.objects[i].1;
+      if object.class.is_empty() {
+        object.class = class.to_string();
+      }
+      for p in node.children().filter(|n| n.is_element()) {
+        let name = p.tag_name().name();
+        let name = name.rsplit('.').next().unwrap_or(name).to_string();
+        let value = match p.attribute((RDF_NS, "resource")) {
+          Some(r) => id(r),
+          None => p.text().unwrap_or("").trim().to_string(),
+        };
+        object.props.insert(name, value);
+      }
+    }
+    Ok(())
+  }
+
+  fn get(&self, id: &str) -> Option<&Object> {
+    self.ids.get(id).map(|i| &self.objects[*i].1)
+  }
+
+  fn of<'a>(&'a self, classes: &'a [&str]) -> impl Iterator<Item = (&'a str, &'a Object)> + 'a {
+    self.objects.iter().filter(move |(_, o)| classes.contains(&o.class.as_str())).map(|(id, o)| (id.as_str(), o))
+  }
+
+  fn follow(&self, o: &Object, p: &str) -> Option<&Object> {
+    o.text(p).and_then(|id| self.get(id))
+  }
+
+  fn nominal_voltage(&self, o: &Object) -> Option<f64> {
+    self.follow(o, "BaseVoltage").and_then(|bv| bv.f64("nominalVoltage"))
+  }
+}
+
+/// The result of a CGMES import: the case, and how many objects of each CIM class had no equivalent in it.
+#[derive(Serialize, Debug)]
+pub struct Import {
+  pub case: Case,
+  pub skipped: BTreeMap<String, usize>,
+}
+
+struct TerminalInfo {
+  sequence: f64,
+  bus: Option<usize>,
+  connected: bool,
+}
+
+fn build(model: &Model, name: &str) -> Result<Import> {
+  let mut case = Case {
+    name: name.to_string(),
+    version: Version::Version2,
+    base_mva: BASE_MVA,
+    bus: vec![],
+    gen: vec![],
+    gencost: vec![],
+    branch: vec![],
+    dcline: vec![],
+    bus_name: vec![],
+    extra: Default::default(),
+  };
+
+  // Buses
+  let mut buses = HashMap::new();
+  for (id, tn) in model.of(&["TopologicalNode"]) {
+    let base_kv = model
+      .nominal_voltage(tn)
+      .or_else(|| model.follow(tn, "ConnectivityNodeContainer").and_then(|vl| model.nominal_voltage(vl)))
+      .unwrap_or(0.0);
+    buses.insert(id, case.bus.len());
+    case.bus.push(Bus { idx: BusId(case.bus.len() + 1), base_kv, ..Bus::default() });
+    case.bus_name.push(tn.text("name").unwrap_or(id).to_string());
+  }
+  if case.bus.is_empty() {
+    return Err(anyhow!("No TopologicalNode found, is the TP profile missing?"));
+  }
+  for (_, sv) in model.of(&["SvVoltage"]) {
+    if let Some(&b) = sv.text("TopologicalNode").and_then(|tn| buses.get(tn)) {
+      let bus = &mut case.bus[b];
+      if let (Some(v), true) = (sv.f64("v"), bus.base_kv > 0.0) {
+        bus.voltage_mag = v / bus.base_kv;
+      }
+      bus.voltage_ang = sv.f64("angle").unwrap_or(0.0);
+    }
+  }
+
+  // Terminals of each piece of conducting equipment, in sequence order
+  let mut terminals: HashMap<&str, Vec<TerminalInfo>> = HashMap::new();
+  for (_, t) in model.of(&["Terminal"]) {
+    let ce = match t.text("ConductingEquipment") {
+      Some(ce) => ce,
+      None => continue,
+    };
+    let tn = t.text("TopologicalNode").or_else(|| model.follow(t, "ConnectivityNode").and_then(|cn| cn.text("TopologicalNode")));
+    let info = TerminalInfo {
+      sequence: t.f64("sequenceNumber").unwrap_or(0.0),
+      bus: tn.and_then(|tn| buses.get(tn).copied()),
+      connected: t.bool("connected").unwrap_or(true),
+    };
+    terminals.entry(ce).or_default().push(info);
+  }
+  for v in terminals.values_mut() {
+    v.sort_by(|a, b| a.sequence.partial_cmp(&b.sequence).unwrap_or(std::cmp::Ordering::Equal));
+  }
+  let terminal = |ce: &str, n: usize| -> Option<&TerminalInfo> { terminals.get(ce).and_then(|v| v.get(n)) };
+  let in_service = |ce: &str, o: &Object| -> bool {
+    o.bool("inService").unwrap_or(true) && terminals.get(ce).is_some_and(|v| v.iter().all(|t| t.connected))
+  };
+
+  // Lines
+  for (id, line) in model.of(&["ACLineSegment"]) {
+    let (f, t) = match (terminal(id, 0).and_then(|t| t.bus), terminal(id, 1).and_then(|t| t.bus)) {
+      (Some(f), Some(t)) => (f, t),
+      _ => return Err(anyhow!("ACLineSegment {:?} is not connected to two topological nodes", id)),
+    };
+    let kv = model.nominal_voltage(line).unwrap_or(case.bus[f].base_kv);
+    if kv <= 0.0 {
+      return Err(anyhow!("No base voltage for ACLineSegment {:?}", id));
+    }
+    let z = Base::new(BASE_MVA, kv).z();
+    case.branch.push(Branch {
+      f_bus: BusId(f + 1),
+      t_bus: BusId(t + 1),
+      br_r: line.f64("r").unwrap_or(0.0) / z,
+      br_x: line.f64("x").unwrap_or(0.0) / z,
+      br_b: line.f64("bch").unwrap_or(0.0) * z,
+      br_status: if in_service(id, line) { 1.0 } else { 0.0 },
+      ..Branch::default()
+    });
+  }
+
+  // Transformers
+  let mut ends: HashMap<&str, Vec<(&str, &Object)>> = HashMap::new();
+  for (id, end) in model.of(&["PowerTransformerEnd"]) {
+    if let Some(pt) = end.text("PowerTransformer") {
+      ends.entry(pt).or_default().push((id, end));
+    }
+  }
+  let mut ratios: HashMap<&str, f64> = HashMap::new();
+  for (_, rtc) in model.of(&["RatioTapChanger"]) {
+    if let Some(end) = rtc.text("TransformerEnd") {
+      let step = rtc.f64("step").or_else(|| rtc.f64("normalStep")).unwrap_or(0.0);
+      let neutral = rtc.f64("neutralStep").unwrap_or(step);
+      ratios.insert(end, 1.0 + (step - neutral) * rtc.f64("stepVoltageIncrement").unwrap_or(0.0) / 100.0);
+    }
+  }
+  for (id, pt) in model.of(&["PowerTransformer"]) {
+    let mut ends = ends.remove(id).unwrap_or_default();
+    ends.sort_by_key(|(_, e)| e.f64("endNumber").unwrap_or(0.0) as i64);
+    let status = if in_service(id, pt) { 1.0 } else { 0.0 };
+    let mut windings = vec![];
+    for (end_id, end) in ends.iter() {
+      let terminal = end.text("Terminal").and_then(|t| model.get(t));
+      let bus = terminal.and_then(|t| t.text("TopologicalNode")).and_then(|tn| buses.get(tn).copied());
+      let bus = bus.or_else(|| {
+        let cn = terminal.and_then(|t| model.follow(t, "ConnectivityNode"));
+        cn.and_then(|cn| cn.text("TopologicalNode")).and_then(|tn| buses.get(tn).copied())
+      });
+      let bus = bus.ok_or_else(|| anyhow!("PowerTransformerEnd {:?} is not connected to a topological node", end_id))?;
+      let rated_u = end.f64("ratedU").filter(|u| *u > 0.0).unwrap_or(case.bus[bus].base_kv);
+      if rated_u <= 0.0 {
+        return Err(anyhow!("No rated voltage for PowerTransformerEnd {:?}", end_id));
+      }
+      let ratio = ratios.get(end_id).copied().unwrap_or(1.0);
+      let kv = if case.bus[bus].base_kv > 0.0 { case.bus[bus].base_kv } else { rated_u };
+      let (r, x, b) = (end.f64("r").unwrap_or(0.0), end.f64("x").unwrap_or(0.0), end.f64("b").unwrap_or(0.0));
+      windings.push((bus, kv, rated_u, ratio, r, x, b));
+    }
+    match windings.as_slice() {
+      [(f, kv_f, u_f, ratio_f, r_f, x_f, b_f), (t, kv_t, u_t, ratio_t, r_t, x_t, b_t)] => {
+        // Impedances referred to the "to" side, which is where MATPOWER puts the series impedance.
+        let n2 = (u_t / u_f).powi(2);
+        let (r, x, b) = (r_f * n2 + r_t, x_f * n2 + x_t, b_f / n2 + b_t);
+        let z = Base::new(BASE_MVA, *kv_t).z();
+        case.branch.push(Branch {
+          f_bus: BusId(f + 1),
+          t_bus: BusId(t + 1),
+          br_r: r / z,
+          br_x: x / z,
+          br_b: b * z,
+          tap: (u_f * ratio_f / kv_f) / (u_t * ratio_t / kv_t),
+          br_status: status,
+          ..Branch::default()
+        });
+      },
+      [(_, _, u_star, ..), _, _] => {
+        let star = case.bus.len();
+        case.bus.push(Bus { idx: BusId(star + 1), base_kv: *u_star, ..Bus::default() });
+        case.bus_name.push(format!("{} star", pt.text("name").unwrap_or(id)));
+        let z = Base::new(BASE_MVA, *u_star).z();
+        for (f, kv_f, u_f, ratio_f, r_f, x_f, b_f) in windings.iter() {
+          let n2 = (u_star / u_f).powi(2);
+          case.branch.push(Branch {
+            f_bus: BusId(f + 1),
+            t_bus: BusId(star + 1),
+            br_r: r_f * n2 / z,
+            br_x: x_f * n2 / z,
+            br_b: b_f / n2 * z,
+            tap: u_f * ratio_f / kv_f,
+            br_status: status,
+            ..Branch::default()
+          });
+        }
+      },
+      _ => return Err(anyhow!("PowerTransformer {:?} has {} ends, expected 2 or 3", id, windings.len())),
+    }
+  }
+
+  // Loads and shunts
+  for (id, load) in model.of(LOADS) {
+    if let Some(b) = terminal(id, 0).and_then(|t| t.bus).filter(|_| in_service(id, load)) {
+      case.bus[b].pd += load.f64("p").unwrap_or(0.0);
+      case.bus[b].qd += load.f64("q").unwrap_or(0.0);
+    }
+  }
+  for (id, shunt) in model.of(&["LinearShuntCompensator"]) {
+    if let Some(b) = terminal(id, 0).and_then(|t| t.bus).filter(|_| in_service(id, shunt)) {
+      let sections = shunt.f64("sections").or_else(|| shunt.f64("normalSections")).unwrap_or(0.0);
+      let kv = if case.bus[b].base_kv > 0.0 { case.bus[b].base_kv } else { shunt.f64("nomU").unwrap_or(0.0) };
+      case.bus[b].shunt_conductance += shunt.f64("gPerSection").unwrap_or(0.0) * sections * kv * kv;
+      case.bus[b].shunt_susceptance += shunt.f64("bPerSection").unwrap_or(0.0) * sections * kv * kv;
+    }
+  }
+
+  // Generators, with CGMES' load sign convention on p and q
+  let mut reference: Option<((u8, f64, f64), usize)> = None;
+  for (id, sm) in model.of(&["SynchronousMachine"]) {
+    let b = match terminal(id, 0).and_then(|t| t.bus) {
+      Some(b) => b,
+      None => return Err(anyhow!("SynchronousMachine {:?} is not connected to a topological node", id)),
+    };
+    let unit = model.follow(sm, "GeneratingUnit");
+    let target = model.follow(sm, "RegulatingControl").and_then(|rc| rc.f64("targetValue"));
+    let status = in_service(id, sm);
+    let gen = Gen {
+      gen: BusId(b + 1),
+      pg: -sm.f64("p").unwrap_or(0.0),
+      qg: -sm.f64("q").unwrap_or(0.0),
+      qmax: sm.f64("maxQ").unwrap_or(9999.0),
+      qmin: sm.f64("minQ").unwrap_or(-9999.0),
+      vg: match target {
+        Some(kv) if case.bus[b].base_kv > 0.0 => kv / case.bus[b].base_kv,
+        _ => case.bus[b].voltage_mag,
+      },
+      mbase: sm.f64("ratedS").filter(|s| *s > 0.0).unwrap_or(BASE_MVA),
+      gen_status: if status { 1 } else { 0 },
+      pmax: unit.and_then(|u| u.f64("maxOperatingP")).unwrap_or(0.0),
+      pmin: unit.and_then(|u| u.f64("minOperatingP")).unwrap_or(0.0),
+      ..Gen::default()
+    };
+    if status {
+      if case.bus[b].bus_type == BusType::PQ {
+        case.bus[b].bus_type = BusType::PV;
+      }
+      // The lowest positive reference priority wins; without any, the largest unit does.
+      let key = match sm.f64("referencePriority").filter(|p| *p > 0.0) {
+        Some(p) => (0, p, 0.0),
+        None => (1, 0.0, -gen.pmax),
+      };
+      if reference.is_none_or(|(k, _)| key < k) {
+        reference = Some((key, b));
+      }
+    }
+    case.gen.push(gen);
+  }
+  if let Some((_, b)) = reference {
+    case.bus[b].bus_type = BusType::Ref;
+  }
+
+  let mut skipped = BTreeMap::new();
+  for (_, o) in model.objects.iter() {
+    if !o.class.is_empty() && !HANDLED.contains(&o.class.as_str()) {
+      *skipped.entry(o.class.clone()).or_insert(0) += 1;
+    }
+  }
+  Ok(Import { case, skipped })
+}
+
+/// Build a case from CGMES profile documents (RDF/XML), in any order.
+pub fn case(documents: &[&str], name: &str) -> Result<Import> {
+  let mut model = Model::default();
+  for d in documents {
+    model.add(d)?;
+  }
+  build(&model, name)
+}
+
+fn unzip(b: &[u8], documents: &mut Vec<String>) -> Result<()> {
+  let mut archive = zip::ZipArchive::new(Cursor::new(b))?;
+  for i in 0..archive.len() {
+    let mut file = archive.by_index(i)?;
+    let name = file.name().to_lowercase();
+    let mut data = vec![];
+    file.read_to_end(&mut data)?;
+    // Profiles are often zipped individually inside the model zip.
+    if name.ends_with(".zip") {
+      unzip(&data, documents)?;
+    } else if name.ends_with(".xml") {
+      documents.push(String::from_utf8(data)?);
+    }
+  }
+  Ok(())
+}
+
+/// True if `b` looks like a zip archive.
+pub fn is_zip(b: &[u8]) -> bool {
+  b.starts_with(b"PK\x03\x04")
+}
+
+/// True if `s` looks like a CIM RDF/XML document.
+pub fn is_cim(s: &str) -> bool {
+  s.contains("rdf:RDF") && s.contains("http://iec.ch/TC57/")
+}
+
+/// Build a case from a zip of CGMES profiles.
+pub fn case_from_zip(b: &[u8], name: &str) -> Result<Import> {
+  let mut documents = vec![];
+  unzip(b, &mut documents)?;
+  if documents.is_empty() {
+    return Err(anyhow!("No XML documents in {:?}", name));
+  }
+  let name = name.rsplit(['/', '\\']).next().unwrap_or(name);
+  let name = name.strip_suffix(".zip").unwrap_or(name);
+  case(&documents.iter().map(|d| d.as_str()).collect::<Vec<_>>(), name)
+}
+
+#[cfg(test)]
+const EQ: &str = r##"<?xml version="1.0" encoding="UTF-8"?>
+<rdf:RDF xmlns:cim="http://iec.ch/TC57/2013/CIM-schema-cim16#" xmlns:md="http://iec.ch/TC57/61970-552/ModelDescription/1#" xmlns:rdf="http://www.w3.org/1999/02/22-rdf-syntax-ns#">
+  <md:FullModel rdf:about="urn:uuid:model-eq">
+    <md:Model.profile>http://entsoe.eu/CIM/EquipmentCore/3/1</md:Model.profile>
+  </md:FullModel>
+  <cim:BaseVoltage rdf:ID="_bv110"><cim:BaseVoltage.nominalVoltage>110</cim:BaseVoltage.nominalVoltage></cim:BaseVoltage>
+  <cim:BaseVoltage rdf:ID="_bv20"><cim:BaseVoltage.nominalVoltage>20</cim:BaseVoltage.nominalVoltage></cim:BaseVoltage>
+  <cim:ACLineSegment rdf:ID="_line">
+    <cim:IdentifiedObject.name>L1</cim:IdentifiedObject.name>
+    <cim:ConductingEquipment.BaseVoltage rdf:resource="#_bv110"/>
+    <cim:ACLineSegment.r>2.42</cim:ACLineSegment.r>
+    <cim:ACLineSegment.x>12.1</cim:ACLineSegment.x>
+    <cim:ACLineSegment.bch>0.0001</cim:ACLineSegment.bch>
+  </cim:ACLineSegment>
+  <cim:Terminal rdf:ID="_line_t1"><cim:ACDCTerminal.sequenceNumber>1</cim:ACDCTerminal.sequenceNumber><cim:Terminal.ConductingEquipment rdf:resource="#_line"/></cim:Terminal>
+  <cim:Terminal rdf:ID="_line_t2"><cim:ACDCTerminal.sequenceNumber>2</cim:ACDCTerminal.sequenceNumber><cim:Terminal.ConductingEquipment rdf:resource="#_line"/></cim:Terminal>
+  <cim:PowerTransformer rdf:ID="_tr"><cim:IdentifiedObject.name>T1</cim:IdentifiedObject.name></cim:PowerTransformer>
+  <cim:PowerTransformerEnd rdf:ID="_tr_e1">
+    <cim:PowerTransformerEnd.PowerTransformer rdf:resource="#_tr"/>
+    <cim:TransformerEnd.Terminal rdf:resource="#_tr_t1"/>
+    <cim:TransformerEnd.endNumber>1</cim:TransformerEnd.endNumber>
+    <cim:PowerTransformerEnd.ratedU>110</cim:PowerTransformerEnd.ratedU>
+    <cim:PowerTransformerEnd.r>1.21</cim:PowerTransformerEnd.r>
+    <cim:PowerTransformerEnd.x>12.1</cim:PowerTransformerEnd.x>
+    <cim:PowerTransformerEnd.b>0</cim:PowerTransformerEnd.b>
+  </cim:PowerTransformerEnd>
+  <cim:PowerTransformerEnd rdf:ID="_tr_e2">
+    <cim:PowerTransformerEnd.PowerTransformer rdf:resource="#_tr"/>
+    <cim:TransformerEnd.Terminal rdf:resource="#_tr_t2"/>
+    <cim:TransformerEnd.endNumber>2</cim:TransformerEnd.endNumber>
+    <cim:PowerTransformerEnd.ratedU>22</cim:PowerTransformerEnd.ratedU>
+    <cim:PowerTransformerEnd.r>0</cim:PowerTransformerEnd.r>
+    <cim:PowerTransformerEnd.x>0</cim:PowerTransformerEnd.x>
+    <cim:PowerTransformerEnd.b>0</cim:PowerTransformerEnd.b>
+  </cim:PowerTransformerEnd>
+  <cim:Terminal rdf:ID="_tr_t1"><cim:ACDCTerminal.sequenceNumber>1</cim:ACDCTerminal.sequenceNumber><cim:Terminal.ConductingEquipment rdf:resource="#_tr"/></cim:Terminal>
+  <cim:Terminal rdf:ID="_tr_t2"><cim:ACDCTerminal.sequenceNumber>2</cim:ACDCTerminal.sequenceNumber><cim:Terminal.ConductingEquipment rdf:resource="#_tr"/></cim:Terminal>
+  <cim:ThermalGeneratingUnit rdf:ID="_gu"><cim:GeneratingUnit.maxOperatingP>200</cim:GeneratingUnit.maxOperatingP><cim:GeneratingUnit.minOperatingP>20</cim:GeneratingUnit.minOperatingP></cim:ThermalGeneratingUnit>
+  <cim:SynchronousMachine rdf:ID="_sm">
+    <cim:RotatingMachine.GeneratingUnit rdf:resource="#_gu"/>
+    <cim:RotatingMachine.ratedS>250</cim:RotatingMachine.ratedS>
+    <cim:SynchronousMachine.maxQ>100</cim:SynchronousMachine.maxQ>
+    <cim:SynchronousMachine.minQ>-50</cim:SynchronousMachine.minQ>
+  </cim:SynchronousMachine>
+  <cim:Terminal rdf:ID="_sm_t"><cim:Terminal.ConductingEquipment rdf:resource="#_sm"/></cim:Terminal>
+  <cim:ConformLoad rdf:ID="_load"/>
+  <cim:Terminal rdf:ID="_load_t"><cim:Terminal.ConductingEquipment rdf:resource="#_load"/></cim:Terminal>
+  <cim:CurrentLimit rdf:ID="_limit"><cim:CurrentLimit.value>500</cim:CurrentLimit.value></cim:CurrentLimit>
+  <cim:LoadResponseCharacteristic rdf:ID="_lrc"/>
+</rdf:RDF>"##;
+
+#[cfg(test)]
+const TP: &str = r##"<?xml version="1.0" encoding="UTF-8"?>
+<rdf:RDF xmlns:cim="http://iec.ch/TC57/2013/CIM-schema-cim16#" xmlns:rdf="http://www.w3.org/1999/02/22-rdf-syntax-ns#">
+  <cim:TopologicalNode rdf:ID="_n1"><cim:IdentifiedObject.name>North</cim:IdentifiedObject.name><cim:TopologicalNode.BaseVoltage rdf:resource="#_bv110"/></cim:TopologicalNode>
+  <cim:TopologicalNode rdf:ID="_n2"><cim:IdentifiedObject.name>South</cim:IdentifiedObject.name><cim:TopologicalNode.BaseVoltage rdf:resource="#_bv110"/></cim:TopologicalNode>
+  <cim:TopologicalNode rdf:ID="_n3"><cim:IdentifiedObject.name>South MV</cim:IdentifiedObject.name><cim:TopologicalNode.BaseVoltage rdf:resource="#_bv20"/></cim:TopologicalNode>
+  <cim:Terminal rdf:about="#_line_t1"><cim:Terminal.TopologicalNode rdf:resource="#_n1"/></cim:Terminal>
+  <cim:Terminal rdf:about="#_line_t2"><cim:Terminal.TopologicalNode rdf:resource="#_n2"/></cim:Terminal>
+  <cim:Terminal rdf:about="#_tr_t1"><cim:Terminal.TopologicalNode rdf:resource="#_n2"/></cim:Terminal>
+  <cim:Terminal rdf:about="#_tr_t2"><cim:Terminal.TopologicalNode rdf:resource="#_n3"/></cim:Terminal>
+  <cim:Terminal rdf:about="#_sm_t"><cim:Terminal.TopologicalNode rdf:resource="#_n1"/></cim:Terminal>
+  <cim:Terminal rdf:about="#_load_t"><cim:Terminal.TopologicalNode rdf:resource="#_n3"/></cim:Terminal>
+</rdf:RDF>"##;
+
+#[cfg(test)]
+const SSH: &str = r##"<?xml version="1.0" encoding="UTF-8"?>
+<rdf:RDF xmlns:cim="http://iec.ch/TC57/2013/CIM-schema-cim16#" xmlns:rdf="http://www.w3.org/1999/02/22-rdf-syntax-ns#">
+  <cim:SynchronousMachine rdf:about="#_sm"><cim:RotatingMachine.p>-80</cim:RotatingMachine.p><cim:RotatingMachine.q>-10</cim:RotatingMachine.q></cim:SynchronousMachine>
+  <cim:ConformLoad rdf:about="#_load"><cim:EnergyConsumer.p>75</cim:EnergyConsumer.p><cim:EnergyConsumer.q>20</cim:EnergyConsumer.q></cim:ConformLoad>
+  <cim:Terminal rdf:about="#_line_t2"><cim:ACDCTerminal.connected>true</cim:ACDCTerminal.connected></cim:Terminal>
+</rdf:RDF>"##;
+
+#[test]
+fn test_case() {
+  let i = case(&[SSH, TP, EQ], "model").unwrap();
+  let c = i.case;
+  assert_eq!(c.bus_name, vec!["North", "South", "South MV"]);
+  assert_eq!(c.bus.iter().map(|b| b.base_kv).collect::<Vec<_>>(), vec![110.0, 110.0, 20.0]);
+  assert_eq!(c.bus[0].bus_type, BusType::Ref);
+  assert_eq!(c.bus[2].pd, 75.0);
+  assert_eq!(c.bus[2].qd, 20.0);
+
+  assert_eq!(c.branch.len(), 2);
+  assert_eq!((c.branch[0].f_bus, c.branch[0].t_bus), (BusId(1), BusId(2)));
+  assert!((c.branch[0].br_r - 0.02).abs() < 1e-12);
+  assert!((c.branch[0].br_x - 0.1).abs() < 1e-12);
+  assert!((c.branch[0].br_b - 0.0121).abs() < 1e-12);
+  assert_eq!(c.branch[0].tap, 0.0);
+  // 110/22 kV on 110/20 kV bases, impedance referred to the 20 kV side
+  assert_eq!((c.branch[1].f_bus, c.branch[1].t_bus), (BusId(2), BusId(3)));
+  assert!((c.branch[1].tap - 110.0 / 22.0 * 20.0 / 110.0).abs() < 1e-12);
+  assert!((c.branch[1].br_x - 12.1 * (22.0f64 / 110.0).powi(2) / 4.0).abs() < 1e-12);
+
+  assert_eq!(c.gen.len(), 1);
+  assert_eq!(c.gen[0].gen, BusId(1));
+  assert_eq!(c.gen[0].pg, 80.0);
+  assert_eq!(c.gen[0].qg, 10.0);
+  assert_eq!((c.gen[0].pmin, c.gen[0].pmax), (20.0, 200.0));
+  assert_eq!((c.gen[0].qmin, c.gen[0].qmax), (-50.0, 100.0));
+  assert_eq!(c.gen[0].mbase, 250.0);
+
+  let skipped = i.skipped.into_iter().collect::<Vec<_>>();
+  assert_eq!(skipped, vec![("CurrentLimit".to_string(), 1), ("LoadResponseCharacteristic".to_string(), 1)]);
+}
+
+#[test]
+fn test_case_from_zip() {
+  use std::io::Write;
+
+  let mut inner = zip::ZipWriter::new(Cursor::new(vec![]));
+  inner.start_file("model_SSH.xml", zip::write::FileOptions::default()).unwrap();
+  inner.write_all(SSH.as_bytes()).unwrap();
+  let inner = inner.finish().unwrap().into_inner();
+
+  let mut outer = zip::ZipWriter::new(Cursor::new(vec![]));
+  outer.start_file("model_EQ.xml", zip::write::FileOptions::default()).unwrap();
+  outer.write_all(EQ.as_bytes()).unwrap();
+  outer.start_file("model_TP.XML", zip::write::FileOptions::default()).unwrap();
+  outer.write_all(TP.as_bytes()).unwrap();
+  outer.start_file("model_SSH.zip", zip::write::FileOptions::default()).unwrap();
+  outer.write_all(&inner).unwrap();
+  let b = outer.finish().unwrap().into_inner();
+
+  assert!(is_zip(&b));
+  let i = case_from_zip(&b, "models/model.zip").unwrap();
+  assert_eq!(i.case.name, "model");
+  assert_eq!(i.case.gen[0].pg, 80.0);
+  let i = crate::read::import(&b, "model.zip").unwrap();
+  assert_eq!(i.skipped.keys().collect::<Vec<_>>(), vec!["CurrentLimit", "LoadResponseCharacteristic"]);
+}
+
+#[test]
+fn test_case_errors() {
+  assert!(case(&[EQ], "model").is_err());
+  assert!(case(&["<rdf:RDF"], "model").is_err());
+}
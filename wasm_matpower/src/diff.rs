@@ -0,0 +1,373 @@
+// Differences between cases
+
+// Rows are matched by what they connect rather than by position, so reordered tables compare equal: buses by number,
+// generators by bus and their order at that bus, and branches and DC lines by from bus, to bus and their order
+// between those buses. Matched rows are compared column by column in MATPOWER column order, generators together with
+// their cost rows. A difference can be replayed with MATPOWER's `apply_changes` as a `chgtab`, which changes rows of
+// the first case; removed elements are switched off there, and added ones, which a `chgtab` can't express, are listed
+// in comments.
+
+use std::{collections::HashMap, fmt, fmt::Write};
+
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::case::{BusId, Case, GenCost, BRANCH_COLUMNS, BUS_COLUMNS, DCLINE_COLUMNS, GENCOST_COLUMNS, GEN_COLUMNS};
+
+/// An element as `diff` matches it. Ordinals are 1-based and count generators at the same bus, or branches and DC
+/// lines between the same buses in the same direction, in table order.
+#[derive(Serialize, Debug, PartialEq, Eq, Hash, Clone, Copy)]
+#[serde(tag = "table", rename_all = "lowercase")]
+pub enum Element {
+  Bus { bus: BusId },
+  Gen { bus: BusId, ordinal: usize },
+  Branch { f_bus: BusId, t_bus: BusId, ordinal: usize },
+  DcLine { f_bus: BusId, t_bus: BusId, ordinal: usize },
+}
+
+impl fmt::Display for Element {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Element::Bus { bus } => write!(f, "bus {}", bus),
+      Element::Gen { bus, ordinal } => write!(f, "gen {} at bus {}", ordinal, bus),
+      Element::Branch { f_bus, t_bus, ordinal } => write!(f, "branch {}-{} #{}", f_bus, t_bus, ordinal),
+      Element::DcLine { f_bus, t_bus, ordinal } => write!(f, "dcline {}-{} #{}", f_bus, t_bus, ordinal),
+    }
+  }
+}
+
+/// An element in only one of the cases, with its 0-based row in that case.
+#[derive(Serialize, Debug, PartialEq, Clone, Copy)]
+pub struct Presence {
+  pub element: Element,
+  pub row: usize,
+}
+
+/// A value that differs between matched rows by more than the tolerance. `field` is the MATPOWER column name, with
+/// cost columns as `gencost.NCOST` or `qcost.COST+1`.
+#[derive(Serialize, Debug, PartialEq, Clone)]
+pub struct FieldChange {
+  pub element: Element,
+  pub field: String,
+  pub old: f64,
+  pub new: f64,
+  // `chgtab` table, 1-based row in the first case and 1-based column, if a `chgtab` can change the value
+  #[serde(skip)]
+  target: Option<(u8, usize, usize)>,
+}
+
+/// What changed from one case to another, from `diff`.
+#[derive(Serialize, Debug, PartialEq, Clone, Default)]
+pub struct CaseDiff {
+  /// old and new system MVA base, if they differ
+  pub base_mva: Option<(f64, f64)>,
+  /// elements only in the second case
+  pub added: Vec<Presence>,
+  /// elements only in the first case
+  pub removed: Vec<Presence>,
+  /// values that differ between matched elements
+  pub changed: Vec<FieldChange>,
+}
+
+// `chgtab` table numbers and the replace change type, from MATPOWER's `idx_ct`
+const CT_TBUS: u8 = 1;
+const CT_TGEN: u8 = 2;
+const CT_TBRCH: u8 = 3;
+const CT_TGENCOST: u8 = 9;
+const CT_REP: u8 = 1;
+
+fn keys(elements: impl Iterator<Item = (BusId, BusId)>, key: fn(BusId, BusId, usize) -> Element) -> Vec<Element> {
+  let mut count = HashMap::new();
+  elements
+    .map(|(f, t)| {
+      let n = count.entry((f, t)).or_insert(0);
+      *n += 1;
+      key(f, t, *n)
+    })
+    .collect()
+}
+
+fn differs(a: f64, b: f64, tolerance: f64) -> bool {
+  if a.is_nan() || b.is_nan() {
+    a.is_nan() != b.is_nan()
+  } else {
+    (a - b).abs() > tolerance
+  }
+}
+
+fn cost_field(prefix: &str, i: usize) -> String {
+  match i {
+    i if i < GENCOST_COLUMNS.len() => format!("{}.{}", prefix, GENCOST_COLUMNS[i].0),
+    4 => format!("{}.COST", prefix),
+    i => format!("{}.COST+{}", prefix, i - 4),
+  }
+}
+
+struct Rows<'a> {
+  keys: Vec<Element>,
+  rows: Vec<Vec<f64>>,
+  columns: &'a [(&'a str, &'a str)],
+  ct_table: Option<u8>,
+}
+
+impl CaseDiff {
+  fn compare(&mut self, old: Rows, new: Rows, tolerance: f64) {
+    let index = new.keys.iter().enumerate().map(|(i, k)| (*k, i)).collect::<HashMap<_, _>>();
+    let mut matched = vec![false; new.keys.len()];
+    for (i, key) in old.keys.iter().enumerate() {
+      let j = match index.get(key) {
+        Some(j) => *j,
+        None => {
+          self.removed.push(Presence { element: *key, row: i });
+          continue;
+        },
+      };
+      matched[j] = true;
+      // Solution columns missing from either row are not compared.
+      for (c, (a, b)) in old.rows[i].iter().zip(new.rows[j].iter()).enumerate() {
+        if differs(*a, *b, tolerance) {
+          self.changed.push(FieldChange {
+            element: *key,
+            field: old.columns[c].0.to_string(),
+            old: *a,
+            new: *b,
+            target: old.ct_table.map(|t| (t, i + 1, c + 1)),
+          });
+        }
+      }
+    }
+    for (j, key) in new.keys.iter().enumerate().filter(|(j, _)| !matched[*j]) {
+      self.added.push(Presence { element: *key, row: j });
+    }
+  }
+
+  // Compares the active or, with `reactive`, the reactive power cost rows of matched generators. Rows are padded with
+  // zeros to the same length, as in a MATPOWER `gencost` matrix. When MODEL or NCOST changes, every coefficient of the
+  // new row is a change, so that a `chgtab` writes the whole cost function.
+  fn compare_costs(&mut self, old: (&Case, &[Element]), new: (&Case, &[Element]), reactive: bool, tolerance: f64) {
+    let cost = |c: &Case, row: usize| -> Option<Vec<f64>> {
+      let offset = if reactive { c.gen.len() } else { 0 };
+      c.gencost.get(offset + row).map(GenCost::to_row)
+    };
+    let prefix = if reactive { "qcost" } else { "gencost" };
+    let index = new.1.iter().enumerate().map(|(i, k)| (*k, i)).collect::<HashMap<_, _>>();
+    for (i, key) in old.1.iter().enumerate() {
+      let (a, b) = match index.get(key).map(|j| (cost(old.0, i), cost(new.0, *j))) {
+        Some((Some(a), Some(b))) => (a, b),
+        _ => continue,
+      };
+      let row = if reactive { old.0.gen.len() + i + 1 } else { i + 1 };
+      let reshaped = differs(a[0], b[0], tolerance) || differs(a[3], b[3], tolerance);
+      for c in 0..a.len().max(b.len()) {
+        let rewrite = reshaped && c >= GENCOST_COLUMNS.len() && c < b.len();
+        let (a, b) = (a.get(c).copied().unwrap_or(0.0), b.get(c).copied().unwrap_or(0.0));
+        if differs(a, b, tolerance) || rewrite {
+          let target = Some((CT_TGENCOST, row, c + 1));
+          self.changed.push(FieldChange { element: *key, field: cost_field(prefix, c), old: a, new: b, target });
+        }
+      }
+    }
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.base_mva.is_none() && self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+  }
+
+  /// One line per difference.
+  pub fn report(&self) -> String {
+    let mut s = String::new();
+    if let Some((old, new)) = self.base_mva {
+      writeln!(s, "Changed baseMVA from {} to {}", old, new).unwrap();
+    }
+    for p in self.removed.iter() {
+      writeln!(s, "Removed {} (row {})", p.element, p.row + 1).unwrap();
+    }
+    for p in self.added.iter() {
+      writeln!(s, "Added {} (row {})", p.element, p.row + 1).unwrap();
+    }
+    for c in self.changed.iter() {
+      writeln!(s, "Changed {} {} from {} to {}", c.element, c.field, c.old, c.new).unwrap();
+    }
+    if s.is_empty() {
+      s.push_str("No differences\n");
+    }
+    s
+  }
+
+  pub fn to_json(&self) -> Value {
+    serde_json::to_value(self).unwrap_or(Value::Null)
+  }
+
+  /// A MATLAB function `name` returning a `chgtab` that turns the first case into the second with `apply_changes`.
+  /// Removed buses become isolated and removed generators and branches go out of service. Added elements, removed DC
+  /// lines and DC line changes have no `chgtab` form and are listed in comments.
+  pub fn to_chgtab(&self, name: &str) -> String {
+    let mut rows = vec![];
+    let mut skipped = vec![];
+    for p in self.removed.iter() {
+      let row = p.row + 1;
+      match p.element {
+        Element::Bus { .. } => rows.push((CT_TBUS, row, 2, 4.0, format!("remove {}", p.element))),
+        Element::Gen { .. } => rows.push((CT_TGEN, row, 8, 0.0, format!("remove {}", p.element))),
+        Element::Branch { .. } => rows.push((CT_TBRCH, row, 11, 0.0, format!("remove {}", p.element))),
+        Element::DcLine { .. } => skipped.push(format!("Removed {}", p.element)),
+      }
+    }
+    for c in self.changed.iter() {
+      match c.target {
+        Some((table, row, column)) => rows.push((table, row, column, c.new, format!("{} {}", c.element, c.field))),
+        None => skipped.push(format!("Changed {} {} from {} to {}", c.element, c.field, c.old, c.new)),
+      }
+    }
+    skipped.extend(self.added.iter().map(|p| format!("Added {}", p.element)));
+    if let Some((old, new)) = self.base_mva {
+      skipped.push(format!("Changed baseMVA from {} to {}", old, new));
+    }
+
+    let mut s = String::new();
+    writeln!(s, "function chgtab = {}", name).unwrap();
+    writeln!(s, "% label probability table row column type value, with the column numbers of `idx_ct`").unwrap();
+    if !skipped.is_empty() {
+      writeln!(s, "% Not expressible as a chgtab:").unwrap();
+      for line in skipped.iter() {
+        writeln!(s, "%   {}", line).unwrap();
+      }
+    }
+    writeln!(s, "chgtab = [").unwrap();
+    for (table, row, column, value, comment) in rows.iter() {
+      writeln!(s, "\t1\t1\t{}\t{}\t{}\t{}\t{};\t% {}", table, row, column, CT_REP, value, comment).unwrap();
+    }
+    writeln!(s, "];").unwrap();
+    s
+  }
+}
+
+/// Differences from case `a` to case `b`, ignoring value changes of at most `tolerance`.
+pub fn diff(a: &Case, b: &Case, tolerance: f64) -> CaseDiff {
+  let mut d = CaseDiff::default();
+  if differs(a.base_mva, b.base_mva, tolerance) {
+    d.base_mva = Some((a.base_mva, b.base_mva));
+  }
+  let bus = |c: &Case| Rows {
+    keys: c.bus.iter().map(|b| Element::Bus { bus: b.idx }).collect(),
+    rows: c.bus.iter().map(|b| b.to_row()).collect(),
+    columns: &BUS_COLUMNS,
+    ct_table: Some(CT_TBUS),
+  };
+  let gen_keys = |c: &Case| keys(c.gen.iter().map(|g| (g.gen, g.gen)), |bus, _, ordinal| Element::Gen { bus, ordinal });
+  let gen = |c: &Case| Rows {
+    keys: gen_keys(c),
+    rows: c.gen.iter().map(|g| g.to_row()).collect(),
+    columns: &GEN_COLUMNS,
+    ct_table: Some(CT_TGEN),
+  };
+  let branch = |c: &Case| Rows {
+    keys: keys(c.branch.iter().map(|br| (br.f_bus, br.t_bus)), |f_bus, t_bus, ordinal| Element::Branch {
+      f_bus,
+      t_bus,
+      ordinal,
+    }),
+    rows: c.branch.iter().map(|br| br.to_row()).collect(),
+    columns: &BRANCH_COLUMNS,
+    ct_table: Some(CT_TBRCH),
+  };
+  let dcline = |c: &Case| Rows {
+    keys: keys(c.dcline.iter().map(|d| (d.f_bus, d.t_bus)), |f_bus, t_bus, ordinal| Element::DcLine {
+      f_bus,
+      t_bus,
+      ordinal,
+    }),
+    rows: c.dcline.iter().map(|d| d.to_row()).collect(),
+    columns: &DCLINE_COLUMNS,
+    ct_table: None,
+  };
+  d.compare(bus(a), bus(b), tolerance);
+  d.compare(gen(a), gen(b), tolerance);
+  let (ka, kb) = (gen_keys(a), gen_keys(b));
+  d.compare_costs((a, &ka), (b, &kb), false, tolerance);
+  d.compare_costs((a, &ka), (b, &kb), true, tolerance);
+  d.compare(branch(a), branch(b), tolerance);
+  d.compare(dcline(a), dcline(b), tolerance);
+  d
+}
+
+#[cfg(test)]
+const CASE3: &str = r#"function mpc = case3
+mpc.version = '2';
+mpc.baseMVA = 100;
+mpc.bus = [
+	1	3	0	0	0	0	1	1	0	230	1	1.1	0.9;
+	2	1	50	10	0	0	1	1	0	230	1	1.1	0.9;
+	3	2	90	30	0	0	1	1	0	230	1	1.1	0.9;
+];
+mpc.gen = [
+	1	60	0	300	-300	1	100	1	250	10	0	0	0	0	0	0	0	0	0	0	0;
+	3	50	0	300	-300	1	100	1	250	10	0	0	0	0	0	0	0	0	0	0	0;
+];
+mpc.branch = [
+	1	2	0.01	0.1	0	250	250	300	0	0	1	-360	360;
+	2	3	0.01	0.1	0	250	250	300	0	0	1	-360	360;
+	1	2	0.01	0.1	0	250	250	300	0	0	1	-360	360;
+];
+mpc.gencost = [
+	2	0	0	2	40	0;
+	2	0	0	2	10	0;
+];
+"#;
+
+#[test]
+fn test_diff() {
+  let a = crate::case::case(CASE3).unwrap();
+  assert!(diff(&a, &a, 0.0).is_empty());
+  assert_eq!(diff(&a, &a, 0.0).report(), "No differences\n");
+
+  // Reordering rows is not a difference.
+  let mut b = a.clone();
+  b.bus.swap(0, 2);
+  b.gen.swap(0, 1);
+  b.gencost.swap(0, 1);
+  b.branch.swap(0, 1);
+  assert!(diff(&a, &b, 0.0).is_empty());
+
+  b.bus[1].pd = 60.0;
+  b.bus[1].qd = 10.0 + 1e-9;
+  b.branch.remove(2);
+  b.gen[0].gen = BusId(2);
+  b.gencost[1].cost = vec![45.0, 0.0, 0.0];
+  b.gencost[1].ncost = 3;
+  let d = diff(&a, &b, 1e-6);
+  assert_eq!(
+    d.report(),
+    "Removed gen 1 at bus 3 (row 2)\n\
+     Removed branch 1-2 #2 (row 3)\n\
+     Added gen 1 at bus 2 (row 1)\n\
+     Changed bus 2 PD from 50 to 60\n\
+     Changed gen 1 at bus 1 gencost.NCOST from 2 to 3\n\
+     Changed gen 1 at bus 1 gencost.COST from 40 to 45\n\
+     Changed gen 1 at bus 1 gencost.COST+1 from 0 to 0\n\
+     Changed gen 1 at bus 1 gencost.COST+2 from 0 to 0\n"
+  );
+  assert_eq!(
+    d.to_chgtab("chgtab_case3"),
+    "function chgtab = chgtab_case3
+% label probability table row column type value, with the column numbers of `idx_ct`
+% Not expressible as a chgtab:
+%   Added gen 1 at bus 2
+chgtab = [
+\t1\t1\t2\t2\t8\t1\t0;\t% remove gen 1 at bus 3
+\t1\t1\t3\t3\t11\t1\t0;\t% remove branch 1-2 #2
+\t1\t1\t1\t2\t3\t1\t60;\t% bus 2 PD
+\t1\t1\t9\t1\t4\t1\t3;\t% gen 1 at bus 1 gencost.NCOST
+\t1\t1\t9\t1\t5\t1\t45;\t% gen 1 at bus 1 gencost.COST
+\t1\t1\t9\t1\t6\t1\t0;\t% gen 1 at bus 1 gencost.COST+1
+\t1\t1\t9\t1\t7\t1\t0;\t% gen 1 at bus 1 gencost.COST+2
+];
+"
+  );
+  let json = d.to_json();
+  assert_eq!(
+    json["removed"][1],
+    serde_json::json!({"element": {"table": "branch", "f_bus": 1, "t_bus": 2, "ordinal": 2}, "row": 2})
+  );
+  assert_eq!(json["changed"][0]["field"], "PD");
+}
@@ -0,0 +1,632 @@
+// Editing cases with undo and redo
+
+// `CaseEditor` owns a case and applies edits to it as lists of primitive row operations: set one value, insert a
+// row or remove a row. Each operation carries what it needs to be reversed, so undo and redo replay the lists, and the
+// edit history can be written out as a MATLAB function in the style of MATPOWER's `modcase` scripts. Edits keep bus
+// references valid: renumbering a bus renumbers the rows that refer to it, and a bus with elements attached is only
+// removed together with them. Rows are 0-based, as in `Case`; messages and scripts number them from 1, as MATLAB does.
+
+use std::fmt::Write;
+
+use anyhow::{anyhow, Result};
+
+use crate::{
+  case::{
+    Branch, Bus, BusId, Case, DcLine, Gen, GenCost, BRANCH_COLUMNS, BUS_COLUMNS, DCLINE_COLUMNS, GEN_COLUMNS,
+  },
+  index::{RowSpans, Table},
+};
+
+// A whole row, with the bus name or the generator's cost rows that go with it.
+#[derive(Debug, Clone, PartialEq)]
+enum Row {
+  Bus(Bus, Option<String>),
+  Gen(Gen, Vec<GenCost>),
+  Branch(Branch),
+  DcLine(DcLine),
+}
+
+impl Row {
+  fn table(&self) -> Table {
+    match self {
+      Row::Bus(..) => Table::Bus,
+      Row::Gen(..) => Table::Gen,
+      Row::Branch(_) => Table::Branch,
+      Row::DcLine(_) => Table::DcLine,
+    }
+  }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Op {
+  Set { table: Table, row: usize, column: usize, old: f64, new: f64 },
+  Insert { row: usize, data: Row },
+  Remove { row: usize, data: Row },
+}
+
+impl Op {
+  fn inverse(&self) -> Op {
+    match self.clone() {
+      Op::Set { table, row, column, old, new } => Op::Set { table, row, column, old: new, new: old },
+      Op::Insert { row, data } => Op::Remove { row, data },
+      Op::Remove { row, data } => Op::Insert { row, data },
+    }
+  }
+}
+
+#[derive(Debug, Clone)]
+struct Change {
+  description: String,
+  ops: Vec<Op>,
+}
+
+fn columns(table: Table) -> &'static [(&'static str, &'static str)] {
+  match table {
+    Table::Bus => &BUS_COLUMNS,
+    Table::Gen => &GEN_COLUMNS,
+    Table::Branch => &BRANCH_COLUMNS,
+    Table::DcLine => &DCLINE_COLUMNS,
+  }
+}
+
+fn status_column(table: Table) -> Result<usize> {
+  match table {
+    Table::Bus => Err(anyhow!("Buses have no status, set BUS_TYPE to 4 to isolate a bus")),
+    Table::Gen => Ok(7),
+    Table::Branch => Ok(10),
+    Table::DcLine => Ok(2),
+  }
+}
+
+fn len(case: &Case, table: Table) -> usize {
+  match table {
+    Table::Bus => case.bus.len(),
+    Table::Gen => case.gen.len(),
+    Table::Branch => case.branch.len(),
+    Table::DcLine => case.dcline.len(),
+  }
+}
+
+fn to_row(case: &Case, table: Table, row: usize) -> Result<Vec<f64>> {
+  if row >= len(case, table) {
+    return Err(anyhow!("{} has no row {}", table, row + 1));
+  }
+  Ok(match table {
+    Table::Bus => case.bus[row].to_row(),
+    Table::Gen => case.gen[row].to_row(),
+    Table::Branch => case.branch[row].to_row(),
+    Table::DcLine => case.dcline[row].to_row(),
+  })
+}
+
+fn apply(case: &mut Case, op: &Op) -> Result<()> {
+  match op {
+    Op::Set { table, row, column, new, .. } => {
+      let (table, row) = (*table, *row);
+      let mut values = to_row(case, table, row)?;
+      let name = columns(table)[*column].0;
+      if *column >= values.len() {
+        return Err(anyhow!("{} row {} has no {} value", table, row + 1, name));
+      }
+      values[*column] = *new;
+      let invalid = |e| anyhow!("Invalid {} for {} row {}: {}", name, table, row + 1, e);
+      match table {
+        Table::Bus => case.bus[row] = Bus { coords: case.bus[row].coords, ..Bus::from_row(&values).map_err(invalid)? },
+        Table::Gen => case.gen[row] = Gen::from_row(&values).map_err(invalid)?,
+        Table::Branch => case.branch[row] = Branch::from_row(&values).map_err(invalid)?,
+        Table::DcLine => case.dcline[row] = DcLine::from_row(&values).map_err(invalid)?,
+      }
+    },
+    Op::Insert { row, data } => {
+      let row = *row;
+      if row > len(case, data.table()) {
+        return Err(anyhow!("{} has no row {}", data.table(), row + 1));
+      }
+      match data.clone() {
+        Row::Bus(bus, name) => {
+          case.bus.insert(row, bus);
+          if let Some(name) = name {
+            case.bus_name.insert(row, name);
+          }
+        },
+        Row::Gen(gen, cost) => {
+          case.gen.insert(row, gen);
+          let ng = case.gen.len();
+          let mut cost = cost.into_iter();
+          if let Some(p) = cost.next() {
+            case.gencost.insert(row, p);
+          }
+          if let Some(q) = cost.next() {
+            case.gencost.insert(ng + row, q);
+          }
+        },
+        Row::Branch(branch) => case.branch.insert(row, branch),
+        Row::DcLine(dcline) => case.dcline.insert(row, dcline),
+      }
+    },
+    Op::Remove { row, data } => {
+      let row = *row;
+      if row >= len(case, data.table()) {
+        return Err(anyhow!("{} has no row {}", data.table(), row + 1));
+      }
+      match data.table() {
+        Table::Bus => {
+          case.bus.remove(row);
+          if let Row::Bus(_, Some(_)) = data {
+            case.bus_name.remove(row);
+          }
+        },
+        Table::Gen => {
+          let ng = case.gen.len();
+          if case.gencost.len() == 2 * ng {
+            case.gencost.remove(ng + row);
+          }
+          if !case.gencost.is_empty() {
+            case.gencost.remove(row);
+          }
+          case.gen.remove(row);
+        },
+        Table::Branch => {
+          case.branch.remove(row);
+        },
+        Table::DcLine => {
+          case.dcline.remove(row);
+        },
+      }
+    },
+  }
+  Ok(())
+}
+
+// The whole row `row` of `table`, as `Op::Remove` needs it to be undone.
+fn row_data(case: &Case, table: Table, row: usize) -> Row {
+  match table {
+    Table::Bus => Row::Bus(case.bus[row], case.bus_name.get(row).cloned()),
+    Table::Gen => {
+      let ng = case.gen.len();
+      let cost = [row, ng + row].iter().filter_map(|i| case.gencost.get(*i).cloned()).collect();
+      Row::Gen(case.gen[row], cost)
+    },
+    Table::Branch => Row::Branch(case.branch[row]),
+    Table::DcLine => Row::DcLine(case.dcline[row]),
+  }
+}
+
+/// Edits a case and remembers them for undo, redo and `to_script`.
+#[derive(Debug, Clone)]
+pub struct CaseEditor {
+  case: Case,
+  undo: Vec<Change>,
+  redo: Vec<Change>,
+}
+
+impl CaseEditor {
+  /// An editor for `case`, which must have unique bus numbers, no dangling bus references, and one or two cost rows
+  /// per generator if it has costs.
+  pub fn new(case: Case) -> Result<CaseEditor> {
+    case.check_references(&RowSpans::default())?;
+    case.check_gencost_rows()?;
+    Ok(CaseEditor { case, undo: vec![], redo: vec![] })
+  }
+
+  pub fn case(&self) -> &Case {
+    &self.case
+  }
+
+  pub fn into_case(self) -> Case {
+    self.case
+  }
+
+  // Applies `ops` in order. If one fails, those already applied are reversed and the case is as it was.
+  fn commit(&mut self, description: String, ops: Vec<Op>) -> Result<()> {
+    for (i, op) in ops.iter().enumerate() {
+      if let Err(e) = apply(&mut self.case, op) {
+        for op in ops[..i].iter().rev() {
+          apply(&mut self.case, &op.inverse())?;
+        }
+        return Err(e);
+      }
+    }
+    self.undo.push(Change { description, ops });
+    self.redo.clear();
+    Ok(())
+  }
+
+  fn has_bus(&self, id: BusId) -> bool {
+    self.case.bus.iter().any(|b| b.idx == id)
+  }
+
+  fn require_bus(&self, id: BusId) -> Result<()> {
+    if self.has_bus(id) {
+      Ok(())
+    } else {
+      Err(anyhow!("Unknown bus {}", id))
+    }
+  }
+
+  /// Set the value in column `column`, a MATPOWER column name such as `VM` or `BR_STATUS`, of row `row` of `table`.
+  /// Renumbering a bus renumbers the generators, branches and DC lines at it; other bus references must name an
+  /// existing bus.
+  pub fn set_field(&mut self, table: Table, row: usize, column: &str, value: f64) -> Result<()> {
+    let c = columns(table)
+      .iter()
+      .position(|(name, _)| name.eq_ignore_ascii_case(column))
+      .ok_or_else(|| anyhow!("{} has no column {}", table, column))?;
+    let old = to_row(&self.case, table, row)?;
+    if c >= old.len() {
+      return Err(anyhow!("{} row {} has no {} value", table, row + 1, columns(table)[c].0));
+    }
+    let mut ops = vec![Op::Set { table, row, column: c, old: old[c], new: value }];
+    let id = BusId(value as usize);
+    match (table, c) {
+      (Table::Bus, 0) if old[0] != value => {
+        if value >= 0.0 && value.fract() == 0.0 && self.has_bus(id) {
+          return Err(anyhow!("Bus {} already exists", id));
+        }
+        let (old, new) = (old[0], value);
+        let set = |table, row, column| Op::Set { table, row, column, old, new };
+        let old = BusId(old as usize);
+        for (i, _) in self.case.gen.iter().enumerate().filter(|(_, g)| g.gen == old) {
+          ops.push(set(Table::Gen, i, 0));
+        }
+        for (i, br) in self.case.branch.iter().enumerate() {
+          if br.f_bus == old {
+            ops.push(set(Table::Branch, i, 0));
+          }
+          if br.t_bus == old {
+            ops.push(set(Table::Branch, i, 1));
+          }
+        }
+        for (i, d) in self.case.dcline.iter().enumerate() {
+          if d.f_bus == old {
+            ops.push(set(Table::DcLine, i, 0));
+          }
+          if d.t_bus == old {
+            ops.push(set(Table::DcLine, i, 1));
+          }
+        }
+      },
+      (Table::Gen, 0) | (Table::Branch, 0) | (Table::Branch, 1) | (Table::DcLine, 0) | (Table::DcLine, 1) => {
+        self.require_bus(id)?
+      },
+      _ => {},
+    }
+    self.commit(format!("Set {} of {} row {} to {}", columns(table)[c].0, table, row + 1, value), ops)
+  }
+
+  /// Put row `row` of the generator, branch or DC line table in or out of service.
+  pub fn set_status(&mut self, table: Table, row: usize, in_service: bool) -> Result<()> {
+    let column = status_column(table)?;
+    let old = to_row(&self.case, table, row)?[column];
+    let new = if in_service { 1.0 } else { 0.0 };
+    let description = format!("Put {} row {} {} service", table, row + 1, if in_service { "in" } else { "out of" });
+    self.commit(description, vec![Op::Set { table, row, column, old, new }])
+  }
+
+  /// Switch the status of row `row` of the generator, branch or DC line table. Returns whether it is now in service.
+  pub fn toggle_status(&mut self, table: Table, row: usize) -> Result<bool> {
+    let in_service = to_row(&self.case, table, row)?[status_column(table)?] <= 0.0;
+    self.set_status(table, row, in_service)?;
+    Ok(in_service)
+  }
+
+  /// Add a bus at the end of the bus table. In a case with bus names, a bus added without one is named by number; a
+  /// case without them takes no name.
+  pub fn add_bus(&mut self, bus: Bus, name: Option<String>) -> Result<()> {
+    if self.has_bus(bus.idx) {
+      return Err(anyhow!("Bus {} already exists", bus.idx));
+    }
+    let name = match (name, self.case.bus_name.is_empty()) {
+      (Some(_), true) if !self.case.bus.is_empty() => return Err(anyhow!("The case has no bus names")),
+      (None, false) => Some(bus.idx.to_string()),
+      (name, _) => name,
+    };
+    let row = self.case.bus.len();
+    self.commit(format!("Add bus {}", bus.idx), vec![Op::Insert { row, data: Row::Bus(bus, name) }])
+  }
+
+  /// Remove bus `id`. Generators, branches and DC lines at the bus are removed with it if `cascade` is set, otherwise
+  /// they are listed in the error.
+  pub fn remove_bus(&mut self, id: BusId, cascade: bool) -> Result<()> {
+    let row = self.case.bus.iter().position(|b| b.idx == id).ok_or_else(|| anyhow!("Unknown bus {}", id))?;
+    let attached = |table: Table, rows: Vec<usize>| rows.into_iter().map(move |r| (table, r));
+    // Highest rows first, so removing one doesn't move the others.
+    let mut rows = vec![];
+    rows.extend(attached(
+      Table::DcLine,
+      (0..self.case.dcline.len())
+        .rev()
+        .filter(|i| self.case.dcline[*i].f_bus == id || self.case.dcline[*i].t_bus == id)
+        .collect(),
+    ));
+    rows.extend(attached(
+      Table::Branch,
+      (0..self.case.branch.len())
+        .rev()
+        .filter(|i| self.case.branch[*i].f_bus == id || self.case.branch[*i].t_bus == id)
+        .collect(),
+    ));
+    rows.extend(attached(Table::Gen, (0..self.case.gen.len()).rev().filter(|i| self.case.gen[*i].gen == id).collect()));
+    if !rows.is_empty() && !cascade {
+      let list = rows.iter().rev().map(|(t, r)| format!("{} row {}", t, r + 1)).collect::<Vec<_>>().join(", ");
+      return Err(anyhow!("Bus {} is still connected to {}", id, list));
+    }
+    let mut ops =
+      rows.iter().map(|(t, r)| Op::Remove { row: *r, data: row_data(&self.case, *t, *r) }).collect::<Vec<_>>();
+    ops.push(Op::Remove { row, data: row_data(&self.case, Table::Bus, row) });
+    let description = match rows.len() {
+      0 => format!("Remove bus {}", id),
+      n => format!("Remove bus {} and {} attached elements", id, n),
+    };
+    self.commit(description, ops)
+  }
+
+  /// Add a generator at the end of the generator table. If the case has costs, `cost` is the generator's active
+  /// power cost and defaults to zero; a reactive power cost, if the case has those, is zero.
+  pub fn add_gen(&mut self, gen: Gen, cost: Option<GenCost>) -> Result<()> {
+    self.require_bus(gen.gen)?;
+    let zero = GenCost::default();
+    let ng = self.case.gen.len();
+    let cost = match (self.case.gencost.len(), cost) {
+      (0, Some(_)) if ng > 0 => return Err(anyhow!("The case has no generator costs")),
+      (0, cost) => cost.into_iter().collect(),
+      (n, cost) if n == 2 * ng => vec![cost.unwrap_or_else(|| zero.clone()), zero],
+      (_, cost) => vec![cost.unwrap_or(zero)],
+    };
+    let description = format!("Add generator at bus {}", gen.gen);
+    self.commit(description, vec![Op::Insert { row: ng, data: Row::Gen(gen, cost) }])
+  }
+
+  /// Remove row `row` of the generator table and its costs.
+  pub fn remove_gen(&mut self, row: usize) -> Result<()> {
+    to_row(&self.case, Table::Gen, row)?;
+    let data = row_data(&self.case, Table::Gen, row);
+    self.commit(format!("Remove {} row {}", Table::Gen, row + 1), vec![Op::Remove { row, data }])
+  }
+
+  /// Add a branch at the end of the branch table.
+  pub fn add_branch(&mut self, branch: Branch) -> Result<()> {
+    self.require_bus(branch.f_bus)?;
+    self.require_bus(branch.t_bus)?;
+    let row = self.case.branch.len();
+    let description = format!("Add branch from bus {} to bus {}", branch.f_bus, branch.t_bus);
+    self.commit(description, vec![Op::Insert { row, data: Row::Branch(branch) }])
+  }
+
+  /// Remove row `row` of the branch table.
+  pub fn remove_branch(&mut self, row: usize) -> Result<()> {
+    to_row(&self.case, Table::Branch, row)?;
+    let data = row_data(&self.case, Table::Branch, row);
+    self.commit(format!("Remove {} row {}", Table::Branch, row + 1), vec![Op::Remove { row, data }])
+  }
+
+  pub fn can_undo(&self) -> bool {
+    !self.undo.is_empty()
+  }
+
+  pub fn can_redo(&self) -> bool {
+    !self.redo.is_empty()
+  }
+
+  /// Reverse the last edit. Returns false if there was nothing to undo.
+  pub fn undo(&mut self) -> Result<bool> {
+    let change = match self.undo.pop() {
+      Some(change) => change,
+      None => return Ok(false),
+    };
+    for op in change.ops.iter().rev() {
+      apply(&mut self.case, &op.inverse())?;
+    }
+    self.redo.push(change);
+    Ok(true)
+  }
+
+  /// Make the last undone edit again. Returns false if there was nothing to redo.
+  pub fn redo(&mut self) -> Result<bool> {
+    let change = match self.redo.pop() {
+      Some(change) => change,
+      None => return Ok(false),
+    };
+    for op in change.ops.iter() {
+      apply(&mut self.case, op)?;
+    }
+    self.undo.push(change);
+    Ok(true)
+  }
+
+  /// Descriptions of the edits that are in effect, oldest first.
+  pub fn history(&self) -> Vec<&str> {
+    self.undo.iter().map(|c| c.description.as_str()).collect()
+  }
+
+  /// The edits in effect as a MATLAB function `name` that makes them to an `mpc` struct, e.g. the original case
+  /// loaded with `loadcase`.
+  pub fn to_script(&self, name: &str) -> String {
+    let mut s = String::new();
+    writeln!(s, "function mpc = {}(mpc)", name).unwrap();
+    writeln!(s, "% Edits to {}", self.case.name).unwrap();
+    writeln!(s, "define_constants;").unwrap();
+    // `define_constants` has no DC line columns, so those are numbered.
+    let column = |table: Table, c: usize| match table {
+      Table::DcLine => (c + 1).to_string(),
+      _ => columns(table)[c].0.to_string(),
+    };
+    for change in self.undo.iter() {
+      writeln!(s, "\n% {}", change.description).unwrap();
+      for op in change.ops.iter() {
+        match op {
+          Op::Set { table, row, column: c, new, .. } => {
+            writeln!(s, "{}({}, {}) = {};", table, row + 1, column(*table, *c), number(*new)).unwrap();
+          },
+          Op::Insert { row, data } => {
+            let (prev, at) = (row.to_string(), (row + 1).to_string());
+            match data {
+              Row::Bus(bus, name) => {
+                insert_row(&mut s, "mpc.bus", &prev, &at, &bus.to_row());
+                if let Some(name) = name {
+                  let name = name.replace('\'', "''");
+                  writeln!(s, "mpc.bus_name = [mpc.bus_name(1:{}); {{'{}'}}; mpc.bus_name({}:end)];", prev, name, at)
+                    .unwrap();
+                }
+              },
+              Row::Gen(gen, cost) => {
+                insert_row(&mut s, "mpc.gen", &prev, &at, &gen.to_row());
+                if let Some(p) = cost.first() {
+                  insert_row(&mut s, "mpc.gencost", &prev, &at, &p.to_row());
+                }
+                // Reactive power costs follow the active power costs of all generators, the new one included.
+                if let Some(q) = cost.get(1) {
+                  writeln!(s, "ng = size(mpc.gen, 1);").unwrap();
+                  insert_row(&mut s, "mpc.gencost", &format!("ng+{}", prev), &format!("ng+{}", at), &q.to_row());
+                }
+              },
+              Row::Branch(branch) => insert_row(&mut s, "mpc.branch", &prev, &at, &branch.to_row()),
+              Row::DcLine(dcline) => insert_row(&mut s, "mpc.dcline", &prev, &at, &dcline.to_row()),
+            }
+          },
+          Op::Remove { row, data } => {
+            let table = data.table();
+            writeln!(s, "{}({}, :) = [];", table, row + 1).unwrap();
+            match data {
+              Row::Bus(_, Some(_)) => {
+                writeln!(s, "mpc.bus_name({}) = [];", row + 1).unwrap();
+              },
+              Row::Gen(_, cost) => {
+                if cost.len() == 2 {
+                  writeln!(s, "mpc.gencost(size(mpc.gen, 1) + {}, :) = [];", row + 2).unwrap();
+                }
+                if !cost.is_empty() {
+                  writeln!(s, "mpc.gencost({}, :) = [];", row + 1).unwrap();
+                }
+              },
+              _ => {},
+            }
+          },
+        }
+      }
+    }
+    s
+  }
+}
+
+// Write a MATLAB number, which has `Inf` and `NaN` where Rust has `inf` and `NaN`.
+fn number(v: f64) -> String {
+  if v.is_infinite() {
+    if v > 0.0 { "Inf" } else { "-Inf" }.to_string()
+  } else {
+    v.to_string()
+  }
+}
+
+// Insert `values` as row `at` of `matrix`, where `prev` is `at - 1`, padding with zeros to the matrix's width.
+fn insert_row(s: &mut String, matrix: &str, prev: &str, at: &str, values: &[f64]) {
+  let m = matrix;
+  writeln!(s, "{m} = [{m}(1:{}, :); zeros(1, size({m}, 2)); {m}({}:end, :)];", prev, at, m = m).unwrap();
+  let values = values.iter().map(|v| number(*v)).collect::<Vec<_>>().join(" ");
+  writeln!(s, "{}({}, 1:{}) = [{}];", m, at, values.split(' ').count(), values).unwrap();
+}
+
+#[cfg(test)]
+const CASE3: &str = r#"function mpc = case3
+mpc.version = '2';
+mpc.baseMVA = 100;
+mpc.bus = [
+	1	3	0	0	0	0	1	1	0	230	1	1.1	0.9;
+	2	1	50	10	0	0	1	1	0	230	1	1.1	0.9;
+	3	2	90	30	0	0	1	1	0	230	1	1.1	0.9;
+];
+mpc.gen = [
+	1	60	0	300	-300	1	100	1	250	10	0	0	0	0	0	0	0	0	0	0	0;
+	3	50	0	300	-300	1	100	1	250	10	0	0	0	0	0	0	0	0	0	0	0;
+];
+mpc.branch = [
+	1	2	0.01	0.1	0	250	250	300	0	0	1	-360	360;
+	2	3	0.01	0.1	0	250	250	300	0	0	1	-360	360;
+	1	3	0.01	0.1	0	250	250	300	0	0	1	-360	360;
+];
+mpc.gencost = [
+	2	0	0	2	40	0;
+	2	0	0	2	10	0;
+];
+"#;
+
+#[test]
+fn test_edit() {
+  let original = crate::case::case(CASE3).unwrap();
+  let mut e = CaseEditor::new(original.clone()).unwrap();
+  e.set_field(Table::Bus, 1, "VM", 0.98).unwrap();
+  assert_eq!(e.case().bus[1].voltage_mag, 0.98);
+  e.set_field(Table::Bus, 2, "BUS_I", 30.0).unwrap();
+  assert_eq!(
+    (e.case().gen[1].gen, e.case().branch[1].t_bus, e.case().branch[2].t_bus),
+    (BusId(30), BusId(30), BusId(30))
+  );
+  assert_eq!(e.set_field(Table::Bus, 0, "BUS_I", 2.0).unwrap_err().to_string(), "Bus 2 already exists");
+  assert_eq!(e.set_field(Table::Branch, 0, "T_BUS", 3.0).unwrap_err().to_string(), "Unknown bus 3");
+  assert_eq!(
+    e.set_field(Table::Bus, 0, "BUS_TYPE", 7.0).unwrap_err().to_string(),
+    "Invalid BUS_TYPE for mpc.bus row 1: Invalid bus type 7"
+  );
+  assert_eq!(e.set_field(Table::Gen, 0, "MU_PMAX", 1.0).unwrap_err().to_string(), "mpc.gen row 1 has no MU_PMAX value");
+  assert!(!e.toggle_status(Table::Branch, 2).unwrap());
+  assert!(!e.case().branch[2].in_service());
+
+  e.add_gen(Gen { gen: BusId(2), ..Gen::default() }, None).unwrap();
+  assert_eq!((e.case().gen.len(), e.case().gencost[2].cost.clone()), (3, vec![0.0]));
+  assert_eq!(
+    e.remove_bus(BusId(30), false).unwrap_err().to_string(),
+    "Bus 30 is still connected to mpc.gen row 2, mpc.branch row 2, mpc.branch row 3"
+  );
+  e.remove_bus(BusId(30), true).unwrap();
+  let c = e.case();
+  assert_eq!((c.bus.len(), c.gen.len(), c.gencost.len(), c.branch.len()), (2, 2, 2, 1));
+  assert_eq!(c.gencost.iter().map(|g| g.cost[0]).collect::<Vec<_>>(), vec![40.0, 0.0]);
+  assert_eq!(
+    e.history(),
+    vec![
+      "Set VM of mpc.bus row 2 to 0.98",
+      "Set BUS_I of mpc.bus row 3 to 30",
+      "Put mpc.branch row 3 out of service",
+      "Add generator at bus 2",
+      "Remove bus 30 and 3 attached elements",
+    ]
+  );
+
+  let edited = e.case().clone();
+  while e.undo().unwrap() {}
+  assert_eq!(e.case(), &original);
+  assert!(!e.can_undo() && e.can_redo());
+  while e.redo().unwrap() {}
+  assert_eq!(e.case(), &edited);
+  e.undo().unwrap();
+  e.remove_gen(0).unwrap();
+  assert!(!e.can_redo());
+  assert_eq!((e.case().gen.len(), e.case().gencost[0].cost.clone()), (2, vec![10.0, 0.0]));
+}
+
+#[test]
+fn test_to_script() {
+  let mut e = CaseEditor::new(crate::case::case(CASE3).unwrap()).unwrap();
+  e.set_field(Table::Bus, 1, "VM", 0.98).unwrap();
+  e.add_branch(Branch { f_bus: BusId(2), t_bus: BusId(3), br_x: 0.2, angmax: f64::INFINITY, ..Branch::default() })
+    .unwrap();
+  e.remove_gen(0).unwrap();
+  assert_eq!(
+    e.to_script("edits"),
+    "function mpc = edits(mpc)
+% Edits to case3
+define_constants;
+
+% Set VM of mpc.bus row 2 to 0.98
+mpc.bus(2, VM) = 0.98;
+
+% Add branch from bus 2 to bus 3
+mpc.branch = [mpc.branch(1:3, :); zeros(1, size(mpc.branch, 2)); mpc.branch(4:end, :)];
+mpc.branch(4, 1:13) = [2 3 0 0.2 0 0 0 0 0 0 1 -360 Inf];
+
+% Remove mpc.gen row 1
+mpc.gen(1, :) = [];
+mpc.gencost(1, :) = [];
+"
+  );
+}
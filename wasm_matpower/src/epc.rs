@@ -0,0 +1,416 @@
+// GE PSLF `.epc` reader
+
+// An EPC file is a list of `<kind> data [N]` sections, each record being key fields (bus numbers, quoted names, base
+// kV, ids), a `:` and then data fields. A record ending in `/` continues on the next line. Bus, branch, transformer,
+// generator, load and shunt sections are read; other sections are skipped.
+
+use std::collections::{BTreeMap, HashMap};
+
+use anyhow::{anyhow, Result};
+
+use crate::case::{Branch, Bus, BusId, BusType, Case, Gen, Version};
+
+// Whitespace separated tokens, keeping double quoted strings (which may hold spaces) together with their quotes.
+fn tokens(s: &str) -> Result<Vec<&str>> {
+  let mut v = vec![];
+  let mut rest = s.trim_start();
+  while !rest.is_empty() {
+    let end = if let Some(quoted) = rest.strip_prefix('"') {
+      quoted.find('"').map(|i| i + 2).ok_or_else(|| anyhow!("Unterminated string in {:?}", s))?
+    } else {
+      rest.find(char::is_whitespace).unwrap_or(rest.len())
+    };
+    v.push(&rest[..end]);
+    rest = rest[end..].trim_start();
+  }
+  Ok(v)
+}
+
+#[test]
+fn test_tokens() {
+  assert_eq!(
+    tokens(r#"  1 "BUS ONE " 230.00 : 1  1.02"#).unwrap(),
+    vec!["1", r#""BUS ONE ""#, "230.00", ":", "1", "1.02"]
+  );
+  assert!(tokens(r#"1 "BUS"#).is_err());
+}
+
+// One record, split at the `:`
+struct Record<'a> {
+  keys: Vec<&'a str>,
+  data: Vec<&'a str>,
+  line: usize,
+}
+
+impl<'a> Record<'a> {
+  fn parse(s: &'a str, line: usize) -> Result<Self> {
+    let t = tokens(s).map_err(|e| anyhow!("{} on line {}", e, line))?;
+    let colon = t.iter().position(|t| *t == ":").ok_or_else(|| anyhow!("Missing `:` on line {}", line))?;
+    Ok(Record { keys: t[..colon].to_vec(), data: t[colon + 1..].to_vec(), line })
+  }
+
+  fn number(&self, s: Option<&str>, what: &str) -> Result<f64> {
+    match s {
+      None => Ok(0.0),
+      Some(s) => s.parse().map_err(|_| anyhow!("Invalid {} {:?} on line {}", what, s, self.line)),
+    }
+  }
+
+  // Missing trailing data fields read as zero.
+  fn f64(&self, i: usize) -> Result<f64> {
+    self.number(self.data.get(i).copied(), &format!("field {}", i + 1))
+  }
+
+  fn bus(&self, b: Option<&&str>) -> Result<usize> {
+    let b = b.ok_or_else(|| anyhow!("Missing bus number on line {}", self.line))?;
+    b.parse().map_err(|_| anyhow!("Invalid bus number {:?} on line {}", b, self.line))
+  }
+
+  fn name(&self, i: usize) -> String {
+    self.keys.get(i).map_or("", |s| s.trim_matches('"').trim()).to_string()
+  }
+
+  fn in_service(&self) -> Result<bool> {
+    Ok(self.f64(0)? > 0.0)
+  }
+}
+
+// Record text and starting line number by section name
+type Sections = BTreeMap<String, Vec<(String, usize)>>;
+
+// System base and records by section, with continuation lines joined
+fn sections(s: &str) -> Result<(f64, Sections)> {
+  let mut base_mva = 100.0;
+  let mut sections = Sections::new();
+  let mut section: Option<String> = None;
+  let mut text = false;
+  let mut pending: Option<(String, usize)> = None;
+  for (i, line) in s.lines().enumerate() {
+    let line = line.trim_end();
+    // Title and comments run free form until a line starting with `!`.
+    if text {
+      text = !line.starts_with('!');
+      continue;
+    }
+    if let Some((mut record, start)) = pending.take() {
+      record.push(' ');
+      record.push_str(line);
+      match record.strip_suffix('/') {
+        Some(r) => pending = Some((r.to_string(), start)),
+        None => sections.entry(section.clone().unwrap_or_default()).or_default().push((record, start)),
+      }
+      continue;
+    }
+    let lower = line.to_lowercase();
+    let word = lower.split_whitespace().next().unwrap_or("");
+    if word == "title" || word == "comments" {
+      text = true;
+      section = None;
+    } else if word == "end" {
+      break;
+    } else if word == "solution" {
+      section = Some("solution".to_string());
+    } else if let Some(i) = lower.find(" data").filter(|_| line.starts_with(|c: char| c.is_ascii_alphabetic())) {
+      section = Some(lower[..i].trim().to_string());
+    } else if line.trim().is_empty() {
+      continue;
+    } else if section.as_deref() == Some("solution") {
+      // Solution parameters are `name value` pairs.
+      let mut t = line.split_whitespace();
+      if let (Some("sbase"), Some(v)) = (t.next(), t.next()) {
+        base_mva = v.parse().map_err(|_| anyhow!("Invalid sbase {:?} on line {}", v, i + 1))?;
+      }
+    } else if section.is_some() {
+      match line.strip_suffix('/') {
+        Some(r) => pending = Some((r.to_string(), i + 1)),
+        None => sections.entry(section.clone().unwrap_or_default()).or_default().push((line.to_string(), i + 1)),
+      }
+    }
+  }
+  if let Some((_, start)) = pending {
+    return Err(anyhow!("Unterminated continuation starting on line {}", start));
+  }
+  Ok((base_mva, sections))
+}
+
+/// Build a case from the contents of a PSLF `.epc` file.
+pub fn case(s: &str, name: &str) -> Result<Case> {
+  let (base_mva, sections) = sections(s)?;
+  let records = |section: &str| -> Result<Vec<Record>> {
+    sections.get(section).map_or(&[][..], |v| v.as_slice()).iter().map(|(s, line)| Record::parse(s, *line)).collect()
+  };
+  let mut c = Case {
+    name: name.to_string(),
+    version: Version::Version2,
+    base_mva,
+    bus: vec![],
+    gen: vec![],
+    gencost: vec![],
+    branch: vec![],
+    dcline: vec![],
+    bus_name: vec![],
+    extra: Default::default(),
+  };
+
+  // bus "name" kv : ty vsched volt angle ar zone vmax vmin ...
+  let mut buses = HashMap::new();
+  let mut vsched = HashMap::new();
+  for r in records("bus")? {
+    let idx = r.bus(r.keys.first())?;
+    let bus_type = match r.f64(0)? as i64 {
+      0 => BusType::Ref,
+      2 | -2 => BusType::PV,
+      _ => BusType::PQ,
+    };
+    let (v_max, v_min) = (r.f64(6)?, r.f64(7)?);
+    // A repeated bus number is kept for validation to report; records at it go to its first row.
+    vsched.entry(idx).or_insert(r.f64(1)?);
+    buses.entry(idx).or_insert(c.bus.len());
+    c.bus.push(Bus {
+      idx: BusId(idx),
+      bus_type,
+      area: (r.f64(4)? as usize).max(1),
+      zone: (r.f64(5)? as usize).max(1),
+      voltage_mag: r.f64(2)?,
+      voltage_ang: r.f64(3)?,
+      base_kv: r.number(r.keys.get(2).copied(), "base kV")?,
+      v_max: if v_max > 0.0 { v_max } else { 1.1 },
+      v_min: if v_min > 0.0 { v_min } else { 0.9 },
+      ..Bus::default()
+    });
+    c.bus_name.push(r.name(1));
+  }
+  if c.bus.is_empty() {
+    return Err(anyhow!("No buses found, expected a `bus data` section"));
+  }
+  let bus = |r: &Record, b: Option<&&str>| -> Result<usize> {
+    let b = r.bus(b)?;
+    buses.get(&b).copied().ok_or_else(|| anyhow!("Unknown bus {} on line {}", b, r.line))
+  };
+
+  // bus "name" kv "id" "long_id" : st mw mvar mw_i mvar_i mw_z mvar_z ...
+  // Constant current and impedance parts are taken at 1 p.u.
+  for r in records("load")? {
+    let b = bus(&r, r.keys.first())?;
+    if r.in_service()? {
+      c.bus[b].pd += r.f64(1)? + r.f64(3)? + r.f64(5)?;
+      c.bus[b].qd += r.f64(2)? + r.f64(4)? + r.f64(6)?;
+    }
+  }
+
+  // bus "name" kv "id" tbus "name" kv "ck" se "long_id" : st ar zone pu_mw pu_mvar ...
+  // Line shunts have no place in a MATPOWER branch and are lumped onto their bus.
+  for r in records("shunt")? {
+    let b = bus(&r, r.keys.first())?;
+    if r.in_service()? {
+      c.bus[b].shunt_conductance += r.f64(3)? * base_mva;
+      c.bus[b].shunt_susceptance += r.f64(4)? * base_mva;
+    }
+  }
+
+  // bus "name" kv "id" "long_id" : st igreg "name" kv prf qrf ar zone pgen pmax pmin qgen qmax qmin mbase ...
+  // Generators and branches may refer to buses that are not in the file, for validation to report.
+  for r in records("generator")? {
+    let b = r.bus(r.keys.first())?;
+    let reg = match r.number(r.data.get(1).copied(), "regulated bus")? as usize {
+      0 => b,
+      n => n,
+    };
+    let mbase = r.f64(14)?;
+    c.gen.push(Gen {
+      gen: BusId(b),
+      pg: r.f64(8)?,
+      qg: r.f64(11)?,
+      qmax: r.f64(12)?,
+      qmin: r.f64(13)?,
+      vg: vsched.get(&reg).copied().filter(|v| *v > 0.0).unwrap_or(1.0),
+      mbase: if mbase > 0.0 { mbase } else { base_mva },
+      gen_status: r.in_service()? as usize,
+      pmax: r.f64(9)?,
+      pmin: r.f64(10)?,
+      ..Gen::default()
+    });
+  }
+
+  // f "name" kv t "name" kv "ck" se "long_id" : st resist react charge rate1 rate2 rate3 ...
+  for r in records("branch")? {
+    c.branch.push(Branch {
+      f_bus: BusId(r.bus(r.keys.first())?),
+      t_bus: BusId(r.bus(r.keys.get(3))?),
+      br_r: r.f64(1)?,
+      br_x: r.f64(2)?,
+      br_b: r.f64(3)?,
+      rate_a: r.f64(4)?,
+      rate_b: r.f64(5)?,
+      rate_c: r.f64(6)?,
+      br_status: r.in_service()? as usize as f64,
+      ..Branch::default()
+    });
+  }
+
+  // f "name" kv t "name" kv "ck" "long_id" : st ty kreg "name" kv zt int "name" kv tert "name" kv ar zone tbase
+  //   ps_r ps_x pt_r pt_x ts_r ts_x vnomp vnoms vnomt anglp gmag bmag r1 r2 r3 r4 aloss tmax tmin vtmax vtmin stepp
+  //   tapp tapfp tapfs tapft anglet ...
+  // Impedances are on the transformer base and the winding nominal voltages. Magnetizing admittance is dropped.
+  let mut next_idx = c.bus.iter().map(|b| b.idx.0).max().unwrap_or(0);
+  for r in records("transformer")? {
+    let (p, s) = (bus(&r, r.keys.first())?, bus(&r, r.keys.get(3))?);
+    let tert = match r.f64(9)? as usize {
+      0 => None,
+      _ => Some(bus(&r, r.data.get(9))?),
+    };
+    let tbase = match r.f64(14)? {
+      v if v > 0.0 => v,
+      _ => base_mva,
+    };
+    let nominal = |i: usize, b: usize| -> Result<f64> {
+      Ok(match r.f64(i)? {
+        v if v > 0.0 => v,
+        _ => c.bus[b].base_kv,
+      })
+    };
+    let tap = |i: usize| -> Result<f64> {
+      Ok(match r.f64(i)? {
+        v if v > 0.0 => v,
+        _ => 1.0,
+      })
+    };
+    // Per unit on the system base and the to-side bus base kV
+    let z = |r_: f64, x: f64, kv_nom: f64, kv: f64| {
+      let k = if kv > 0.0 { (kv_nom / kv).powi(2) } else { 1.0 } * base_mva / tbase;
+      (r_ * k, x * k)
+    };
+    let ratio = |tap: f64, kv_nom: f64, kv: f64| if kv > 0.0 { tap * kv_nom / kv } else { tap };
+    let status = r.in_service()? as usize as f64;
+    let (rate_a, rate_b, rate_c) = (r.f64(27)?, r.f64(28)?, r.f64(29)?);
+    let (kvp, kvs) = (nominal(21, p)?, nominal(22, s)?);
+    let (tp, ts) = (ratio(tap(38)?, kvp, c.bus[p].base_kv), ratio(tap(39)?, kvs, c.bus[s].base_kv));
+    let winding = |f_bus: BusId, t_bus: BusId, (br_r, br_x): (f64, f64), tap: f64, shift: f64| Branch {
+      f_bus,
+      t_bus,
+      br_r,
+      br_x,
+      rate_a,
+      rate_b,
+      rate_c,
+      tap,
+      shift,
+      br_status: status,
+      ..Branch::default()
+    };
+    match tert {
+      None => {
+        let zps = z(r.f64(15)?, r.f64(16)?, kvs, c.bus[s].base_kv);
+        c.branch.push(winding(c.bus[p].idx, c.bus[s].idx, zps, tp / ts, r.f64(24)?));
+      },
+      // Three winding transformers become a star of three branches around a new bus.
+      Some(t) => {
+        let kvt = nominal(23, t)?;
+        let tt = ratio(tap(40)?, kvt, c.bus[t].base_kv);
+        // The star bus is on the primary bus base kV at the primary nominal voltage.
+        let star_ratio = ratio(1.0, kvp, c.bus[p].base_kv);
+        // Pairwise impedances on the primary nominal voltage, split into star impedances
+        let zps = z(r.f64(15)?, r.f64(16)?, kvp, c.bus[p].base_kv);
+        let zpt = z(r.f64(17)?, r.f64(18)?, kvp, c.bus[p].base_kv);
+        let zts = z(r.f64(19)?, r.f64(20)?, kvp, c.bus[p].base_kv);
+        let star = |a: (f64, f64), b: (f64, f64), c: (f64, f64)| ((a.0 + b.0 - c.0) / 2.0, (a.1 + b.1 - c.1) / 2.0);
+        next_idx += 1;
+        let w = [
+          winding(c.bus[p].idx, BusId(next_idx), star(zps, zpt, zts), tp / star_ratio, r.f64(24)?),
+          winding(c.bus[s].idx, BusId(next_idx), star(zps, zts, zpt), ts / star_ratio, 0.0),
+          winding(c.bus[t].idx, BusId(next_idx), star(zpt, zts, zps), tt / star_ratio, r.f64(41)?),
+        ];
+        c.branch.extend(w);
+        let (base_kv, area, zone) = (c.bus[p].base_kv, c.bus[p].area, c.bus[p].zone);
+        c.bus.push(Bus { idx: BusId(next_idx), base_kv, area, zone, ..Bus::default() });
+        c.bus_name.push(format!("{}_STAR", c.bus_name[p]));
+      },
+    }
+  }
+  Ok(c)
+}
+
+/// True if `s` looks like a PSLF `.epc` file.
+pub fn is_epc(s: &str) -> bool {
+  s.lines().any(|l| l.to_lowercase().starts_with("bus data"))
+}
+
+#[cfg(test)]
+const EPC: &str = r#"title
+Three bus test case
+!
+comments
+Made up for the tests
+!
+solution parameters
+tap     1
+sbase   100.0
+bus data  [    4]            ty  vsched   volt     angle    ar zone  vmax   vmin
+    1 "ONE     " 230.00  :  0 1.0400 1.0400    0.0000   1    1 1.1000 0.9000
+    2 "TWO     " 230.00  :  1 0.0000 1.0100   -2.5000   1    2 1.1000 0.9000
+    3 "THREE   " 115.00  :  2 1.0200 1.0200   -4.0000   2    2 1.0500 0.9500
+    4 "FOUR    "  13.80  :  1 0.0000 1.0000   -4.0000   2    2 1.0500 0.9500
+branch data  [    2]        ck  se  long_id    st resist   react    charge   rate1  rate2  rate3
+    1 "ONE     " 230.00     2 "TWO     " 230.00 "1 "   1  "  " :  1 0.001000 0.010000 0.020000 100.0 110.0 /
+   120.0
+    1 "ONE     " 230.00     2 "TWO     " 230.00 "2 "   1  "  " :  0 0.001000 0.010000 0.020000 100.0 110.0 120.0
+transformer data  [    2]
+    2 "TWO     " 230.00     3 "THREE   " 115.00 "1 " "  " :  1  1     3 "THREE   " 115.00  1     2 "TWO     " 230.00 /
+    0 "        " 0.00   2  2  200.0 0.002000 0.080000 0.000000 0.000000 0.000000 0.000000 230.00 115.00 0.00 /
+    -30.0 0.0 0.0 150.0 160.0 170.0 0.0 0.0 1.1 0.9 1.1 0.9 0.00625 0.0 1.0500 1.0000 1.0000 0.0
+    2 "TWO     " 230.00     3 "THREE   " 115.00 "2 " "  " :  1  1     3 "THREE   " 115.00  1     2 "TWO     " 230.00 /
+    4 "FOUR    " 13.80   2  2  100.0 0.0 0.1 0.0 0.2 0.0 0.3 230.00 115.00 13.80 0.0 0.0 0.0 /
+    100.0 100.0 100.0 0.0 0.0 1.1 0.9 1.1 0.9 0.0 0.0 1.0 1.0 1.0 0.0
+generator data  [    2]    id   long_id    st ---no--     reg_name       prf  qrf  ar zone   pgen   pmax   pmin   qgen   qmax   qmin   mbase
+    1 "ONE     " 230.00 "1 " "  " :  1     0 "        "   0.00 1.0 1.0   1    1 150.0 300.0 10.0 20.0 100.0 -100.0 200.0
+    3 "THREE   " 115.00 "1 " "  " :  0     0 "        "   0.00 1.0 1.0   2    2  50.0 100.0  0.0  5.0  50.0  -50.0   0.0
+load data  [    2]    id   long_id    st      mw      mvar    mw_i    mvar_i  mw_z    mvar_z
+    2 "TWO     " 230.00 "1 " "  " :  1 100.0 30.0 10.0 3.0 0.0 0.0   1    2
+    2 "TWO     " 230.00 "2 " "  " :  0 100.0 30.0 0.0 0.0 0.0 0.0   1    2
+shunt data  [    1]
+    3 "THREE   " 115.00 "1 "     0 "        "   0.00 "  "  0  "  " :  1   2    2 0.0 0.5
+area data  [    2]
+    1 "NORTH" 0.0 0.0 0.0 0.0
+    2 "SOUTH" 0.0 0.0 0.0 0.0
+end
+"#;
+
+#[test]
+fn test_case() {
+  let c = case(EPC, "test").unwrap();
+  assert_eq!(c.bus_name, vec!["ONE", "TWO", "THREE", "FOUR", "TWO_STAR"]);
+  assert_eq!(c.bus.iter().map(|b| b.bus_type).collect::<Vec<_>>()[..4], [BusType::Ref, BusType::PQ, BusType::PV, BusType::PQ]);
+  assert_eq!((c.bus[1].voltage_mag, c.bus[1].voltage_ang, c.bus[1].zone), (1.01, -2.5, 2));
+  assert_eq!((c.bus[1].pd, c.bus[1].qd), (110.0, 33.0));
+  assert_eq!(c.bus[2].shunt_susceptance, 50.0);
+
+  assert_eq!(c.gen.len(), 2);
+  assert_eq!((c.gen[0].gen.0, c.gen[0].pg, c.gen[0].vg, c.gen[0].gen_status), (1, 150.0, 1.04, 1));
+  assert_eq!((c.gen[1].gen.0, c.gen[1].vg, c.gen[1].mbase, c.gen[1].gen_status), (3, 1.02, 100.0, 0));
+
+  assert_eq!(c.branch.len(), 6);
+  assert_eq!((c.branch[0].br_x, c.branch[0].rate_c, c.branch[0].br_status), (0.01, 120.0, 1.0));
+  assert_eq!(c.branch[1].br_status, 0.0);
+  let t = &c.branch[2];
+  assert_eq!((t.f_bus.0, t.t_bus.0, t.rate_a, t.shift), (2, 3, 150.0, -30.0));
+  assert!((t.br_x - 0.04).abs() < 1e-12 && (t.tap - 1.05).abs() < 1e-12);
+  let star = c.bus[4].idx.0;
+  assert_eq!(c.branch[3..].iter().map(|b| (b.f_bus.0, b.t_bus.0)).collect::<Vec<_>>(), vec![(2, star), (3, star), (4, star)]);
+  assert!((c.branch[3].br_x - 0.0).abs() < 1e-12 && (c.branch[4].br_x - 0.1).abs() < 1e-12 && (c.branch[5].br_x - 0.2).abs() < 1e-12);
+
+  // A 240 kV primary winding at a 230 kV bus
+  let c = case(&EPC.replace("230.00 115.00 13.80", "240.00 115.00 13.80"), "test").unwrap();
+  let taps = c.branch[3..].iter().map(|b| b.tap).collect::<Vec<_>>();
+  assert!((taps[0] - 1.0).abs() < 1e-12 && (taps[1] - 230.0 / 240.0).abs() < 1e-12 && (taps[2] - 230.0 / 240.0).abs() < 1e-12);
+}
+
+#[test]
+fn test_case_errors() {
+  assert!(case("bus data [0]\n", "test").unwrap_err().to_string().contains("No buses found"));
+  let bus = "bus data [1]\n 1 \"ONE\" 230.0 : 0 1.0 1.0 0.0 1 1\n";
+  let c = case(&format!("{}branch data [1]\n 1 \"ONE\" 230.0 9 \"NINE\" 230.0 \"1\" : 1 0 0.1 0\n", bus), "t").unwrap();
+  assert_eq!(c.dangling_references()[0].bus, BusId(9));
+  let e = case(&format!("{}load data [1]\n 9 \"NINE\" 230.0 \"1\" \"\" : 1 10 5 0 0 0 0\n", bus), "t");
+  assert_eq!(e.unwrap_err().to_string(), "Unknown bus 9 on line 4");
+  assert!(case("bus data [1]\n 1 \"ONE\" 230.0 : 0 1.0 /\n", "t").unwrap_err().to_string().contains("Unterminated"));
+}
@@ -0,0 +1,254 @@
+// Bus coordinates and GeoJSON export
+
+// MATPOWER cases carry no coordinates. Synthetic grids (e.g. ACTIVSg) ship substation lat/lon as side files, which
+// are attached to buses by bus number here.
+
+use std::collections::HashMap;
+
+use anyhow::{anyhow, Result};
+use serde_json::{json, Map, Value};
+
+use crate::case::{BusId, Case};
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum Column {
+  Bus,
+  Lat,
+  Lon,
+}
+
+fn column(header: &str) -> Option<Column> {
+  let h = header.chars().filter(|c| c.is_ascii_alphanumeric()).collect::<String>().to_lowercase();
+  match h.as_str() {
+    "bus" | "busi" | "busnum" | "busnumber" | "busid" | "number" | "id" => Some(Column::Bus),
+    "lat" | "latitude" => Some(Column::Lat),
+    "lon" | "lng" | "long" | "longitude" => Some(Column::Lon),
+    h if h.ends_with("latitude") => Some(Column::Lat),
+    h if h.ends_with("longitude") => Some(Column::Lon),
+    _ => None,
+  }
+}
+
+#[test]
+fn test_column() {
+  assert_eq!(column("Bus Number"), Some(Column::Bus));
+  assert_eq!(column("bus_i"), Some(Column::Bus));
+  assert_eq!(column("Substation Latitude"), Some(Column::Lat));
+  assert_eq!(column("lng"), Some(Column::Lon));
+  assert_eq!(column("Name"), None);
+}
+
+fn bus_number(s: &str) -> Option<usize> {
+  let s = s.trim();
+  s.parse::<usize>().ok().or_else(|| s.parse::<f64>().ok().filter(|f| f.fract() == 0.0 && *f >= 0.0).map(|f| f as usize))
+}
+
+fn coordinates_csv(s: &str) -> Result<Vec<(usize, f64, f64)>> {
+  let mut reader = csv::ReaderBuilder::new().has_headers(false).flexible(true).trim(csv::Trim::All).from_reader(s.as_bytes());
+  let mut columns: Option<(usize, usize, usize)> = None;
+  let mut v = vec![];
+  for (row, record) in reader.records().enumerate() {
+    let record = record?;
+    match columns {
+      // Exports from PowerWorld and friends may have preamble lines before the header, so look for the first row
+      // naming all three columns.
+      None => {
+        let kinds = record.iter().map(column).collect::<Vec<_>>();
+        let find = |k| kinds.iter().position(|c| *c == Some(k));
+        if let (Some(b), Some(lat), Some(lon)) = (find(Column::Bus), find(Column::Lat), find(Column::Lon)) {
+          columns = Some((b, lat, lon));
+        }
+      },
+      Some((b, lat, lon)) => {
+        let (b, lat, lon) = match (record.get(b), record.get(lat), record.get(lon)) {
+          (Some(b), Some(lat), Some(lon)) => (b, lat, lon),
+          _ => continue,
+        };
+        if b.is_empty() {
+          continue;
+        }
+        let b = bus_number(b).ok_or_else(|| anyhow!("Invalid bus number {:?} on line {}", b, row + 1))?;
+        let lat = lat.parse::<f64>().map_err(|e| anyhow!("Invalid latitude {:?} on line {}: {}", lat, row + 1, e))?;
+        let lon = lon.parse::<f64>().map_err(|e| anyhow!("Invalid longitude {:?} on line {}: {}", lon, row + 1, e))?;
+        v.push((b, lat, lon));
+      },
+    }
+  }
+  if columns.is_none() {
+    return Err(anyhow!("Unable to find bus, latitude and longitude columns"));
+  }
+  Ok(v)
+}
+
+#[test]
+fn test_coordinates_csv() {
+  let data = "Bus\nBus Number,Name,Substation Latitude,Substation Longitude\n1,\"ODESSA, 2\",31.9,-102.3\n2,PRESIDIO,29.6,-104.4\n";
+  assert_eq!(coordinates_csv(data).unwrap(), vec![(1, 31.9, -102.3), (2, 29.6, -104.4)]);
+  assert!(coordinates_csv("a,b\n1,2\n").is_err());
+  assert!(coordinates_csv("bus,lat,lon\nx,1,2\n").is_err());
+}
+
+fn lat_lon(v: &Value) -> Option<(f64, f64)> {
+  match v {
+    Value::Array(a) if a.len() == 2 => Some((a[0].as_f64()?, a[1].as_f64()?)),
+    Value::Object(o) => {
+      let mut lat = None;
+      let mut lon = None;
+      for (k, v) in o {
+        match column(k) {
+          Some(Column::Lat) => lat = v.as_f64(),
+          Some(Column::Lon) => lon = v.as_f64(),
+          _ => (),
+        }
+      }
+      Some((lat?, lon?))
+    },
+    _ => None,
+  }
+}
+
+fn coordinates_json(s: &str) -> Result<Vec<(usize, f64, f64)>> {
+  let value: Value = serde_json::from_str(s)?;
+  let mut v = vec![];
+  match value {
+    // [{"bus": 1, "lat": 31.9, "lon": -102.3}, ...]
+    Value::Array(a) => {
+      for (i, entry) in a.iter().enumerate() {
+        let b = entry
+          .as_object()
+          .and_then(|o| o.iter().find(|(k, _)| column(k) == Some(Column::Bus)))
+          .and_then(|(_, b)| b.as_u64().map(|b| b as usize).or_else(|| b.as_str().and_then(bus_number)))
+          .ok_or_else(|| anyhow!("Missing bus number in entry {}", i))?;
+        let (lat, lon) = lat_lon(entry).ok_or_else(|| anyhow!("Missing latitude or longitude in entry {}", i))?;
+        v.push((b, lat, lon));
+      }
+    },
+    // {"1": [31.9, -102.3], "2": {"lat": 29.6, "lon": -104.4}, ...}
+    Value::Object(o) => {
+      for (k, entry) in o.iter() {
+        let b = bus_number(k).ok_or_else(|| anyhow!("Invalid bus number {:?}", k))?;
+        let (lat, lon) = lat_lon(entry).ok_or_else(|| anyhow!("Missing latitude or longitude for bus {}", k))?;
+        v.push((b, lat, lon));
+      }
+    },
+    _ => return Err(anyhow!("Expected an array or an object of bus coordinates")),
+  }
+  Ok(v)
+}
+
+#[test]
+fn test_coordinates_json() {
+  let data = r#"[{"bus": 1, "latitude": 31.9, "longitude": -102.3}, {"bus": "2", "lat": 29.6, "lon": -104.4}]"#;
+  assert_eq!(coordinates_json(data).unwrap(), vec![(1, 31.9, -102.3), (2, 29.6, -104.4)]);
+  let data = r#"{"1": [31.9, -102.3], "2": {"lat": 29.6, "lng": -104.4}}"#;
+  assert_eq!(coordinates_json(data).unwrap(), vec![(1, 31.9, -102.3), (2, 29.6, -104.4)]);
+  assert!(coordinates_json(r#"[{"lat": 1, "lon": 2}]"#).is_err());
+}
+
+/// Parse a bus coordinate side file, either CSV with a header row or JSON.
+pub fn coordinates(s: &str) -> Result<Vec<(usize, f64, f64)>> {
+  match s.trim_start().chars().next() {
+    Some('[') | Some('{') => coordinates_json(s),
+    _ => coordinates_csv(s),
+  }
+}
+
+fn point(coords: (f64, f64)) -> Value {
+  let (lat, lon) = coords;
+  json!({ "type": "Point", "coordinates": [lon, lat] })
+}
+
+fn line_string(from: (f64, f64), to: (f64, f64)) -> Value {
+  json!({ "type": "LineString", "coordinates": [[from.1, from.0], [to.1, to.0]] })
+}
+
+fn feature(geometry: Value, kind: &str, properties: Value) -> Value {
+  let mut properties = match properties {
+    Value::Object(o) => o,
+    _ => Map::new(),
+  };
+  properties.remove("coords");
+  properties.insert("kind".to_string(), Value::from(kind));
+  json!({ "type": "Feature", "geometry": geometry, "properties": properties })
+}
+
+impl Case {
+  /// Attach `(lat, lon)` from a coordinate side file to buses by bus number. Returns the number of buses updated;
+  /// entries for buses not in the case are ignored.
+  pub fn attach_coordinates(&mut self, s: &str) -> Result<usize> {
+    let coords = coordinates(s)?.into_iter().map(|(b, lat, lon)| (BusId(b), (lat, lon))).collect::<HashMap<_, _>>();
+    let mut n = 0;
+    for bus in self.bus.iter_mut() {
+      if let Some(c) = coords.get(&bus.idx) {
+        bus.coords = Some(*c);
+        n += 1;
+      }
+    }
+    Ok(n)
+  }
+
+  /// GeoJSON `FeatureCollection` with buses as `Point`s and branches and dclines as `LineString`s. Elements without
+  /// coordinates on every bus they touch are left out.
+  pub fn to_geojson(&self) -> Value {
+    let coords = self.bus.iter().filter_map(|b| b.coords.map(|c| (b.idx, c))).collect::<HashMap<_, _>>();
+    let mut features = vec![];
+    for (i, bus) in self.bus.iter().enumerate() {
+      if let Some(c) = bus.coords {
+        let mut properties = serde_json::to_value(bus).unwrap_or(Value::Null);
+        if let (Value::Object(o), Some(name)) = (&mut properties, self.bus_name.get(i)) {
+          o.insert("name".to_string(), Value::from(name.as_str()));
+        }
+        features.push(feature(point(c), "bus", properties));
+      }
+    }
+    for branch in self.branch.iter() {
+      if let (Some(f), Some(t)) = (coords.get(&branch.f_bus), coords.get(&branch.t_bus)) {
+        features.push(feature(line_string(*f, *t), "branch", serde_json::to_value(branch).unwrap_or(Value::Null)));
+      }
+    }
+    for dcline in self.dcline.iter() {
+      if let (Some(f), Some(t)) = (coords.get(&dcline.f_bus), coords.get(&dcline.t_bus)) {
+        features.push(feature(line_string(*f, *t), "dcline", serde_json::to_value(dcline).unwrap_or(Value::Null)));
+      }
+    }
+    json!({ "type": "FeatureCollection", "name": self.name, "features": features })
+  }
+}
+
+#[test]
+fn test_to_geojson() {
+  let mut c = crate::case::case(
+    r#"function mpc = case3
+mpc.version = '2';
+mpc.baseMVA = 100;
+mpc.bus = [
+	1	3	0	0	0	0	1	1	0	230	1	1.1	0.9;
+	2	1	90	30	0	0	1	1	0	230	1	1.1	0.9;
+	3	1	100	35	0	0	1	1	0	230	1	1.1	0.9;
+];
+mpc.gen = [
+	1	0	0	300	-300	1	100	1	250	10	0	0	0	0	0	0	0	0	0	0	0;
+];
+mpc.branch = [
+	1	2	0	0.0576	0	250	250	250	0	0	1	-360	360;
+	2	3	0.017	0.092	0.158	250	250	250	0	0	1	-360	360;
+];
+mpc.bus_name = {
+	'One';
+	'Two';
+	'Three';
+};
+"#,
+  )
+  .unwrap();
+  assert_eq!(c.attach_coordinates("bus,lat,lon\n1,30.0,-100.0\n2,31.0,-101.0\n99,0,0\n").unwrap(), 2);
+  let g = c.to_geojson();
+  let features = g["features"].as_array().unwrap();
+  assert_eq!(features.len(), 3);
+  assert_eq!(features[0]["geometry"]["coordinates"], json!([-100.0, 30.0]));
+  assert_eq!(features[0]["properties"]["name"], "One");
+  assert_eq!(features[0]["properties"]["kind"], "bus");
+  assert!(features[0]["properties"].get("coords").is_none());
+  assert_eq!(features[2]["geometry"]["type"], "LineString");
+  assert_eq!(features[2]["properties"]["br_x"], 0.0576);
+}
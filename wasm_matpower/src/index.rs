@@ -0,0 +1,285 @@
+// Bus lookup and referential integrity
+
+// Generators, branches and DC lines refer to buses by number. `BusIndex` maps bus numbers to positions in `Case::bus`
+// once, so lookups don't scan the bus table, and finds references to bus numbers that are not in it.
+
+use std::{collections::HashMap, fmt};
+
+use anyhow::{anyhow, Result};
+use serde::Serialize;
+
+use crate::case::{Bus, BusId, BusType, Case};
+
+/// Where a row is in the text it was parsed from. `line` and `column` are 1-based; `len` is in bytes and runs to the
+/// row's closing `;`.
+#[derive(Serialize, Debug, PartialEq, Eq, Clone, Copy)]
+pub struct SourceSpan {
+  pub line: u32,
+  pub column: usize,
+  pub len: usize,
+}
+
+impl fmt::Display for SourceSpan {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "line {}, column {}", self.line, self.column)
+  }
+}
+
+// Spans of the rows of each table, in table order. Empty when the case did not come from text.
+#[derive(Debug, Default)]
+pub(crate) struct RowSpans {
+  pub(crate) bus: Vec<SourceSpan>,
+  pub(crate) gen: Vec<SourceSpan>,
+  pub(crate) branch: Vec<SourceSpan>,
+  pub(crate) dcline: Vec<SourceSpan>,
+}
+
+/// The tables that hold buses or refer to them.
+#[derive(Serialize, Debug, PartialEq, Eq, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum Table {
+  Bus,
+  Gen,
+  Branch,
+  DcLine,
+}
+
+impl fmt::Display for Table {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Table::Bus => write!(f, "mpc.bus"),
+      Table::Gen => write!(f, "mpc.gen"),
+      Table::Branch => write!(f, "mpc.branch"),
+      Table::DcLine => write!(f, "mpc.dcline"),
+    }
+  }
+}
+
+impl std::str::FromStr for Table {
+  type Err = anyhow::Error;
+
+  fn from_str(s: &str) -> Result<Table> {
+    match s.trim_start_matches("mpc.") {
+      "bus" => Ok(Table::Bus),
+      "gen" => Ok(Table::Gen),
+      "branch" => Ok(Table::Branch),
+      "dcline" => Ok(Table::DcLine),
+      s => Err(anyhow!("Unknown table {:?}", s)),
+    }
+  }
+}
+
+/// A row referring to a bus number that is not in `mpc.bus`. `row` is 1-based, as in MATLAB.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct DanglingReference {
+  pub table: Table,
+  pub row: usize,
+  pub bus: BusId,
+  pub span: Option<SourceSpan>,
+}
+
+impl fmt::Display for DanglingReference {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "{} row {} refers to bus {}, which is not in mpc.bus", self.table, self.row, self.bus)?;
+    if let Some(span) = self.span {
+      write!(f, " ({})", span)?;
+    }
+    Ok(())
+  }
+}
+
+/// Positions of buses in `Case::bus` by bus number.
+#[derive(Debug, Clone)]
+pub struct BusIndex {
+  position: HashMap<BusId, usize>,
+}
+
+impl BusIndex {
+  /// Index `bus`, failing on the first bus number that appears twice.
+  pub fn new(bus: &[Bus]) -> Result<BusIndex> {
+    let mut position = HashMap::with_capacity(bus.len());
+    for (i, b) in bus.iter().enumerate() {
+      if let Some(j) = position.insert(b.idx, i) {
+        return Err(anyhow!("Duplicate bus {} in mpc.bus rows {} and {}", b.idx, j + 1, i + 1));
+      }
+    }
+    Ok(BusIndex { position })
+  }
+
+  // Index `bus`, keeping the first row of a bus number that appears twice.
+  pub(crate) fn first_rows(bus: &[Bus]) -> BusIndex {
+    let mut position = HashMap::with_capacity(bus.len());
+    for (i, b) in bus.iter().enumerate() {
+      position.entry(b.idx).or_insert(i);
+    }
+    BusIndex { position }
+  }
+
+  /// Position in `Case::bus` of the bus numbered `id`.
+  pub fn position(&self, id: BusId) -> Option<usize> {
+    self.position.get(&id).copied()
+  }
+
+  pub fn contains(&self, id: BusId) -> bool {
+    self.position.contains_key(&id)
+  }
+
+  pub fn len(&self) -> usize {
+    self.position.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.position.is_empty()
+  }
+
+  /// Generator, branch and DC line rows of `case` that refer to buses missing from the index, in table order.
+  pub fn dangling(&self, case: &Case) -> Vec<DanglingReference> {
+    self.dangling_with_spans(case, &RowSpans::default())
+  }
+
+  pub(crate) fn dangling_with_spans(&self, case: &Case, spans: &RowSpans) -> Vec<DanglingReference> {
+    let mut v = vec![];
+    let mut check = |table: Table, row: usize, bus: BusId, spans: &[SourceSpan]| {
+      if !self.contains(bus) {
+        v.push(DanglingReference { table, row: row + 1, bus, span: spans.get(row).copied() });
+      }
+    };
+    for (i, g) in case.gen.iter().enumerate() {
+      check(Table::Gen, i, g.gen, &spans.gen);
+    }
+    for (i, br) in case.branch.iter().enumerate() {
+      check(Table::Branch, i, br.f_bus, &spans.branch);
+      check(Table::Branch, i, br.t_bus, &spans.branch);
+    }
+    for (i, d) in case.dcline.iter().enumerate() {
+      check(Table::DcLine, i, d.f_bus, &spans.dcline);
+      check(Table::DcLine, i, d.t_bus, &spans.dcline);
+    }
+    v
+  }
+}
+
+impl Case {
+  /// Index of the case's buses by bus number.
+  pub fn bus_index(&self) -> Result<BusIndex> {
+    BusIndex::new(&self.bus)
+  }
+
+  /// Generator, branch and DC line rows that refer to bus numbers not in `mpc.bus`, in table order. Unlike
+  /// `bus_index`, this works on cases with duplicate bus numbers.
+  pub fn dangling_references(&self) -> Vec<DanglingReference> {
+    BusIndex::first_rows(&self.bus).dangling(self)
+  }
+
+  /// Rows of the buses in each island, the buses connected by in-service branches, in order of each island's first
+  /// bus. Isolated buses, and rows repeating an earlier bus number, are in no island.
+  pub fn islands(&self) -> Vec<Vec<usize>> {
+    let mut position = HashMap::new();
+    for (i, b) in self.bus.iter().enumerate().filter(|(_, b)| b.bus_type != BusType::Isolated) {
+      position.entry(b.idx).or_insert(i);
+    }
+    let mut parent = (0..self.bus.len()).collect::<Vec<_>>();
+    fn root(parent: &mut [usize], mut i: usize) -> usize {
+      while parent[i] != i {
+        parent[i] = parent[parent[i]];
+        i = parent[i];
+      }
+      i
+    }
+    for br in self.branch.iter().filter(|br| br.in_service()) {
+      if let (Some(f), Some(t)) = (position.get(&br.f_bus), position.get(&br.t_bus)) {
+        let (f, t) = (root(&mut parent, *f), root(&mut parent, *t));
+        parent[f.max(t)] = f.min(t);
+      }
+    }
+    let mut islands: Vec<Vec<usize>> = vec![];
+    let mut island_of = HashMap::new();
+    for (i, _) in self.bus.iter().enumerate().filter(|(i, b)| position.get(&b.idx) == Some(i)) {
+      let r = root(&mut parent, i);
+      let k = *island_of.entry(r).or_insert_with(|| {
+        islands.push(vec![]);
+        islands.len() - 1
+      });
+      islands[k].push(i);
+    }
+    islands
+  }
+
+  // Fails on duplicate bus numbers, or with one line per dangling reference.
+  pub(crate) fn check_references(&self, spans: &RowSpans) -> Result<BusIndex> {
+    let index = self.bus_index()?;
+    let dangling = index.dangling_with_spans(self, spans);
+    if dangling.is_empty() {
+      Ok(index)
+    } else {
+      Err(anyhow!("{}", dangling.iter().map(ToString::to_string).collect::<Vec<_>>().join("\n")))
+    }
+  }
+
+  // MATPOWER takes gencost rows to be generator costs in generator order, then reactive power costs if there are twice
+  // as many rows as generators.
+  pub(crate) fn check_gencost_rows(&self) -> Result<()> {
+    let (ng, n) = (self.gen.len(), self.gencost.len());
+    if n != 0 && n != ng && n != 2 * ng {
+      return Err(anyhow!("Expected {} or {} cost rows for {} generators, found {}", ng, 2 * ng, ng, n));
+    }
+    Ok(())
+  }
+}
+
+#[test]
+fn test_bus_index() {
+  use crate::case::{Branch, Gen};
+
+  let mut c = crate::builder::CaseBuilder::new("case")
+    .bus(Bus { idx: BusId(10), ..Bus::default() })
+    .bus(Bus { idx: BusId(20), ..Bus::default() })
+    .gen(Gen { gen: BusId(20), ..Gen::default() })
+    .branch(Branch { f_bus: BusId(10), t_bus: BusId(20), ..Branch::default() })
+    .build()
+    .unwrap();
+  let index = c.bus_index().unwrap();
+  assert_eq!((index.position(BusId(20)), index.position(BusId(1)), index.len()), (Some(1), None, 2));
+  assert!(index.dangling(&c).is_empty());
+
+  c.branch[0].t_bus = BusId(9999);
+  c.gen[0].gen = BusId(3);
+  assert_eq!(
+    index.dangling(&c),
+    vec![
+      DanglingReference { table: Table::Gen, row: 1, bus: BusId(3), span: None },
+      DanglingReference { table: Table::Branch, row: 1, bus: BusId(9999), span: None },
+    ]
+  );
+  let spans = RowSpans { branch: vec![SourceSpan { line: 12, column: 2, len: 40 }], ..RowSpans::default() };
+  assert_eq!(
+    c.check_references(&spans).unwrap_err().to_string(),
+    "mpc.gen row 1 refers to bus 3, which is not in mpc.bus\n\
+     mpc.branch row 1 refers to bus 9999, which is not in mpc.bus (line 12, column 2)"
+  );
+
+  c.bus[1].idx = BusId(10);
+  assert_eq!(c.bus_index().unwrap_err().to_string(), "Duplicate bus 10 in mpc.bus rows 1 and 2");
+  assert_eq!(c.dangling_references().iter().map(|d| d.bus).collect::<Vec<_>>(), vec![BusId(3), BusId(9999)]);
+}
+
+#[test]
+fn test_islands() {
+  use crate::case::Branch;
+
+  let bus = |n: usize, bus_type: BusType| Bus { idx: BusId(n), bus_type, ..Bus::default() };
+  let branch = |f: usize, t: usize| Branch { f_bus: BusId(f), t_bus: BusId(t), ..Branch::default() };
+  let c = crate::builder::CaseBuilder::new("case")
+    .bus(bus(1, BusType::Ref))
+    .bus(bus(2, BusType::PQ))
+    .bus(bus(3, BusType::PQ))
+    .bus(bus(4, BusType::Isolated))
+    .bus(bus(5, BusType::PV))
+    .branch(branch(1, 2))
+    .branch(branch(3, 5))
+    .branch(branch(2, 4))
+    .branch(Branch { br_status: 0.0, ..branch(2, 3) })
+    .build()
+    .unwrap();
+  assert_eq!(c.islands(), vec![vec![0, 1], vec![2, 4]]);
+}
@@ -0,0 +1,131 @@
+// MATPOWER-JSON, the layout of MATLAB's `jsonencode(mpc)`
+
+// Tables are arrays of numeric rows in MATPOWER column order, so `bus(:, VM)` style indexing works unchanged. MATLAB
+// writes a single row as a flat array and a scalar as a number, and encodes NaN and Inf as `null`, which reads back
+// as NaN.
+
+use anyhow::{anyhow, Result};
+use serde_json::{json, Map, Value};
+
+use crate::case::{Case, Tables};
+
+fn number(v: &Value) -> Result<f64> {
+  match v {
+    Value::Number(n) => n.as_f64().ok_or_else(|| anyhow!("Invalid number {}", n)),
+    Value::Null => Ok(f64::NAN),
+    v => Err(anyhow!("Expected a number, found {}", v)),
+  }
+}
+
+fn matrix(mpc: &Map<String, Value>, field: &str) -> Result<Vec<Vec<f64>>> {
+  let row = |v: &Vec<Value>| v.iter().map(number).collect::<Result<Vec<_>>>();
+  let m = match mpc.get(field) {
+    None => return Ok(vec![]),
+    Some(Value::Array(rows)) if rows.iter().all(Value::is_array) => {
+      rows.iter().map(|r| r.as_array().map_or(Ok(vec![]), row)).collect::<Result<Vec<_>>>()
+    },
+    Some(Value::Array(values)) if values.is_empty() => Ok(vec![]),
+    Some(Value::Array(values)) => row(values).map(|r| vec![r]),
+    Some(v) => number(v).map(|n| vec![vec![n]]),
+  };
+  m.map_err(|e| anyhow!("Invalid `{}`: {}", field, e))
+}
+
+#[test]
+fn test_matrix() {
+  let mpc = json!({"a": [[1, 2], [3, null]], "b": [1, 2], "c": 5, "d": [], "e": "x"});
+  let mpc = mpc.as_object().unwrap();
+  assert_eq!(matrix(mpc, "a").unwrap()[0], vec![1.0, 2.0]);
+  assert!(matrix(mpc, "a").unwrap()[1][1].is_nan());
+  assert_eq!(matrix(mpc, "b").unwrap(), vec![vec![1.0, 2.0]]);
+  assert_eq!(matrix(mpc, "c").unwrap(), vec![vec![5.0]]);
+  assert!(matrix(mpc, "d").unwrap().is_empty());
+  assert!(matrix(mpc, "missing").unwrap().is_empty());
+  assert_eq!(matrix(mpc, "e").unwrap_err().to_string(), "Invalid `e`: Expected a number, found \"x\"");
+}
+
+/// Build a case from MATPOWER-JSON. `name` is used as the case name, which the `mpc` struct does not hold.
+pub fn case(s: &str, name: &str) -> Result<Case> {
+  let value: Value = serde_json::from_str(s)?;
+  let mpc = value.as_object().ok_or_else(|| anyhow!("Expected a JSON object with the fields of `mpc`"))?;
+  let version = match mpc.get("version") {
+    Some(Value::String(v)) => v.parse()?,
+    Some(Value::Number(n)) => n.to_string().parse()?,
+    _ => return Err(anyhow!("Missing `version`")),
+  };
+  let base_mva = mpc.get("baseMVA").ok_or_else(|| anyhow!("Missing `baseMVA`")).and_then(number)?;
+  for field in ["bus", "gen", "branch"] {
+    if !mpc.contains_key(field) {
+      return Err(anyhow!("Missing `{}`", field));
+    }
+  }
+  let tables = Tables {
+    bus: matrix(mpc, "bus")?,
+    gen: matrix(mpc, "gen")?,
+    branch: matrix(mpc, "branch")?,
+    gencost: matrix(mpc, "gencost")?,
+    dcline: matrix(mpc, "dcline")?,
+  };
+  let bus_name = match mpc.get("bus_name") {
+    None => vec![],
+    Some(Value::String(s)) => vec![s.clone()],
+    Some(Value::Array(names)) => {
+      names.iter().map(|n| n.as_str().map(str::to_string).ok_or_else(|| anyhow!("Invalid bus name {}", n))).collect::<Result<_>>()?
+    },
+    Some(v) => return Err(anyhow!("Invalid `bus_name`: {}", v)),
+  };
+  Case::from_tables(name.to_string(), version, base_mva, &tables, bus_name)
+}
+
+/// True if `s` looks like MATPOWER-JSON rather than some other JSON document.
+pub fn is_matpower_json(s: &str) -> bool {
+  s.trim_start().starts_with('{') && s.contains("\"baseMVA\"")
+}
+
+impl Case {
+  /// The case as `jsonencode(mpc)` would write it. Optional tables are left out when empty.
+  pub fn to_matpower_json(&self) -> Value {
+    let tables = self.to_tables();
+    let mut mpc = Map::new();
+    mpc.insert("version".to_string(), Value::from((self.version as i64).to_string()));
+    mpc.insert("baseMVA".to_string(), Value::from(self.base_mva));
+    mpc.insert("bus".to_string(), json!(tables.bus));
+    mpc.insert("gen".to_string(), json!(tables.gen));
+    mpc.insert("branch".to_string(), json!(tables.branch));
+    if !tables.gencost.is_empty() {
+      mpc.insert("gencost".to_string(), json!(tables.gencost));
+    }
+    if !tables.dcline.is_empty() {
+      mpc.insert("dcline".to_string(), json!(tables.dcline));
+    }
+    if !self.bus_name.is_empty() {
+      mpc.insert("bus_name".to_string(), json!(self.bus_name));
+    }
+    Value::Object(mpc)
+  }
+}
+
+#[test]
+fn test_case() {
+  let s = r#"{"version":"2","baseMVA":100,
+    "bus":[[1,3,0,0,0,0,1,1,0,230,1,1.1,0.9],[2,1,90,30,0,0,1,1,0,230,1,1.1,0.9]],
+    "gen":[1,0,0,300,-300,1,100,1,250,10,0,0,0,0,0,0,0,0,0,0,0],
+    "branch":[1,2,0,0.0576,0,250,250,250,0,0,1,-360,360],
+    "gencost":[2,0,0,3,0.11,5,150],
+    "bus_name":["One","Two"]}"#;
+  assert!(is_matpower_json(s));
+  let c = case(s, "case2").unwrap();
+  assert_eq!((c.name.as_str(), c.base_mva, c.bus.len(), c.gen.len()), ("case2", 100.0, 2, 1));
+  assert_eq!(c.gencost[0].cost, vec![0.11, 5.0, 150.0]);
+  assert_eq!(c.bus_name, vec!["One", "Two"]);
+
+  let v = c.to_matpower_json();
+  assert_eq!(v["version"], "2");
+  assert_eq!(v["bus"][1][2], 90.0);
+  assert_eq!(v["branch"][0][3], 0.0576);
+  assert!(v.get("dcline").is_none());
+  assert_eq!(case(&v.to_string(), "case2").unwrap(), c);
+
+  assert_eq!(case(r#"{"version":"2","baseMVA":100,"bus":[]}"#, "c").unwrap_err().to_string(), "Missing `gen`");
+  assert_eq!(case(r#"{"baseMVA":100}"#, "c").unwrap_err().to_string(), "Missing `version`");
+}
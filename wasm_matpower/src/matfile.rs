@@ -0,0 +1,509 @@
+// MAT-file (level 5) reader
+
+// Cases saved from MATLAB with `save('case.mat', 'mpc')`, or `save('case.mat', '-struct', 'mpc')` for one variable
+// per field. Only the parts of the format that a MATPOWER case uses are supported: numeric, sparse, char, cell and
+// struct arrays, optionally inside `miCOMPRESSED` elements.
+//
+// https://www.mathworks.com/help/pdf_doc/matlab/matfile_format.pdf
+
+use std::io::Read;
+
+use anyhow::{anyhow, Result};
+use flate2::read::ZlibDecoder;
+
+use crate::case::{Case, Tables, Version};
+
+const HEADER_LEN: usize = 128;
+
+const MI_INT8: u32 = 1;
+const MI_UINT8: u32 = 2;
+const MI_INT16: u32 = 3;
+const MI_UINT16: u32 = 4;
+const MI_INT32: u32 = 5;
+const MI_UINT32: u32 = 6;
+const MI_SINGLE: u32 = 7;
+const MI_DOUBLE: u32 = 9;
+const MI_INT64: u32 = 12;
+const MI_UINT64: u32 = 13;
+const MI_MATRIX: u32 = 14;
+const MI_COMPRESSED: u32 = 15;
+const MI_UTF8: u32 = 16;
+const MI_UTF16: u32 = 17;
+const MI_UTF32: u32 = 18;
+
+const MX_CELL_CLASS: u32 = 1;
+const MX_STRUCT_CLASS: u32 = 2;
+const MX_CHAR_CLASS: u32 = 4;
+const MX_SPARSE_CLASS: u32 = 5;
+const MX_DOUBLE_CLASS: u32 = 6;
+const MX_UINT64_CLASS: u32 = 15;
+
+#[derive(Debug, PartialEq, Clone)]
+enum Value {
+  Numeric { dims: Vec<usize>, data: Vec<f64> }, // column-major
+  Sparse { dims: Vec<usize>, entries: Vec<(usize, usize, f64)> },
+  Char { rows: Vec<String> },
+  Cell { dims: Vec<usize>, cells: Vec<Value> },
+  Struct { fields: Vec<String>, values: Vec<Value> }, // first element only
+  Unsupported,
+}
+
+impl Value {
+  fn field(&self, name: &str) -> Option<&Value> {
+    match self {
+      Value::Struct { fields, values } => fields.iter().position(|f| f == name).and_then(|i| values.get(i)),
+      _ => None,
+    }
+  }
+
+  fn scalar(&self) -> Option<f64> {
+    match self {
+      Value::Numeric { data, .. } if data.len() == 1 => Some(data[0]),
+      _ => None,
+    }
+  }
+
+  fn rows(&self) -> Option<Vec<Vec<f64>>> {
+    match self {
+      Value::Numeric { dims, data } => {
+        let (r, c) = (dims.first().copied().unwrap_or(0), size(dims.get(1..).unwrap_or(&[])).unwrap_or(0));
+        Some((0..r).map(|i| (0..c).map(|j| data[i + r * j]).collect()).collect())
+      },
+      // Only the rows up to the last one with a nonzero are built: the row count of a sparse array isn't backed by its
+      // data, and no case table has a row of zeros.
+      Value::Sparse { dims, entries } => {
+        let c = dims.get(1).copied().unwrap_or(0);
+        let mut rows = vec![vec![0.0; c]; entries.iter().map(|(i, _, _)| i + 1).max().unwrap_or(0)];
+        for (i, j, v) in entries.iter() {
+          rows[*i][*j] = *v;
+        }
+        Some(rows)
+      },
+      _ => None,
+    }
+  }
+
+  fn strings(&self) -> Option<Vec<String>> {
+    match self {
+      Value::Char { rows } => Some(rows.iter().map(|s| s.trim_end().to_string()).collect()),
+      Value::Cell { cells, .. } => {
+        cells.iter().map(|c| c.strings().map(|s| s.into_iter().next().unwrap_or_default())).collect()
+      },
+      _ => None,
+    }
+  }
+}
+
+// The number of elements of an array, if it fits in a `usize`.
+fn size(dims: &[usize]) -> Option<usize> {
+  dims.iter().try_fold(1usize, |n, d| n.checked_mul(*d))
+}
+
+struct Reader {
+  big_endian: bool,
+}
+
+impl Reader {
+  fn u32(&self, b: &[u8]) -> u32 {
+    let b = [b[0], b[1], b[2], b[3]];
+    if self.big_endian {
+      u32::from_be_bytes(b)
+    } else {
+      u32::from_le_bytes(b)
+    }
+  }
+
+  // Returns the element type, its data and the number of bytes consumed including padding.
+  fn element<'a>(&self, b: &'a [u8]) -> Result<(u32, &'a [u8], usize)> {
+    if b.len() < 8 {
+      return Err(anyhow!("Truncated data element"));
+    }
+    let first = self.u32(&b[0..4]);
+    if first >> 16 != 0 {
+      // Small data element format: type and size packed into the first four bytes.
+      let (t, n) = (first & 0xffff, (first >> 16) as usize);
+      if n > 4 {
+        return Err(anyhow!("Invalid small data element of {} bytes", n));
+      }
+      return Ok((t, &b[4..4 + n], 8));
+    }
+    let n = self.u32(&b[4..8]) as usize;
+    let data = b.get(8..8 + n).ok_or_else(|| anyhow!("Truncated data element of {} bytes", n))?;
+    let consumed = if first == MI_COMPRESSED { 8 + n } else { 8 + n.div_ceil(8) * 8 };
+    Ok((first, data, consumed.min(b.len())))
+  }
+
+  fn elements<'a>(&self, mut b: &'a [u8]) -> Result<Vec<(u32, &'a [u8])>> {
+    let mut v = vec![];
+    while !b.is_empty() {
+      let (t, data, n) = self.element(b)?;
+      v.push((t, data));
+      b = &b[n..];
+    }
+    Ok(v)
+  }
+
+  fn numbers(&self, t: u32, b: &[u8]) -> Result<Vec<f64>> {
+    macro_rules! convert {
+      ($ty:ty, $n:expr) => {
+        b.chunks_exact($n)
+          .map(|c| {
+            let mut a = [0u8; $n];
+            a.copy_from_slice(c);
+            (if self.big_endian { <$ty>::from_be_bytes(a) } else { <$ty>::from_le_bytes(a) }) as f64
+          })
+          .collect()
+      };
+    }
+    Ok(match t {
+      MI_INT8 => b.iter().map(|x| *x as i8 as f64).collect(),
+      MI_UINT8 | MI_UTF8 => b.iter().map(|x| *x as f64).collect(),
+      MI_INT16 => convert!(i16, 2),
+      MI_UINT16 | MI_UTF16 => convert!(u16, 2),
+      MI_INT32 => convert!(i32, 4),
+      MI_UINT32 | MI_UTF32 => convert!(u32, 4),
+      MI_SINGLE => convert!(f32, 4),
+      MI_DOUBLE => convert!(f64, 8),
+      MI_INT64 => convert!(i64, 8),
+      MI_UINT64 => convert!(u64, 8),
+      t => return Err(anyhow!("Unsupported numeric data type {}", t)),
+    })
+  }
+
+  fn chars(&self, t: u32, b: &[u8], dims: &[usize]) -> Result<Vec<String>> {
+    let (r, c) = (dims.first().copied().unwrap_or(0), size(dims.get(1..).unwrap_or(&[])).unwrap_or(0));
+    if t == MI_UTF8 && r == 1 {
+      return Ok(vec![String::from_utf8_lossy(b).into_owned()]);
+    }
+    let units = self.numbers(t, b)?.into_iter().map(|u| u as u32).collect::<Vec<_>>();
+    let n = size(dims).ok_or_else(|| anyhow!("Character array of {:?} is too large", dims))?;
+    if units.len() < n {
+      return Err(anyhow!("Expected {} characters, found {}", n, units.len()));
+    }
+    Ok(
+      (0..r)
+        .map(|i| {
+          let row = (0..c).map(|j| units[i + r * j]);
+          if t == MI_UTF16 || t == MI_UINT16 {
+            std::char::decode_utf16(row.map(|u| u as u16)).map(|c| c.unwrap_or(std::char::REPLACEMENT_CHARACTER)).collect()
+          } else {
+            row.map(|u| std::char::from_u32(u).unwrap_or(std::char::REPLACEMENT_CHARACTER)).collect()
+          }
+        })
+        .collect(),
+    )
+  }
+
+  fn matrix(&self, b: &[u8]) -> Result<(String, Value)> {
+    // Empty cells may be written as an miMATRIX element with no data.
+    if b.is_empty() {
+      return Ok(("".to_string(), Value::Numeric { dims: vec![0, 0], data: vec![] }));
+    }
+    let elements = self.elements(b)?;
+    if elements.len() < 3 {
+      return Err(anyhow!("Expected array flags, dimensions and name in miMATRIX"));
+    }
+    let (_, flags) = elements[0];
+    if flags.len() < 8 {
+      return Err(anyhow!("Invalid array flags"));
+    }
+    let class = self.u32(&flags[0..4]) & 0xff;
+    let dims = self.numbers(elements[1].0, elements[1].1)?.into_iter().map(|d| d as usize).collect::<Vec<_>>();
+    let name = String::from_utf8_lossy(elements[2].1).into_owned();
+    let n = size(&dims).ok_or_else(|| anyhow!("Array {:?} of {:?} is too large", name, dims))?;
+    let rest = &elements[3..];
+    let value = match class {
+      MX_DOUBLE_CLASS..=MX_UINT64_CLASS => {
+        let (t, data) = rest.first().ok_or_else(|| anyhow!("Missing real part of {:?}", name))?;
+        let data = self.numbers(*t, data)?;
+        if data.len() < n {
+          return Err(anyhow!("Expected {} values in {:?}, found {}", n, name, data.len()));
+        }
+        Value::Numeric { dims, data }
+      },
+      MX_SPARSE_CLASS => {
+        let (r, c) = (dims.first().copied().unwrap_or(0), dims.get(1).copied().unwrap_or(0));
+        let ir = rest.first().map(|(t, d)| self.numbers(*t, d)).transpose()?.unwrap_or_default();
+        let jc = rest.get(1).map(|(t, d)| self.numbers(*t, d)).transpose()?.unwrap_or_default();
+        let pr = rest.get(2).map(|(t, d)| self.numbers(*t, d)).transpose()?;
+        // Every column has an offset, so that the width is backed by the data.
+        if jc.len() <= c {
+          return Err(anyhow!("Expected {} column offsets in {:?}, found {}", c as u64 + 1, name, jc.len()));
+        }
+        let mut entries = vec![];
+        for j in 0..c {
+          for k in jc[j] as usize..jc[j + 1] as usize {
+            let i = ir.get(k).map(|i| *i as usize).filter(|i| *i < r);
+            let i = i.ok_or_else(|| anyhow!("Invalid sparse row index in {:?}", name))?;
+            // Logical sparse arrays may omit the values.
+            entries.push((i, j, pr.as_ref().map_or(1.0, |pr| pr.get(k).copied().unwrap_or(0.0))));
+          }
+        }
+        Value::Sparse { dims, entries }
+      },
+      MX_CHAR_CLASS => match rest.first() {
+        Some((t, data)) => Value::Char { rows: self.chars(*t, data, &dims)? },
+        None => Value::Char { rows: vec![] },
+      },
+      MX_CELL_CLASS => {
+        let cells = rest.iter().take(n).map(|(_, data)| self.matrix(data).map(|(_, v)| v)).collect::<Result<Vec<_>>>()?;
+        Value::Cell { dims, cells }
+      },
+      MX_STRUCT_CLASS => {
+        let (_, len) = rest.first().ok_or_else(|| anyhow!("Missing field name length in {:?}", name))?;
+        let len = self.numbers(MI_INT32, len)?.first().copied().unwrap_or(0.0) as usize;
+        let (_, names) = rest.get(1).ok_or_else(|| anyhow!("Missing field names in {:?}", name))?;
+        let fields = if len == 0 {
+          vec![]
+        } else {
+          names
+            .chunks(len)
+            .map(|c| String::from_utf8_lossy(c.split(|b| *b == 0).next().unwrap_or(c)).into_owned())
+            .collect::<Vec<_>>()
+        };
+        let values = if n == 0 {
+          vec![]
+        } else {
+          rest[2..].iter().take(fields.len()).map(|(_, data)| self.matrix(data).map(|(_, v)| v)).collect::<Result<_>>()?
+        };
+        Value::Struct { fields, values }
+      },
+      _ => Value::Unsupported,
+    };
+    Ok((name, value))
+  }
+
+  fn variables(&self, b: &[u8]) -> Result<Vec<(String, Value)>> {
+    let mut v = vec![];
+    for (t, data) in self.elements(b)? {
+      match t {
+        MI_MATRIX => v.push(self.matrix(data)?),
+        MI_COMPRESSED => {
+          let mut inflated = vec![];
+          ZlibDecoder::new(data).read_to_end(&mut inflated)?;
+          v.extend(self.variables(&inflated)?);
+        },
+        _ => (),
+      }
+    }
+    Ok(v)
+  }
+}
+
+/// True if `b` starts with a level 5 MAT-file header.
+pub fn is_matfile(b: &[u8]) -> bool {
+  b.len() >= HEADER_LEN && b.starts_with(b"MATLAB 5.0 MAT-file") && matches!(&b[126..128], b"IM" | b"MI")
+}
+
+/// Build a case from a level 5 MAT-file holding an `mpc` struct, or one variable per `mpc` field.
+pub fn case(b: &[u8], name: &str) -> Result<Case> {
+  if !is_matfile(b) {
+    return Err(anyhow!("Not a MAT-file (level 5)"));
+  }
+  let reader = Reader { big_endian: &b[126..128] == b"MI" };
+  let variables = reader.variables(&b[HEADER_LEN..])?;
+
+  let mpc = variables.iter().map(|(_, v)| v).find(|v| v.field("bus").is_some()).cloned().unwrap_or_else(|| {
+    Value::Struct {
+      fields: variables.iter().map(|(n, _)| n.clone()).collect(),
+      values: variables.iter().map(|(_, v)| v.clone()).collect(),
+    }
+  });
+  let table = |field: &str, required: bool| -> Result<Vec<Vec<f64>>> {
+    match mpc.field(field) {
+      Some(v) => v.rows().ok_or_else(|| anyhow!("Expected `{}` to be a numeric matrix", field)),
+      None if required => Err(anyhow!("Missing `{}`", field)),
+      None => Ok(vec![]),
+    }
+  };
+
+  let version = match mpc.field("version") {
+    Some(Value::Char { rows }) => rows.first().map(|s| s.parse()).unwrap_or(Ok(Version::Version2))?,
+    Some(v) => v.scalar().map(|v| v.to_string().parse()).unwrap_or(Ok(Version::Version2))?,
+    None => Version::Version2,
+  };
+  let base_mva = mpc.field("baseMVA").and_then(Value::scalar).ok_or_else(|| anyhow!("Missing `baseMVA`"))?;
+  let tables = Tables {
+    bus: table("bus", true)?,
+    gen: table("gen", true)?,
+    branch: table("branch", true)?,
+    gencost: table("gencost", false)?,
+    dcline: table("dcline", false)?,
+  };
+  let bus_name = match mpc.field("bus_name") {
+    Some(v) => v.strings().ok_or_else(|| anyhow!("Expected `bus_name` to be a cell array of strings"))?,
+    None => vec![],
+  };
+  let name = name.rsplit(['/', '\\']).next().unwrap_or(name);
+  let name = name.strip_suffix(".mat").unwrap_or(name);
+  Case::from_tables(name.to_string(), version, base_mva, &tables, bus_name)
+}
+
+#[cfg(test)]
+fn element(t: u32, data: &[u8]) -> Vec<u8> {
+  let mut v = vec![];
+  if data.len() <= 4 && t != MI_MATRIX {
+    v.extend(&(t | (data.len() as u32) << 16).to_le_bytes());
+    v.extend(data);
+    v.resize(8, 0);
+  } else {
+    v.extend(&t.to_le_bytes());
+    v.extend(&(data.len() as u32).to_le_bytes());
+    v.extend(data);
+    v.resize(8 + data.len().div_ceil(8) * 8, 0);
+  }
+  v
+}
+
+#[cfg(test)]
+fn array(name: &str, class: u32, dims: &[i32], body: &[Vec<u8>]) -> Vec<u8> {
+  let mut data = element(MI_UINT32, &[class.to_le_bytes(), 0u32.to_le_bytes()].concat());
+  data.extend(element(MI_INT32, &dims.iter().flat_map(|d| d.to_le_bytes()).collect::<Vec<_>>()));
+  data.extend(element(MI_INT8, name.as_bytes()));
+  for b in body {
+    data.extend(b);
+  }
+  element(MI_MATRIX, &data)
+}
+
+#[cfg(test)]
+fn double(name: &str, rows: &[&[f64]]) -> Vec<u8> {
+  let (r, c) = (rows.len(), rows.first().map_or(0, |row| row.len()));
+  let data = (0..c).flat_map(|j| rows.iter().map(move |row| row[j])).flat_map(|x| x.to_le_bytes()).collect::<Vec<_>>();
+  array(name, MX_DOUBLE_CLASS, &[r as i32, c as i32], &[element(MI_DOUBLE, &data)])
+}
+
+#[cfg(test)]
+fn chars(name: &str, s: &str) -> Vec<u8> {
+  let data = s.encode_utf16().flat_map(|u| u.to_le_bytes()).collect::<Vec<_>>();
+  array(name, MX_CHAR_CLASS, &[1, s.len() as i32], &[element(MI_UTF16, &data)])
+}
+
+#[cfg(test)]
+fn matfile(variables: &[Vec<u8>], compress: bool) -> Vec<u8> {
+  use std::io::Write;
+
+  let mut b = format!("{:<116}", "MATLAB 5.0 MAT-file, Platform: GLNXA64, Created on: Mon Jan  3 12:00:00 2022").into_bytes();
+  b.extend(&[0u8; 8]);
+  b.extend(&0x0100u16.to_le_bytes());
+  b.extend(b"IM");
+  for v in variables {
+    if compress {
+      let mut e = flate2::write::ZlibEncoder::new(vec![], flate2::Compression::default());
+      e.write_all(v).unwrap();
+      let z = e.finish().unwrap();
+      b.extend(&MI_COMPRESSED.to_le_bytes());
+      b.extend(&(z.len() as u32).to_le_bytes());
+      b.extend(z);
+    } else {
+      b.extend(v);
+    }
+  }
+  b
+}
+
+#[cfg(test)]
+fn mpc_fields() -> Vec<(&'static str, Vec<u8>)> {
+  vec![
+    ("version", chars("", "2")),
+    ("baseMVA", double("", &[&[100.0]])),
+    ("bus", double("", &[&[1.0, 3.0, 0.0, 0.0, 0.0, 0.0, 1.0, 1.0, 0.0, 230.0, 1.0, 1.1, 0.9], &[
+      2.0, 1.0, 90.0, 30.0, 0.0, 0.0, 1.0, 1.0, 0.0, 230.0, 1.0, 1.1, 0.9,
+    ]])),
+    ("gen", double("", &[&[
+      1.0, 0.0, 0.0, 300.0, -300.0, 1.0, 100.0, 1.0, 250.0, 10.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0,
+    ]])),
+    ("branch", double("", &[&[1.0, 2.0, 0.0, 0.0576, 0.0, 250.0, 250.0, 250.0, 0.0, 0.0, 1.0, -360.0, 360.0]])),
+    ("gencost", double("", &[&[2.0, 0.0, 0.0, 3.0, 0.11, 5.0, 150.0]])),
+    ("bus_name", array("", MX_CELL_CLASS, &[2, 1], &[chars("", "One"), chars("", "Two")])),
+  ]
+}
+
+#[test]
+fn test_case_struct() {
+  let fields = mpc_fields();
+  let len = 8;
+  let names = fields
+    .iter()
+    .flat_map(|(n, _)| {
+      let mut n = n.as_bytes().to_vec();
+      n.resize(len, 0);
+      n
+    })
+    .collect::<Vec<_>>();
+  let mut body = vec![element(MI_INT32, &(len as i32).to_le_bytes()), element(MI_INT8, &names)];
+  body.extend(fields.into_iter().map(|(_, v)| v));
+  let mpc = array("mpc", MX_STRUCT_CLASS, &[1, 1], &body);
+
+  for compress in [false, true].iter() {
+    let c = case(&matfile(std::slice::from_ref(&mpc), *compress), "data/case2.mat").unwrap();
+    assert_eq!(c.name, "case2");
+    assert_eq!(c.version, Version::Version2);
+    assert_eq!(c.base_mva, 100.0);
+    assert_eq!(c.bus.len(), 2);
+    assert_eq!(c.bus[1].pd, 90.0);
+    assert_eq!(c.gen[0].pmax, 250.0);
+    assert_eq!(c.branch[0].br_x, 0.0576);
+    assert_eq!(c.gencost[0].cost, vec![0.11, 5.0, 150.0]);
+    assert_eq!(c.bus_name, vec!["One".to_string(), "Two".to_string()]);
+    assert!(c.dcline.is_empty());
+  }
+}
+
+#[test]
+fn test_case_variables() {
+  // save('case.mat', '-struct', 'mpc')
+  let variables = mpc_fields()
+    .into_iter()
+    .map(|(n, v)| {
+      // Rename the array by rebuilding it with the field name.
+      let reader = Reader { big_endian: false };
+      let (t, data, _) = reader.element(&v).unwrap();
+      assert_eq!(t, MI_MATRIX);
+      let elements = reader.elements(data).unwrap();
+      let mut data = vec![];
+      for (i, (t, d)) in elements.into_iter().enumerate() {
+        data.extend(element(t, if i == 2 { n.as_bytes() } else { d }));
+      }
+      element(MI_MATRIX, &data)
+    })
+    .collect::<Vec<_>>();
+  let c = case(&matfile(&variables, true), "case2").unwrap();
+  assert_eq!(c.bus.len(), 2);
+  assert_eq!(c.bus_name.len(), 2);
+}
+
+#[test]
+fn test_case_errors() {
+  assert!(case(b"function mpc = case9", "case9").is_err());
+  let e = case(&matfile(&[double("baseMVA", &[&[100.0]])], false), "case").unwrap_err();
+  assert_eq!(e.to_string(), "Missing `bus`");
+}
+
+#[test]
+fn test_sparse() {
+  // 2x2 sparse [0 5; 7 0]
+  let ir = [1i32, 0].iter().flat_map(|x| x.to_le_bytes()).collect::<Vec<_>>();
+  let jc = [0i32, 1, 2].iter().flat_map(|x| x.to_le_bytes()).collect::<Vec<_>>();
+  let pr = [7f64, 5.0].iter().flat_map(|x| x.to_le_bytes()).collect::<Vec<_>>();
+  let m = array("s", MX_SPARSE_CLASS, &[2, 2], &[element(MI_INT32, &ir), element(MI_INT32, &jc), element(MI_DOUBLE, &pr)]);
+  let reader = Reader { big_endian: false };
+  let v = reader.variables(&m).unwrap();
+  assert_eq!(v[0].1.rows().unwrap(), vec![vec![0.0, 5.0], vec![7.0, 0.0]]);
+
+  // Dimensions far beyond the data: only the rows holding values are built.
+  let body = [element(MI_INT32, &ir), element(MI_INT32, &jc), element(MI_DOUBLE, &pr)];
+  let m = array("s", MX_SPARSE_CLASS, &[i32::MAX, 2], &body);
+  assert_eq!(reader.variables(&m).unwrap()[0].1.rows().unwrap(), vec![vec![0.0, 5.0], vec![7.0, 0.0]]);
+  let m = array("s", MX_SPARSE_CLASS, &[2, i32::MAX], &body);
+  assert_eq!(reader.variables(&m).unwrap_err().to_string(), "Expected 2147483648 column offsets in \"s\", found 3");
+}
+
+#[test]
+fn test_array_size() {
+  let reader = Reader { big_endian: false };
+  let m = array("m", MX_DOUBLE_CLASS, &[i32::MAX, i32::MAX, i32::MAX], &[element(MI_DOUBLE, &1f64.to_le_bytes())]);
+  assert!(reader.variables(&m).unwrap_err().to_string().ends_with("is too large"));
+  let m = array("m", MX_DOUBLE_CLASS, &[i32::MAX, 2], &[element(MI_DOUBLE, &1f64.to_le_bytes())]);
+  assert!(reader.variables(&m).unwrap_err().to_string().starts_with("Expected 4294967294 values"));
+}
@@ -0,0 +1,187 @@
+// Merging cases
+
+// `Case::merge` stitches two systems into one, such as neighbouring utility models joined by tie lines. The merged
+// case keeps the first case's name, version and MVA base. Bus numbers of the second case are renumbered by a
+// `Renumber` policy so they don't clash, and its branch impedances are rescaled to the first case's MVA base; powers
+// are in MW and MVAr and need no rescaling. Rows of the second case follow those of the first in every table.
+
+use std::collections::HashMap;
+
+use anyhow::{anyhow, Result};
+
+use crate::{
+  case::{Branch, Bus, BusId, Case, DcLine, Gen},
+  index::RowSpans,
+  units::{rebase_admittance, rebase_impedance, Base},
+};
+
+/// How `Case::merge` numbers the buses of the second case.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Renumber {
+  /// Keep the bus numbers and fail if any is in both cases.
+  Keep,
+  /// Add an offset to every bus number.
+  Offset(usize),
+  /// Add the smallest power of ten above the first case's highest bus number, so 1..118 merged with 1..30 gives
+  /// 1001..1030.
+  Auto,
+  /// Renumber buses in the map and keep the numbers of the others.
+  Map(HashMap<BusId, BusId>),
+}
+
+impl Renumber {
+  fn bus(&self, id: BusId, offset: usize) -> BusId {
+    match self {
+      Renumber::Keep => id,
+      Renumber::Offset(_) | Renumber::Auto => BusId(id.0 + offset),
+      Renumber::Map(map) => map.get(&id).copied().unwrap_or(id),
+    }
+  }
+}
+
+impl Case {
+  /// This case and `other` as one, joined by the branches `ties`, whose `f_bus` is a bus of this case and `t_bus` a
+  /// bus of `other` in its own numbering. Tie branch parameters are in per unit on this case's base. Returns the
+  /// merged case and the new number of each bus of `other`.
+  pub fn merge(&self, other: &Case, ties: &[Branch], renumber: Renumber) -> Result<(Case, HashMap<BusId, BusId>)> {
+    let index = self.check_references(&RowSpans::default())?;
+    let other_index = other.check_references(&RowSpans::default())?;
+    self.check_gencost_rows()?;
+    other.check_gencost_rows()?;
+    let (ng, other_ng) = (self.gen.len(), other.gen.len());
+    let costs = |c: &Case| match c.gencost.len() {
+      0 => 0,
+      n if n == c.gen.len() => 1,
+      _ => 2,
+    };
+    // A case without generators fits either way.
+    let (cost_rows, other_cost_rows) = (costs(self), costs(other));
+    if ng > 0 && other_ng > 0 && cost_rows != other_cost_rows {
+      return Err(anyhow!(
+        "Can't merge a case with {} cost rows per generator with one with {}",
+        cost_rows,
+        other_cost_rows
+      ));
+    }
+
+    let offset = match renumber {
+      Renumber::Offset(offset) => offset,
+      Renumber::Auto => {
+        let max = self.bus.iter().map(|b| b.idx.0).max().unwrap_or(0);
+        let mut offset = 10;
+        while offset <= max {
+          offset *= 10;
+        }
+        offset
+      },
+      _ => 0,
+    };
+    let map = other.bus.iter().map(|b| (b.idx, renumber.bus(b.idx, offset))).collect::<HashMap<_, _>>();
+    let mut seen = HashMap::new();
+    for (old, new) in other.bus.iter().map(|b| (b.idx, map[&b.idx])) {
+      if index.contains(new) {
+        return Err(anyhow!("Bus {} of the second case would be bus {}, which is in the first case", old, new));
+      }
+      if let Some(first) = seen.insert(new, old) {
+        return Err(anyhow!("Buses {} and {} of the second case would both be bus {}", first, old, new));
+      }
+    }
+
+    let mut c = self.clone();
+    c.bus.extend(other.bus.iter().map(|b| Bus { idx: map[&b.idx], ..*b }));
+    if !self.bus_name.is_empty() || !other.bus_name.is_empty() {
+      let names = |case: &Case, renumber: &dyn Fn(BusId) -> BusId| -> Vec<String> {
+        if case.bus_name.is_empty() {
+          case.bus.iter().map(|b| renumber(b.idx).to_string()).collect()
+        } else {
+          case.bus_name.clone()
+        }
+      };
+      c.bus_name = names(self, &|id| id);
+      c.bus_name.extend(names(other, &|id| map[&id]));
+    }
+    c.gen.extend(other.gen.iter().map(|g| Gen { gen: map[&g.gen], ..*g }));
+    // Active power costs of both cases come before their reactive power costs.
+    c.gencost.truncate(if cost_rows == 0 { 0 } else { ng });
+    c.gencost.extend(other.gencost.iter().take(other_ng).cloned());
+    if cost_rows == 2 || other_cost_rows == 2 {
+      c.gencost.extend(self.gencost.iter().skip(ng).cloned());
+      c.gencost.extend(other.gencost.iter().skip(other_ng).cloned());
+    }
+    let (from, to) = (Base::new(other.base_mva, 1.0), Base::new(self.base_mva, 1.0));
+    c.branch.extend(other.branch.iter().map(|br| Branch {
+      f_bus: map[&br.f_bus],
+      t_bus: map[&br.t_bus],
+      br_r: rebase_impedance(br.br_r, from, to),
+      br_x: rebase_impedance(br.br_x, from, to),
+      br_b: rebase_admittance(br.br_b, from, to),
+      ..*br
+    }));
+    for (i, tie) in ties.iter().enumerate() {
+      if !index.contains(tie.f_bus) {
+        return Err(anyhow!("Tie {} is from bus {}, which is not in the first case", i + 1, tie.f_bus));
+      }
+      if !other_index.contains(tie.t_bus) {
+        return Err(anyhow!("Tie {} is to bus {}, which is not in the second case", i + 1, tie.t_bus));
+      }
+      c.branch.push(Branch { t_bus: map[&tie.t_bus], ..*tie });
+    }
+    c.dcline.extend(other.dcline.iter().map(|d| DcLine { f_bus: map[&d.f_bus], t_bus: map[&d.t_bus], ..*d }));
+    for (table, records) in other.extra.iter() {
+      c.extra.entry(table.clone()).or_default().extend(records.iter().cloned());
+    }
+    Ok((c, map))
+  }
+}
+
+#[test]
+fn test_merge() {
+  use crate::{
+    builder::CaseBuilder,
+    case::{CostModel, GenCost},
+  };
+
+  let cost =
+    |c: f64| GenCost { model: CostModel::Polynomial, startup: 0.0, shutdown: 0.0, ncost: 2, cost: vec![c, 0.0] };
+  let a = CaseBuilder::new("a")
+    .named_bus(Bus { idx: BusId(1), ..Bus::default() }, "North")
+    .bus(Bus { idx: BusId(2), ..Bus::default() })
+    .gen(Gen { gen: BusId(1), ..Gen::default() })
+    .branch(Branch { f_bus: BusId(1), t_bus: BusId(2), br_x: 0.1, ..Branch::default() })
+    .gencost(cost(10.0))
+    .build()
+    .unwrap();
+  let b = CaseBuilder::new("b")
+    .base_mva(200.0)
+    .bus(Bus { idx: BusId(1), ..Bus::default() })
+    .bus(Bus { idx: BusId(5), ..Bus::default() })
+    .gen(Gen { gen: BusId(5), ..Gen::default() })
+    .branch(Branch { f_bus: BusId(1), t_bus: BusId(5), br_x: 0.1, br_b: 0.02, ..Branch::default() })
+    .gencost(cost(20.0))
+    .build()
+    .unwrap();
+  let tie = Branch { f_bus: BusId(2), t_bus: BusId(1), br_x: 0.05, ..Branch::default() };
+
+  assert_eq!(
+    a.merge(&b, &[], Renumber::Keep).unwrap_err().to_string(),
+    "Bus 1 of the second case would be bus 1, which is in the first case"
+  );
+  let (c, map) = a.merge(&b, &[tie], Renumber::Auto).unwrap();
+  assert_eq!((map[&BusId(1)], map[&BusId(5)]), (BusId(11), BusId(15)));
+  assert_eq!(c.bus.iter().map(|b| b.idx.0).collect::<Vec<_>>(), vec![1, 2, 11, 15]);
+  assert_eq!(c.bus_name, vec!["North", "2", "11", "15"]);
+  assert_eq!((c.gen[1].gen, c.gencost[1].cost[0]), (BusId(15), 20.0));
+  assert_eq!(
+    (c.branch[1].f_bus, c.branch[1].t_bus, c.branch[1].br_x, c.branch[1].br_b),
+    (BusId(11), BusId(15), 0.05, 0.04)
+  );
+  assert_eq!((c.branch[2].f_bus, c.branch[2].t_bus), (BusId(2), BusId(11)));
+  assert!(c.bus_index().unwrap().dangling(&c).is_empty());
+
+  let (c, _) = a.merge(&b, &[], Renumber::Map(vec![(BusId(1), BusId(3))].into_iter().collect())).unwrap();
+  assert_eq!(c.bus.iter().map(|b| b.idx.0).collect::<Vec<_>>(), vec![1, 2, 3, 5]);
+  assert_eq!(
+    a.merge(&b, &[Branch { f_bus: BusId(9), ..tie }], Renumber::Offset(100)).unwrap_err().to_string(),
+    "Tie 1 is from bus 9, which is not in the first case"
+  );
+}
@@ -0,0 +1,353 @@
+// Network matrices
+
+// The bus admittance matrix `Ybus` and the branch admittance matrices `Yf` and `Yt`, built as MATPOWER's `makeYbus`
+// builds them, and the DC and fast-decoupled matrices of `makeBdc` and `makeB` are the starting point of every power
+// flow. Rows and columns of bus matrices follow the rows of `Case::bus` and rows of branch matrices the rows of
+// `Case::branch`, whatever the bus numbers. Admittances are in per unit on the system MVA base. Out-of-service branches
+// keep their rows in `Yf` and `Yt`, with nothing in them.
+
+use std::{fmt::Write, ops::AddAssign};
+
+use anyhow::{anyhow, Result};
+pub use num_complex::Complex64;
+
+use crate::{
+  case::{Branch, Case},
+  index::BusIndex,
+};
+
+/// A sparse matrix in compressed sparse row form. Entries of a row are in column order, and entries that sum to zero
+/// are left out.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SparseMatrix<T> {
+  rows: usize,
+  cols: usize,
+  row_start: Vec<usize>,
+  columns: Vec<usize>,
+  values: Vec<T>,
+}
+
+impl<T: Copy + Default + PartialEq + AddAssign> SparseMatrix<T> {
+  /// A `rows` by `cols` matrix of `(row, column, value)` entries, adding up entries at the same position.
+  pub fn from_triplets(rows: usize, cols: usize, triplets: impl IntoIterator<Item = (usize, usize, T)>) -> Self {
+    let mut triplets = triplets.into_iter().collect::<Vec<_>>();
+    triplets.sort_by_key(|(i, j, _)| (*i, *j));
+    let mut m = SparseMatrix { rows, cols, row_start: vec![0; rows + 1], columns: vec![], values: vec![] };
+    let mut last = None;
+    for (i, j, v) in triplets {
+      assert!(i < rows && j < cols, "entry ({}, {}) is outside a {} by {} matrix", i, j, rows, cols);
+      if last == Some((i, j)) {
+        *m.values.last_mut().unwrap() += v;
+      } else {
+        m.row_start[i + 1] += 1;
+        m.columns.push(j);
+        m.values.push(v);
+        last = Some((i, j));
+      }
+    }
+    for i in 0..rows {
+      m.row_start[i + 1] += m.row_start[i];
+    }
+    m.remove_zeros();
+    m
+  }
+
+  fn remove_zeros(&mut self) {
+    let zero = T::default();
+    let (mut kept, mut start) = (0, 0);
+    for i in 0..self.rows {
+      let end = self.row_start[i + 1];
+      for k in start..end {
+        if self.values[k] != zero {
+          self.columns[kept] = self.columns[k];
+          self.values[kept] = self.values[k];
+          kept += 1;
+        }
+      }
+      start = end;
+      self.row_start[i + 1] = kept;
+    }
+    self.columns.truncate(kept);
+    self.values.truncate(kept);
+  }
+
+  pub fn rows(&self) -> usize {
+    self.rows
+  }
+
+  pub fn cols(&self) -> usize {
+    self.cols
+  }
+
+  /// Number of entries stored.
+  pub fn nnz(&self) -> usize {
+    self.values.len()
+  }
+
+  /// The entry at `(i, j)`, zero if none is stored.
+  pub fn get(&self, i: usize, j: usize) -> T {
+    let range = self.row_start[i]..self.row_start[i + 1];
+    match self.columns[range.clone()].binary_search(&j) {
+      Ok(k) => self.values[range.start + k],
+      Err(_) => T::default(),
+    }
+  }
+
+  /// Column and value of the entries of row `i`.
+  pub fn row(&self, i: usize) -> impl Iterator<Item = (usize, T)> + '_ {
+    let range = self.row_start[i]..self.row_start[i + 1];
+    self.columns[range.clone()].iter().copied().zip(self.values[range].iter().copied())
+  }
+
+  /// Row, column and value of every entry, row by row.
+  pub fn iter(&self) -> impl Iterator<Item = (usize, usize, T)> + '_ {
+    (0..self.rows).flat_map(move |i| self.row(i).map(move |(j, v)| (i, j, v)))
+  }
+
+  pub fn transpose(&self) -> Self {
+    SparseMatrix::from_triplets(self.cols, self.rows, self.iter().map(|(i, j, v)| (j, i, v)))
+  }
+
+  /// The product of the matrix and the vector `x`.
+  pub fn mul_vec(&self, x: &[T]) -> Vec<T>
+  where
+    T: std::ops::Mul<Output = T>,
+  {
+    assert_eq!(x.len(), self.cols);
+    (0..self.rows)
+      .map(|i| {
+        let mut sum = T::default();
+        for (j, v) in self.row(i) {
+          sum += v * x[j];
+        }
+        sum
+      })
+      .collect()
+  }
+}
+
+/// Values that can be written to a Matrix Market file.
+pub trait MatrixMarketValue {
+  /// The field of the banner, such as `real`.
+  const FIELD: &'static str;
+  fn write(&self, s: &mut String);
+}
+
+impl MatrixMarketValue for f64 {
+  const FIELD: &'static str = "real";
+  fn write(&self, s: &mut String) {
+    write!(s, "{}", self).unwrap();
+  }
+}
+
+impl MatrixMarketValue for Complex64 {
+  const FIELD: &'static str = "complex";
+  fn write(&self, s: &mut String) {
+    write!(s, "{} {}", self.re, self.im).unwrap();
+  }
+}
+
+impl<T: MatrixMarketValue + Copy + Default + PartialEq + AddAssign> SparseMatrix<T> {
+  /// The matrix in Matrix Market coordinate format, with 1-based indices, as MATLAB's `mmwrite` and SciPy's
+  /// `scipy.io.mmread` read it.
+  pub fn to_matrix_market(&self) -> String {
+    let mut s = format!("%%MatrixMarket matrix coordinate {} general\n", T::FIELD);
+    writeln!(s, "{} {} {}", self.rows, self.cols, self.nnz()).unwrap();
+    for (i, j, v) in self.iter() {
+      write!(s, "{} {} ", i + 1, j + 1).unwrap();
+      v.write(&mut s);
+      s.push('\n');
+    }
+    s
+  }
+}
+
+/// The admittance matrices of a case, as MATPOWER's `makeYbus` returns them. Bus injections are `Ybus * V` and branch
+/// currents at the from and to ends are `Yf * V` and `Yt * V`, for bus voltages `V` in per unit.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Admittance {
+  /// buses by buses
+  pub ybus: SparseMatrix<Complex64>,
+  /// branches by buses
+  pub yf: SparseMatrix<Complex64>,
+  /// branches by buses
+  pub yt: SparseMatrix<Complex64>,
+}
+
+/// The matrices of the DC approximation, as MATPOWER's `makeBdc` returns them. Bus injections are
+/// `Bbus * Va + Pbusinj` and branch flows `Bf * Va + Pfinj`, in per unit for bus angles `Va` in radians. The
+/// injections account for phase shifters.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Susceptance {
+  /// buses by buses
+  pub bbus: SparseMatrix<f64>,
+  /// branches by buses
+  pub bf: SparseMatrix<f64>,
+  pub pbusinj: Vec<f64>,
+  pub pfinj: Vec<f64>,
+}
+
+/// Which matrix of the fast-decoupled power flow leaves out branch resistances: `B'` in the XB scheme and `B''` in the
+/// BX scheme.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scheme {
+  XB,
+  BX,
+}
+
+impl Case {
+  /// `Ybus`, `Yf` and `Yt` from the branches and bus shunts. A `tap` of 0 is a ratio of 1. Fails if a branch refers to
+  /// a missing bus or an in-service branch has no impedance.
+  pub fn admittance(&self) -> Result<Admittance> {
+    let index = self.bus_index()?;
+    let (nb, nl) = (self.bus.len(), self.branch.len());
+    let (mut yf, mut yt) = (Vec::with_capacity(2 * nl), Vec::with_capacity(2 * nl));
+    let mut ybus = Vec::with_capacity(4 * nl + nb);
+    for (i, br) in self.branch.iter().enumerate() {
+      let (f, t) = ends(&index, i, br)?;
+      if !br.in_service() {
+        continue;
+      }
+      if br.br_r == 0.0 && br.br_x == 0.0 {
+        return Err(anyhow!("Branch {} from bus {} to bus {} has zero impedance", i + 1, br.f_bus, br.t_bus));
+      }
+      let ys = Complex64::new(br.br_r, br.br_x).inv();
+      let ytt = ys + Complex64::new(0.0, br.br_b / 2.0);
+      let ratio = if br.tap == 0.0 { 1.0 } else { br.tap };
+      let tap = Complex64::from_polar(ratio, br.shift.to_radians());
+      let (yff, yft, ytf) = (ytt / (ratio * ratio), -ys / tap.conj(), -ys / tap);
+      yf.extend(vec![(i, f, yff), (i, t, yft)]);
+      yt.extend(vec![(i, f, ytf), (i, t, ytt)]);
+      ybus.extend(vec![(f, f, yff), (f, t, yft), (t, f, ytf), (t, t, ytt)]);
+    }
+    for (i, b) in self.bus.iter().enumerate() {
+      ybus.push((i, i, Complex64::new(b.shunt_conductance, b.shunt_susceptance) / self.base_mva));
+    }
+    Ok(Admittance {
+      ybus: SparseMatrix::from_triplets(nb, nb, ybus),
+      yf: SparseMatrix::from_triplets(nl, nb, yf),
+      yt: SparseMatrix::from_triplets(nl, nb, yt),
+    })
+  }
+
+  /// `Bbus`, `Bf`, `Pbusinj` and `Pfinj` from the branch reactances, taps and phase shifts. A `tap` of 0 is a ratio of
+  /// 1. Fails if a branch refers to a missing bus or an in-service branch has no reactance.
+  pub fn susceptance(&self) -> Result<Susceptance> {
+    let index = self.bus_index()?;
+    let (nb, nl) = (self.bus.len(), self.branch.len());
+    let (mut bf, mut bbus) = (Vec::with_capacity(2 * nl), Vec::with_capacity(4 * nl));
+    let (mut pbusinj, mut pfinj) = (vec![0.0; nb], vec![0.0; nl]);
+    for (i, br) in self.branch.iter().enumerate() {
+      let (f, t) = ends(&index, i, br)?;
+      if !br.in_service() {
+        continue;
+      }
+      if br.br_x == 0.0 {
+        return Err(anyhow!("Branch {} from bus {} to bus {} has zero reactance", i + 1, br.f_bus, br.t_bus));
+      }
+      let b = 1.0 / br.br_x / if br.tap == 0.0 { 1.0 } else { br.tap };
+      bf.extend(vec![(i, f, b), (i, t, -b)]);
+      bbus.extend(vec![(f, f, b), (f, t, -b), (t, f, -b), (t, t, b)]);
+      pfinj[i] = -b * br.shift.to_radians();
+      pbusinj[f] += pfinj[i];
+      pbusinj[t] -= pfinj[i];
+    }
+    Ok(Susceptance {
+      bbus: SparseMatrix::from_triplets(nb, nb, bbus),
+      bf: SparseMatrix::from_triplets(nl, nb, bf),
+      pbusinj,
+      pfinj,
+    })
+  }
+
+  /// `B'` and `B''` of the fast-decoupled power flow, as MATPOWER's `makeB`. `B'` leaves out bus shunts, line charging
+  /// and tap ratios, and `B''` phase shifts. The scheme decides which of the two leaves out resistances.
+  pub fn decoupled_susceptance(&self, scheme: Scheme) -> Result<(SparseMatrix<f64>, SparseMatrix<f64>)> {
+    let mut c = self.clone();
+    for b in c.bus.iter_mut() {
+      b.shunt_susceptance = 0.0;
+    }
+    for br in c.branch.iter_mut() {
+      br.br_b = 0.0;
+      br.tap = 1.0;
+      if scheme == Scheme::XB {
+        br.br_r = 0.0;
+      }
+    }
+    let bp = c.admittance()?.ybus;
+    let mut c = self.clone();
+    for br in c.branch.iter_mut() {
+      br.shift = 0.0;
+      if scheme == Scheme::BX {
+        br.br_r = 0.0;
+      }
+    }
+    let bpp = c.admittance()?.ybus;
+    let negative_imaginary = |m: &SparseMatrix<Complex64>| {
+      SparseMatrix::from_triplets(m.rows(), m.cols(), m.iter().map(|(i, j, v)| (i, j, -v.im)))
+    };
+    Ok((negative_imaginary(&bp), negative_imaginary(&bpp)))
+  }
+}
+
+// Rows in `Case::bus` of the ends of branch `i`.
+pub(crate) fn ends(index: &BusIndex, i: usize, br: &Branch) -> Result<(usize, usize)> {
+  let position =
+    |id| index.position(id).ok_or_else(|| anyhow!("Branch {} refers to bus {}, which is not in mpc.bus", i + 1, id));
+  Ok((position(br.f_bus)?, position(br.t_bus)?))
+}
+
+#[test]
+fn test_sparse_matrix() {
+  let m = SparseMatrix::from_triplets(2, 3, vec![(1, 2, 4.0), (0, 1, 1.0), (1, 2, 0.5), (0, 0, 2.0), (0, 1, -1.0)]);
+  assert_eq!((m.nnz(), m.get(0, 0), m.get(0, 1), m.get(1, 2)), (2, 2.0, 0.0, 4.5));
+  assert_eq!(m.iter().collect::<Vec<_>>(), vec![(0, 0, 2.0), (1, 2, 4.5)]);
+  assert_eq!(m.mul_vec(&[1.0, 2.0, 2.0]), vec![2.0, 9.0]);
+  assert_eq!(m.to_matrix_market(), "%%MatrixMarket matrix coordinate real general\n2 3 2\n1 1 2\n2 3 4.5\n");
+}
+
+#[test]
+fn test_admittance() {
+  use crate::{
+    builder::CaseBuilder,
+    case::{Branch, Bus, BusId},
+  };
+
+  let bus = |n: usize| Bus { idx: BusId(n), ..Bus::default() };
+  let line = Branch { f_bus: BusId(20), t_bus: BusId(10), br_r: 0.5, br_x: 0.5, br_b: 0.5, ..Branch::default() };
+  let mut c = CaseBuilder::new("case")
+    .bus(bus(20))
+    .bus(Bus { shunt_susceptance: 25.0, ..bus(10) })
+    .bus(bus(30))
+    .branch(line)
+    .branch(Branch { t_bus: BusId(30), ..line })
+    .build()
+    .unwrap();
+  c.branch[1].br_status = 0.0;
+  let y = c.admittance().unwrap();
+  assert_eq!(
+    y.ybus.to_matrix_market(),
+    "%%MatrixMarket matrix coordinate complex general\n3 3 4\n1 1 1 -0.75\n1 2 -1 1\n2 1 -1 1\n2 2 1 -0.5\n"
+  );
+  assert_eq!(
+    (y.yf.rows(), y.yf.nnz(), y.yf.get(0, 1), y.yt.get(0, 0)),
+    (2, 2, Complex64::new(-1.0, 1.0), Complex64::new(-1.0, 1.0))
+  );
+
+  // A phase shifting transformer makes `Ybus` unsymmetric, and injections are the sums of the branch end currents.
+  c.branch[1] = Branch { br_status: 1.0, tap: 0.95, shift: 10.0, ..c.branch[1] };
+  let y = c.admittance().unwrap();
+  let (f, t) = (y.ybus.get(0, 2), y.ybus.get(2, 0));
+  assert!((f - t).norm() > 0.1 && (f.norm() - t.norm()).abs() < 1e-12);
+  assert!((y.yf.get(1, 0) - y.yt.get(1, 2) / (0.95 * 0.95)).norm() < 1e-12);
+  let v = vec![Complex64::new(1.0, 0.0), Complex64::from_polar(0.98, -0.1), Complex64::from_polar(1.02, 0.05)];
+  let (i, i_f, i_t) = (y.ybus.mul_vec(&v), y.yf.mul_vec(&v), y.yt.mul_vec(&v));
+  let shunt = Complex64::new(0.0, 0.25) * v[1];
+  assert!((i[0] - i_f[0] - i_f[1]).norm() < 1e-12);
+  assert!((i[1] - i_t[0] - shunt).norm() < 1e-12);
+  assert!((i[2] - i_t[1]).norm() < 1e-12);
+
+  c.branch[0].br_r = 0.0;
+  c.branch[0].br_x = 0.0;
+  assert_eq!(c.admittance().unwrap_err().to_string(), "Branch 1 from bus 20 to bus 10 has zero impedance");
+}
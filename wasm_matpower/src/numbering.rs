@@ -0,0 +1,254 @@
+// Bus renumbering
+
+// MATPOWER's `ext2int` and `int2ext`. The internal case keeps only in-service elements, numbers buses 0..n in table
+// order so bus numbers can index vectors and matrices, and orders generators by bus. `NumberingMap` holds the
+// original case, which `int2ext` writes the internal rows back into, so out-of-service elements come back unchanged.
+// `compact_numbering` is the one-way alternative that keeps every element and renumbers buses 1..n.
+
+use std::collections::HashMap;
+
+use anyhow::{anyhow, Result};
+
+use crate::case::{Branch, Bus, BusId, BusType, Case, DcLine, Gen};
+
+/// How an internal case from `Case::ext2int` relates to the original. Row lists give, for each internal row, its
+/// position in the original table.
+#[derive(Debug, Clone)]
+pub struct NumberingMap {
+  /// external bus number of each internal bus
+  pub bus_i2e: Vec<BusId>,
+  /// original rows of the internal buses
+  pub bus: Vec<usize>,
+  /// original rows of the internal generators
+  pub gen: Vec<usize>,
+  /// original rows of the internal branches
+  pub branch: Vec<usize>,
+  /// original rows of the internal DC lines
+  pub dcline: Vec<usize>,
+  external: Case,
+  e2i: HashMap<BusId, usize>,
+}
+
+impl NumberingMap {
+  /// Internal bus number of external bus `id`, if the bus is in service.
+  pub fn internal(&self, id: BusId) -> Option<usize> {
+    self.e2i.get(&id).copied()
+  }
+
+  /// External bus number of internal bus `i`.
+  pub fn external(&self, i: usize) -> Option<BusId> {
+    self.bus_i2e.get(i).copied()
+  }
+}
+
+impl Case {
+  /// The in-service part of the case with buses numbered 0..n, as MATPOWER's `ext2int`. Isolated buses, generators
+  /// and branches that are out of service or connect to an isolated bus, and DC lines that are out of service or
+  /// connect to a removed bus are left out. Generators are sorted by internal bus, keeping table order at each bus,
+  /// and their costs follow them.
+  pub fn ext2int(&self) -> Result<(Case, NumberingMap)> {
+    let index = self.bus_index()?;
+    if let Some(d) = index.dangling(self).first() {
+      return Err(anyhow!("{}", d));
+    }
+    self.check_gencost_rows()?;
+    let ng = self.gen.len();
+
+    let bus = (0..self.bus.len()).filter(|i| self.bus[*i].bus_type != BusType::Isolated).collect::<Vec<_>>();
+    let bus_i2e = bus.iter().map(|i| self.bus[*i].idx).collect::<Vec<_>>();
+    let e2i = bus_i2e.iter().enumerate().map(|(i, id)| (*id, i)).collect::<HashMap<_, _>>();
+    let mut gen =
+      (0..ng).filter(|i| self.gen[*i].in_service() && e2i.contains_key(&self.gen[*i].gen)).collect::<Vec<_>>();
+    gen.sort_by_key(|i| e2i[&self.gen[*i].gen]);
+    let branch = (0..self.branch.len())
+      .filter(|i| {
+        let br = &self.branch[*i];
+        br.in_service() && e2i.contains_key(&br.f_bus) && e2i.contains_key(&br.t_bus)
+      })
+      .collect::<Vec<_>>();
+    let dcline = (0..self.dcline.len())
+      .filter(|i| {
+        let d = &self.dcline[*i];
+        d.in_service() && e2i.contains_key(&d.f_bus) && e2i.contains_key(&d.t_bus)
+      })
+      .collect::<Vec<_>>();
+
+    let internal = |id: BusId| BusId(e2i[&id]);
+    let mut c = Case {
+      name: self.name.clone(),
+      version: self.version,
+      base_mva: self.base_mva,
+      bus: vec![],
+      gen: vec![],
+      gencost: vec![],
+      branch: vec![],
+      dcline: vec![],
+      bus_name: vec![],
+      extra: Default::default(),
+    };
+    for (i, b) in bus.iter().enumerate() {
+      c.bus.push(Bus { idx: BusId(i), ..self.bus[*b] });
+      if let Some(name) = self.bus_name.get(*b) {
+        c.bus_name.push(name.clone());
+      }
+    }
+    for g in gen.iter() {
+      c.gen.push(Gen { gen: internal(self.gen[*g].gen), ..self.gen[*g] });
+    }
+    if !self.gencost.is_empty() {
+      c.gencost.extend(gen.iter().map(|g| self.gencost[*g].clone()));
+      if self.gencost.len() == 2 * ng {
+        c.gencost.extend(gen.iter().map(|g| self.gencost[ng + g].clone()));
+      }
+    }
+    for br in branch.iter() {
+      let br = &self.branch[*br];
+      c.branch.push(Branch { f_bus: internal(br.f_bus), t_bus: internal(br.t_bus), ..*br });
+    }
+    for d in dcline.iter() {
+      let d = &self.dcline[*d];
+      c.dcline.push(DcLine { f_bus: internal(d.f_bus), t_bus: internal(d.t_bus), ..*d });
+    }
+    Ok((c, NumberingMap { bus_i2e, bus, gen, branch, dcline, external: self.clone(), e2i }))
+  }
+
+  /// The original case with the rows of this internal case written back in external numbering, as MATPOWER's
+  /// `int2ext`. Elements `ext2int` left out are as they were.
+  pub fn int2ext(&self, map: &NumberingMap) -> Result<Case> {
+    let ng = map.external.gen.len();
+    let cost_rows = match map.external.gencost.len() {
+      0 => 0,
+      n if n == 2 * ng => 2 * map.gen.len(),
+      _ => map.gen.len(),
+    };
+    if self.bus.len() != map.bus.len()
+      || self.gen.len() != map.gen.len()
+      || self.gencost.len() != cost_rows
+      || self.branch.len() != map.branch.len()
+      || self.dcline.len() != map.dcline.len()
+    {
+      return Err(anyhow!("The case does not match the numbering map, were elements added or removed?"));
+    }
+    let external = |id: BusId| map.external(id.0).ok_or_else(|| anyhow!("Unknown internal bus {}", id));
+    let mut c = map.external.clone();
+    for (b, e) in self.bus.iter().zip(map.bus.iter()) {
+      c.bus[*e] = Bus { idx: external(b.idx)?, ..*b };
+    }
+    for (g, e) in self.gen.iter().zip(map.gen.iter()) {
+      c.gen[*e] = Gen { gen: external(g.gen)?, ..*g };
+    }
+    if !c.gencost.is_empty() {
+      for (i, e) in map.gen.iter().enumerate() {
+        c.gencost[*e] = self.gencost[i].clone();
+        if c.gencost.len() == 2 * ng {
+          c.gencost[ng + e] = self.gencost[map.gen.len() + i].clone();
+        }
+      }
+    }
+    for (br, e) in self.branch.iter().zip(map.branch.iter()) {
+      c.branch[*e] = Branch { f_bus: external(br.f_bus)?, t_bus: external(br.t_bus)?, ..*br };
+    }
+    for (d, e) in self.dcline.iter().zip(map.dcline.iter()) {
+      c.dcline[*e] = DcLine { f_bus: external(d.f_bus)?, t_bus: external(d.t_bus)?, ..*d };
+    }
+    Ok(c)
+  }
+
+  /// Renumber buses 1..n in table order and update every reference to them. Nothing is removed or reordered.
+  /// Returns the new number of each old bus number.
+  pub fn compact_numbering(&mut self) -> Result<HashMap<BusId, BusId>> {
+    let index = self.bus_index()?;
+    if let Some(d) = index.dangling(self).first() {
+      return Err(anyhow!("{}", d));
+    }
+    let new = self.bus.iter().enumerate().map(|(i, b)| (b.idx, BusId(i + 1))).collect::<HashMap<_, _>>();
+    for b in self.bus.iter_mut() {
+      b.idx = new[&b.idx];
+    }
+    for g in self.gen.iter_mut() {
+      g.gen = new[&g.gen];
+    }
+    for br in self.branch.iter_mut() {
+      br.f_bus = new[&br.f_bus];
+      br.t_bus = new[&br.t_bus];
+    }
+    for d in self.dcline.iter_mut() {
+      d.f_bus = new[&d.f_bus];
+      d.t_bus = new[&d.t_bus];
+    }
+    Ok(new)
+  }
+}
+
+#[cfg(test)]
+const CASE4: &str = r#"function mpc = case4
+mpc.version = '2';
+mpc.baseMVA = 100;
+mpc.bus = [
+	10	3	0	0	0	0	1	1	0	230	1	1.1	0.9;
+	30	1	50	10	0	0	1	1	0	230	1	1.1	0.9;
+	20	4	0	0	0	0	1	1	0	230	1	1.1	0.9;
+	40	2	90	30	0	0	1	1	0	230	1	1.1	0.9;
+];
+mpc.gen = [
+	40	60	0	300	-300	1	100	1	250	10	0	0	0	0	0	0	0	0	0	0	0;
+	10	0	0	300	-300	1	100	1	250	10	0	0	0	0	0	0	0	0	0	0	0;
+	20	5	0	300	-300	1	100	1	250	10	0	0	0	0	0	0	0	0	0	0	0;
+	30	7	0	300	-300	1	100	0	250	10	0	0	0	0	0	0	0	0	0	0	0;
+];
+mpc.branch = [
+	10	30	0.01	0.1	0	250	250	300	0	0	1	-360	360;
+	30	20	0.01	0.1	0	250	250	300	0	0	1	-360	360;
+	30	40	0.01	0.1	0	250	250	300	0	0	1	-360	360;
+	10	40	0.01	0.1	0	250	250	300	0	0	0	-360	360;
+];
+mpc.gencost = [
+	2	0	0	2	40	0;
+	2	0	0	2	10	0;
+	2	0	0	2	50	0;
+	2	0	0	2	70	0;
+];
+"#;
+
+#[test]
+fn test_ext2int() {
+  let c = crate::case::case(CASE4).unwrap();
+  let (i, map) = c.ext2int().unwrap();
+  assert_eq!(map.bus_i2e, vec![BusId(10), BusId(30), BusId(40)]);
+  assert_eq!(i.bus.iter().map(|b| b.idx.0).collect::<Vec<_>>(), vec![0, 1, 2]);
+  // Generators at buses 20 and 30 are dropped, the rest sorted by bus.
+  assert_eq!(map.gen, vec![1, 0]);
+  assert_eq!(i.gen.iter().map(|g| (g.gen.0, g.pg)).collect::<Vec<_>>(), vec![(0, 0.0), (2, 60.0)]);
+  assert_eq!(i.gencost.iter().map(|g| g.cost[0]).collect::<Vec<_>>(), vec![10.0, 40.0]);
+  assert_eq!(i.branch.iter().map(|b| (b.f_bus.0, b.t_bus.0)).collect::<Vec<_>>(), vec![(0, 1), (1, 2)]);
+  assert_eq!((map.internal(BusId(40)), map.internal(BusId(20)), map.external(1)), (Some(2), None, Some(BusId(30))));
+
+  let mut solved = i.clone();
+  solved.bus[1].voltage_mag = 0.98;
+  solved.gen[1].qg = 12.0;
+  let e = solved.int2ext(&map).unwrap();
+  assert_eq!((e.bus[1].idx, e.bus[1].voltage_mag), (BusId(30), 0.98));
+  assert_eq!((e.gen[0].gen, e.gen[0].qg), (BusId(40), 12.0));
+  assert_eq!(e.bus[2], c.bus[2]);
+  assert_eq!((e.gen[3], e.branch[3]), (c.gen[3], c.branch[3]));
+  assert_eq!(i.int2ext(&map).unwrap(), c);
+
+  solved.gencost.pop();
+  assert!(solved.int2ext(&map).is_err());
+  solved.gen.pop();
+  assert!(solved.int2ext(&map).is_err());
+}
+
+#[test]
+fn test_compact_numbering() {
+  let mut c = crate::case::case(CASE4).unwrap();
+  let map = c.compact_numbering().unwrap();
+  assert_eq!(
+    (map[&BusId(10)], map[&BusId(30)], map[&BusId(20)], map[&BusId(40)]),
+    (BusId(1), BusId(2), BusId(3), BusId(4))
+  );
+  assert_eq!(c.bus.iter().map(|b| b.idx.0).collect::<Vec<_>>(), vec![1, 2, 3, 4]);
+  assert_eq!(c.gen.iter().map(|g| g.gen.0).collect::<Vec<_>>(), vec![4, 1, 3, 2]);
+  assert_eq!(c.branch.iter().map(|b| (b.f_bus.0, b.t_bus.0)).collect::<Vec<_>>(), vec![(1, 2), (2, 3), (2, 4), (1, 4)]);
+  assert!(c.bus_index().unwrap().dangling(&c).is_empty());
+}
@@ -0,0 +1,213 @@
+// OpenDSS script export
+
+// A positive-sequence case written as a balanced three phase `.dss` script. The reference bus becomes the circuit's
+// source, so generators there are left to it. Zero-sequence data is not known and is set equal to positive sequence.
+// Transformers have no phase shift or line charging in OpenDSS; branches that need them get a warning at the top of
+// the script.
+
+use std::fmt::Write;
+
+use anyhow::{anyhow, Result};
+
+use crate::{
+  case::{BusType, Case},
+  units::Base,
+};
+
+// DSS names cannot hold spaces, dots or brackets.
+fn dss_name(s: &str) -> String {
+  let s = s.chars().map(|c| if c.is_ascii_alphanumeric() || c == '_' || c == '-' { c } else { '_' }).collect::<String>();
+  if s.is_empty() {
+    "case".to_string()
+  } else {
+    s
+  }
+}
+
+impl Case {
+  /// OpenDSS script with a `Circuit` sourced at the reference bus and `Line`, `Transformer`, `Load`, `Generator`,
+  /// `Capacitor` and `Reactor` elements for the rest of the case. Buses are named by bus number.
+  pub fn to_opendss(&self) -> Result<String> {
+    let units = self.units()?;
+    let reference = self.bus.iter().find(|b| b.bus_type == BusType::Ref).ok_or_else(|| anyhow!("No reference bus"))?;
+    let mut branches = String::new();
+    let mut warnings = vec![];
+    let w = &mut branches;
+    writeln!(w, "\n! Branches")?;
+    for (i, br) in self.branch.iter().enumerate() {
+      let (f, t) = (br.f_bus, br.t_bus);
+      let (kv_f, kv_t) = (units.base_kv(f)?, units.base_kv(t)?);
+      let enabled = if br.br_status > 0.0 { "" } else { " enabled=no" };
+      if br.tap == 0.0 && br.shift == 0.0 && kv_f == kv_t {
+        let si = units.branch_si(br)?;
+        let (r, x, b) = (si.r, si.x, si.b);
+        // A rating of 0 is unlimited.
+        let amps = |name: &str, mva: f64| {
+          if mva > 0.0 {
+            format!(" {}={}", name, Base::new(mva, kv_f).i() * 1000.0)
+          } else {
+            String::new()
+          }
+        };
+        writeln!(
+          w,
+          "New Line.L{} bus1={} bus2={} phases=3 length=1 units=none r1={} x1={} b1={} r0={} x0={} b0={}{}{}{}",
+          i + 1,
+          f,
+          t,
+          r,
+          x,
+          b,
+          r,
+          x,
+          b,
+          amps("normamps", br.rate_a),
+          amps("emergamps", br.rate_c),
+          enabled
+        )?;
+      } else {
+        // Per unit on the transformer rating, with the off-nominal tap on the from winding
+        let mva = if br.rate_a > 0.0 { br.rate_a } else { self.base_mva };
+        let (kva, pu) = (mva * 1000.0, units.branch_pu(br, Base::new(mva, kv_f))?);
+        if br.shift != 0.0 {
+          warnings.push(format!("T{} has a phase shift of {} degrees, which is left out", i + 1, br.shift));
+        }
+        if br.br_b != 0.0 {
+          warnings.push(format!("T{} has a line charging of {} p.u., which is left out", i + 1, br.br_b));
+        }
+        writeln!(
+          w,
+          "New Transformer.T{} phases=3 windings=2 buses=[{} {}] conns=[wye wye] kvs=[{} {}] kvas=[{} {}] taps=[{} 1] %Rs=[{} {}] XHL={}{}",
+          i + 1,
+          f,
+          t,
+          kv_f,
+          kv_t,
+          kva,
+          kva,
+          br.ratio(),
+          pu.r * 50.0,
+          pu.r * 50.0,
+          pu.x * 100.0,
+          enabled
+        )?;
+      }
+    }
+
+    let mut s = String::new();
+    let w = &mut s;
+    writeln!(w, "! {} converted from MATPOWER, {} MVA base", self.name, self.base_mva)?;
+    for warning in warnings.iter() {
+      writeln!(w, "! Warning: {}", warning)?;
+    }
+    writeln!(w, "Clear")?;
+    writeln!(
+      w,
+      "New Circuit.{} bus1={} basekv={} pu={} angle={} phases=3 MVAsc3=1e6 MVAsc1=1e6",
+      dss_name(&self.name),
+      reference.idx,
+      units.base_kv(reference.idx)?,
+      reference.voltage_mag,
+      reference.voltage_ang
+    )?;
+
+    s.push_str(&branches);
+    let w = &mut s;
+
+    writeln!(w, "\n! Loads and shunts")?;
+    for b in self.bus.iter() {
+      let kv = units.base_kv(b.idx)?;
+      if b.pd != 0.0 || b.qd != 0.0 {
+        writeln!(w, "New Load.D{} bus1={} phases=3 kV={} kW={} kvar={} model=1", b.idx, b.idx, kv, b.pd * 1000.0, b.qd * 1000.0)?;
+      }
+      if b.shunt_conductance != 0.0 {
+        writeln!(w, "New Load.G{} bus1={} phases=3 kV={} kW={} kvar=0 model=2", b.idx, b.idx, kv, b.shunt_conductance * 1000.0)?;
+      }
+      if b.shunt_susceptance > 0.0 {
+        writeln!(w, "New Capacitor.C{} bus1={} phases=3 kV={} kvar={}", b.idx, b.idx, kv, b.shunt_susceptance * 1000.0)?;
+      } else if b.shunt_susceptance < 0.0 {
+        writeln!(w, "New Reactor.R{} bus1={} phases=3 kV={} kvar={}", b.idx, b.idx, kv, -b.shunt_susceptance * 1000.0)?;
+      }
+    }
+
+    writeln!(w, "\n! Generators")?;
+    for (i, g) in self.gen.iter().enumerate() {
+      if g.gen == reference.idx {
+        writeln!(w, "! G{} at the reference bus is represented by the circuit source", i + 1)?;
+        continue;
+      }
+      let enabled = if g.gen_status > 0 { "" } else { " enabled=no" };
+      writeln!(
+        w,
+        "New Generator.G{} bus1={} phases=3 kV={} kW={} kvar={} model=3 Vpu={} maxkvar={} minkvar={} kVA={}{}",
+        i + 1,
+        g.gen,
+        units.base_kv(g.gen)?,
+        g.pg * 1000.0,
+        g.qg * 1000.0,
+        g.vg,
+        g.qmax * 1000.0,
+        g.qmin * 1000.0,
+        g.mbase * 1000.0,
+        enabled
+      )?;
+    }
+
+    let mut bases = self.bus.iter().map(|b| b.base_kv).filter(|kv| *kv > 0.0).collect::<Vec<_>>();
+    bases.sort_by(|a, b| a.total_cmp(b));
+    bases.dedup();
+    writeln!(w, "\nSet VoltageBases=[{}]", bases.iter().map(|kv| kv.to_string()).collect::<Vec<_>>().join(" "))?;
+    writeln!(w, "CalcVoltageBases")?;
+    Ok(s)
+  }
+}
+
+#[test]
+fn test_to_opendss() {
+  let mut c = crate::case::case(
+    r#"function mpc = case3
+mpc.version = '2';
+mpc.baseMVA = 100;
+mpc.bus = [
+	1	3	0	0	0	0	1	1.02	0	230	1	1.1	0.9;
+	2	2	90	30	0	19	1	1	0	230	1	1.1	0.9;
+	3	1	100	35	0	0	1	1	0	115	1	1.1	0.9;
+];
+mpc.gen = [
+	1	0	0	300	-300	1.02	100	1	250	10	0	0	0	0	0	0	0	0	0	0	0;
+	2	163	0	300	-300	1	100	1	300	10	0	0	0	0	0	0	0	0	0	0	0;
+];
+mpc.branch = [
+	1	2	0.01	0.1	0.2	250	250	300	0	0	1	-360	360;
+	2	3	0	0.05	0.01	100	100	100	1.05	-30	1	-360	360;
+	1	2	0.01	0.1	0.2	0	0	0	0	0	1	-360	360;
+];
+"#,
+  )
+  .unwrap();
+  let s = c.to_opendss().unwrap();
+  let lines = s.lines().collect::<Vec<_>>();
+  assert!(lines.contains(&"New Circuit.case3 bus1=1 basekv=230 pu=1.02 angle=0 phases=3 MVAsc3=1e6 MVAsc1=1e6"));
+  let line = lines.iter().find(|l| l.starts_with("New Line.L1 ")).unwrap();
+  assert!(line.contains("bus1=1 bus2=2") && line.contains(" r1=5.29 ") && line.contains(" x1=52.9"));
+  assert!(line.contains(" normamps=") && line.contains(" emergamps="));
+  let unlimited = lines.iter().find(|l| l.starts_with("New Line.L3 ")).unwrap();
+  assert!(!unlimited.contains("amps="));
+  assert_eq!(
+    lines[1..3],
+    [
+      "! Warning: T2 has a phase shift of -30 degrees, which is left out",
+      "! Warning: T2 has a line charging of 0.01 p.u., which is left out",
+    ]
+  );
+  let t = lines.iter().find(|l| l.starts_with("New Transformer.T2 ")).unwrap();
+  assert!(t.contains("kvs=[230 115] kvas=[100000 100000] taps=[1.05 1]") && t.contains("XHL=5"));
+  assert!(lines.contains(&"New Load.D2 bus1=2 phases=3 kV=230 kW=90000 kvar=30000 model=1"));
+  assert!(lines.contains(&"New Capacitor.C2 bus1=2 phases=3 kV=230 kvar=19000"));
+  assert!(lines.iter().any(|l| l.starts_with("New Generator.G2 bus1=2 phases=3 kV=230 kW=163000")));
+  assert!(!lines.iter().any(|l| l.starts_with("New Generator.G1 ")));
+  assert!(lines.contains(&"Set VoltageBases=[115 230]"));
+
+  c.bus[2].base_kv = 0.0;
+  assert_eq!(c.to_opendss().unwrap_err().to_string(), "Bus 3 has no base kV");
+}
@@ -0,0 +1,666 @@
+// Power flow
+
+// Power flows solve a case for its bus voltages and branch flows, as MATPOWER's `runpf` does. Each island is solved
+// with its reference buses as slack, so every island needs one. Isolated buses and the branches to them are left out.
+// The solution is written into a copy of the case, as MATPOWER's `results`: bus voltages, the real power of the first
+// in-service generator at each reference bus, and branch flows, which are zero for branches out of service.
+
+use std::collections::HashSet;
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+  case::{BusType, Case, Gen},
+  lu::SparseLu,
+  network::{ends, Admittance, Complex64, Scheme, SparseMatrix},
+};
+
+/// A solved case, or the reason a power flow failed.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct PowerFlowResult {
+  /// the case with the solution, or the case as given if the power flow failed
+  pub case: Case,
+  pub success: bool,
+  pub iterations: usize,
+  /// largest power mismatch in p.u. before the first iteration and after each, for the AC power flow. The
+  /// fast-decoupled method divides mismatches by the bus voltage magnitude.
+  pub mismatch: Vec<f64>,
+  pub message: Option<String>,
+}
+
+impl PowerFlowResult {
+  fn failed(case: &Case, e: anyhow::Error) -> PowerFlowResult {
+    PowerFlowResult {
+      case: case.clone(),
+      success: false,
+      iterations: 0,
+      mismatch: vec![],
+      message: Some(e.to_string()),
+    }
+  }
+}
+
+/// The DC power flow, MATPOWER's `rundcpf`: bus angles from the linearised real power balance, with voltage
+/// magnitudes of 1 p.u. and no losses. Reactive power flows are zero.
+pub fn dc_power_flow(case: &Case) -> PowerFlowResult {
+  match solve_dc(case) {
+    Ok(solved) => PowerFlowResult { case: solved, success: true, iterations: 1, mismatch: vec![], message: None },
+    Err(e) => PowerFlowResult::failed(case, e),
+  }
+}
+
+fn solve_dc(case: &Case) -> Result<Case> {
+  let network = connected(case);
+  let index = network.bus_index()?;
+  let s = network.susceptance()?;
+  let (reference, unknown) = slack(&network)?;
+  let base = case.base_mva;
+
+  // Real power injections in per unit, less those of phase shifters and shunts.
+  let mut p = network
+    .bus
+    .iter()
+    .zip(s.pbusinj.iter())
+    .map(|(b, inj)| -(b.pd + b.shunt_conductance) / base - inj)
+    .collect::<Vec<_>>();
+  for g in network.in_service_gens() {
+    if let Some(i) = index.position(g.gen) {
+      p[i] += g.pg / base;
+    }
+  }
+  let mut va = network.bus.iter().map(|b| b.voltage_ang_rad()).collect::<Vec<_>>();
+  let mut position = vec![None; va.len()];
+  for (k, &i) in unknown.iter().enumerate() {
+    position[i] = Some(k);
+  }
+  let mut rhs = unknown.iter().map(|&i| p[i]).collect::<Vec<_>>();
+  let mut b = vec![];
+  for (i, j, v) in s.bbus.iter() {
+    match (position[i], position[j]) {
+      (Some(k), Some(m)) => b.push((k, m, v)),
+      (Some(k), None) => rhs[k] -= v * va[j],
+      _ => {},
+    }
+  }
+  let b = SparseMatrix::from_triplets(unknown.len(), unknown.len(), b);
+  let lu = SparseLu::new(&b).map_err(|_| anyhow!("The DC power flow equations are singular"))?;
+  for (&i, theta) in unknown.iter().zip(lu.solve(&rhs)) {
+    va[i] = theta;
+  }
+
+  let mut solved = case.clone();
+  for &i in unknown.iter() {
+    solved.bus[i].voltage_ang = va[i].to_degrees();
+  }
+  for &i in reference.iter().chain(unknown.iter()) {
+    solved.bus[i].voltage_mag = 1.0;
+  }
+  let injection = s.bbus.mul_vec(&va);
+  for &r in reference.iter() {
+    let id = solved.bus[r].idx;
+    let g = solved
+      .gen
+      .iter_mut()
+      .find(|g| g.gen == id && g.in_service())
+      .ok_or_else(|| anyhow!("Reference bus {} has no in-service generator", id))?;
+    g.pg += (injection[r] - p[r]) * base;
+  }
+  let flows = s.bf.mul_vec(&va);
+  for (i, br) in solved.branch.iter_mut().enumerate() {
+    let pf = if network.branch[i].in_service() { (flows[i] + s.pfinj[i]) * base } else { 0.0 };
+    br.pf = Some(pf);
+    br.pt = Some(-pf);
+    br.qf = Some(0.0);
+    br.qt = Some(0.0);
+  }
+  Ok(solved)
+}
+
+/// Methods of solving the AC power flow, named as MATPOWER's `pf.alg` option.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Algorithm {
+  /// Newton's method, with the Jacobian factored at every iteration
+  #[serde(rename = "NR")]
+  Newton,
+  /// the fast-decoupled method, XB scheme
+  #[serde(rename = "FDXB")]
+  FastDecoupledXB,
+  /// the fast-decoupled method, BX scheme
+  #[serde(rename = "FDBX")]
+  FastDecoupledBX,
+}
+
+/// Settings of the AC power flow. The defaults are MATPOWER's.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct PowerFlowOptions {
+  pub algorithm: Algorithm,
+  /// largest power mismatch of a solution, in p.u.
+  pub tolerance: f64,
+  /// 10 for Newton's method and 30 for the fast-decoupled method if not set
+  pub max_iterations: Option<usize>,
+  /// make PV buses whose generators go past a reactive power limit PQ buses, with the generators at the limit, and
+  /// solve again. Generators at reference buses are not limited.
+  pub enforce_q_limits: bool,
+}
+
+impl Default for PowerFlowOptions {
+  fn default() -> PowerFlowOptions {
+    PowerFlowOptions { algorithm: Algorithm::Newton, tolerance: 1e-8, max_iterations: None, enforce_q_limits: false }
+  }
+}
+
+impl PowerFlowOptions {
+  fn max_iterations(&self) -> usize {
+    let default = if self.algorithm == Algorithm::Newton { 10 } else { 30 };
+    self.max_iterations.unwrap_or(default)
+  }
+}
+
+// Generators are past a reactive power limit by more than this, in MVAr.
+const Q_LIMIT_TOLERANCE: f64 = 5e-6;
+
+/// The AC power flow in polar coordinates, MATPOWER's `runpf`, by the method of `options.algorithm`. In-service
+/// generators at PV and reference buses hold the bus voltage magnitude at `vg`, and PV buses without one are PQ buses.
+/// The reactive power of a bus is shared among its generators in proportion to their reactive power ranges. A failed
+/// power flow reports the iterations and mismatches up to the failure.
+pub fn ac_power_flow(case: &Case, options: &PowerFlowOptions) -> PowerFlowResult {
+  let mut ac = match Ac::new(case) {
+    Ok(ac) => ac,
+    Err(e) => return PowerFlowResult::failed(case, e),
+  };
+  let solved = ac.solve(options);
+  let (iterations, mismatch) = (ac.iterations, ac.mismatch);
+  match solved {
+    Ok(solved) => PowerFlowResult { case: solved, success: true, iterations, mismatch, message: None },
+    Err(e) => PowerFlowResult { iterations, mismatch, ..PowerFlowResult::failed(case, e) },
+  }
+}
+
+// The AC power flow equations of a case, and the progress of solving them.
+struct Ac<'a> {
+  case: &'a Case,
+  network: Case,
+  y: Admittance,
+  gen_bus: Vec<usize>,
+  // generators at each bus
+  gens_at: Vec<Vec<usize>>,
+  branch_bus: Vec<(usize, usize)>,
+  reference: Vec<usize>,
+  // buses in islands, other than reference buses
+  others: Vec<usize>,
+  // reactive power of generators held at a limit
+  fixed: Vec<Option<f64>>,
+  iterations: usize,
+  mismatch: Vec<f64>,
+}
+
+impl<'a> Ac<'a> {
+  fn new(case: &'a Case) -> Result<Ac<'a>> {
+    let network = connected(case);
+    let index = network.bus_index()?;
+    let y = network.admittance()?;
+    let (reference, others) = slack(&network)?;
+    let gen_bus = network
+      .gen
+      .iter()
+      .enumerate()
+      .map(|(i, g)| {
+        index.position(g.gen).ok_or_else(|| anyhow!("Generator {} is at bus {}, which is not in mpc.bus", i + 1, g.gen))
+      })
+      .collect::<Result<Vec<_>>>()?;
+    let branch_bus =
+      network.branch.iter().enumerate().map(|(i, br)| ends(&index, i, br)).collect::<Result<Vec<_>>>()?;
+    let mut gens_at = vec![vec![]; network.bus.len()];
+    for (g, &i) in gen_bus.iter().enumerate() {
+      gens_at[i].push(g);
+    }
+    if let Some(&r) = reference.iter().find(|&&r| !gens_at[r].iter().any(|&g| network.gen[g].in_service())) {
+      return Err(anyhow!("Reference bus {} has no in-service generator", network.bus[r].idx));
+    }
+    let fixed = vec![None; network.gen.len()];
+    Ok(Ac { case, network, y, gen_bus, gens_at, branch_bus, reference, others, fixed, iterations: 0, mismatch: vec![] })
+  }
+
+  // True if generator `g` holds the voltage of its bus.
+  fn regulating(&self, g: usize) -> bool {
+    let bus_type = self.network.bus[self.gen_bus[g]].bus_type;
+    self.network.gen[g].in_service() && self.fixed[g].is_none() && matches!(bus_type, BusType::PV | BusType::Ref)
+  }
+
+  // The PV and PQ buses.
+  fn bus_types(&self) -> (Vec<usize>, Vec<usize>) {
+    let mut regulated = vec![false; self.network.bus.len()];
+    for g in (0..self.network.gen.len()).filter(|&g| self.regulating(g)) {
+      regulated[self.gen_bus[g]] = true;
+    }
+    self.others.iter().partition(|&&i| self.network.bus[i].bus_type == BusType::PV && regulated[i])
+  }
+
+  // Bus voltages of the case, with the magnitude at the setpoint of the first regulating generator at a bus.
+  fn initial_voltage(&self) -> Vec<Complex64> {
+    let mut v =
+      self.network.bus.iter().map(|b| Complex64::from_polar(b.voltage_mag, b.voltage_ang_rad())).collect::<Vec<_>>();
+    for g in (0..self.network.gen.len()).rev().filter(|&g| self.regulating(g)) {
+      let i = self.gen_bus[g];
+      v[i] = Complex64::from_polar(self.network.gen[g].vg, v[i].arg());
+    }
+    v
+  }
+
+  // Scheduled bus injections in per unit.
+  fn scheduled(&self) -> Vec<Complex64> {
+    let mut s = self.network.bus.iter().map(|b| -Complex64::new(b.pd, b.qd)).collect::<Vec<_>>();
+    for (g, gen) in self.network.gen.iter().enumerate().filter(|(_, g)| g.in_service()) {
+      s[self.gen_bus[g]] += Complex64::new(gen.pg, self.fixed[g].unwrap_or(gen.qg));
+    }
+    s.iter().map(|s| s / self.case.base_mva).collect()
+  }
+
+  fn solve(&mut self, options: &PowerFlowOptions) -> Result<Case> {
+    let mut v = self.initial_voltage();
+    loop {
+      let (pv, pq) = self.bus_types();
+      match options.algorithm {
+        Algorithm::Newton => self.newton(&mut v, &pv, &pq, options)?,
+        Algorithm::FastDecoupledXB => self.fast_decoupled(&mut v, &pv, &pq, options, Scheme::XB)?,
+        Algorithm::FastDecoupledBX => self.fast_decoupled(&mut v, &pv, &pq, options, Scheme::BX)?,
+      }
+      let solved = self.solution(&v, &pv);
+      if !options.enforce_q_limits {
+        return Ok(solved);
+      }
+      let at_pv = pv.iter().flat_map(|&i| self.gens_at[i].iter().copied()).filter(|&g| self.regulating(g));
+      let mut at_limit = false;
+      for g in at_pv.collect::<Vec<_>>() {
+        let gen = &solved.gen[g];
+        if gen.qg > gen.qmax + Q_LIMIT_TOLERANCE {
+          self.fixed[g] = Some(gen.qmax);
+        } else if gen.qg < gen.qmin - Q_LIMIT_TOLERANCE {
+          self.fixed[g] = Some(gen.qmin);
+        }
+        at_limit |= self.fixed[g].is_some();
+      }
+      if !at_limit {
+        return Ok(solved);
+      }
+    }
+  }
+
+  // Newton's method from `v`, with the angles of PV and PQ buses and the magnitudes of PQ buses as unknowns, as
+  // MATPOWER's `newtonpf`.
+  fn newton(&mut self, v: &mut [Complex64], pv: &[usize], pq: &[usize], options: &PowerFlowOptions) -> Result<()> {
+    let nb = v.len();
+    let pvpq = pv.iter().chain(pq.iter()).copied().collect::<Vec<_>>();
+    let (mut angle, mut magnitude) = (vec![None; nb], vec![None; nb]);
+    for (k, &i) in pvpq.iter().enumerate() {
+      angle[i] = Some(k);
+    }
+    for (k, &i) in pq.iter().enumerate() {
+      magnitude[i] = Some(pvpq.len() + k);
+    }
+    let s = self.scheduled();
+    let ybus = &self.y.ybus;
+    // Real power mismatches of PV and PQ buses, then reactive power mismatches of PQ buses.
+    let mismatch = |v: &[Complex64]| {
+      let current = ybus.mul_vec(v);
+      let mis = (0..nb).map(|i| v[i] * current[i].conj() - s[i]).collect::<Vec<_>>();
+      pvpq.iter().map(|&i| mis[i].re).chain(pq.iter().map(|&i| mis[i].im)).collect::<Vec<_>>()
+    };
+    let mut f = mismatch(v);
+    for iteration in 0.. {
+      if converged(&mut self.mismatch, &f, iteration, options)? {
+        break;
+      }
+
+      // The Jacobian, from the derivatives of the injections with respect to the voltage angles and magnitudes, as
+      // MATPOWER's `dSbus_dV`.
+      let current = ybus.mul_vec(v);
+      let unit = v.iter().map(|v| v / v.norm()).collect::<Vec<_>>();
+      let mut jacobian = vec![];
+      let mut add = |i: usize, k: usize, d_angle: Complex64, d_magnitude: Complex64| {
+        for (row, part) in [(angle[i], 0), (magnitude[i], 1)].iter() {
+          let part = |d: Complex64| if *part == 0 { d.re } else { d.im };
+          if let Some(row) = *row {
+            if let Some(col) = angle[k] {
+              jacobian.push((row, col, part(d_angle)));
+            }
+            if let Some(col) = magnitude[k] {
+              jacobian.push((row, col, part(d_magnitude)));
+            }
+          }
+        }
+      };
+      for (i, k, y) in ybus.iter() {
+        add(i, k, -Complex64::i() * v[i] * (y * v[k]).conj(), v[i] * (y * unit[k]).conj());
+      }
+      for i in 0..nb {
+        add(i, i, Complex64::i() * v[i] * current[i].conj(), current[i].conj() * unit[i]);
+      }
+      let n = f.len();
+      let lu = SparseLu::new(&SparseMatrix::from_triplets(n, n, jacobian))
+        .map_err(|_| anyhow!("The power flow Jacobian is singular after {} iterations", iteration))?;
+      let dx = lu.solve(&f);
+      for i in 0..nb {
+        let (mut va, mut vm) = (v[i].arg(), v[i].norm());
+        if let Some(k) = angle[i] {
+          va -= dx[k];
+        }
+        if let Some(k) = magnitude[i] {
+          vm -= dx[k];
+        }
+        v[i] = Complex64::from_polar(vm, va);
+      }
+      self.iterations += 1;
+      f = mismatch(v);
+    }
+    Ok(())
+  }
+
+  // The fast-decoupled method from `v`, as MATPOWER's `fdpf`. Each iteration solves `B' dVa = P / Vm` for the angles
+  // of PV and PQ buses and then `B'' dVm = Q / Vm` for the magnitudes of PQ buses, with `B'` and `B''` factored once.
+  fn fast_decoupled(
+    &mut self,
+    v: &mut [Complex64],
+    pv: &[usize],
+    pq: &[usize],
+    options: &PowerFlowOptions,
+    scheme: Scheme,
+  ) -> Result<()> {
+    let pvpq = pv.iter().chain(pq.iter()).copied().collect::<Vec<_>>();
+    let n = pvpq.len();
+    let (bp, bpp) = self.network.decoupled_susceptance(scheme)?;
+    let bp =
+      SparseLu::new(&submatrix(&bp, &pvpq)).map_err(|_| anyhow!("B' of the fast-decoupled power flow is singular"))?;
+    let bpp =
+      SparseLu::new(&submatrix(&bpp, pq)).map_err(|_| anyhow!("B'' of the fast-decoupled power flow is singular"))?;
+    let s = self.scheduled();
+    let ybus = &self.y.ybus;
+    // Real power mismatches of PV and PQ buses, then reactive power mismatches of PQ buses, over voltage magnitudes.
+    let mismatch = |v: &[Complex64]| {
+      let current = ybus.mul_vec(v);
+      let mis = (0..v.len()).map(|i| (v[i] * current[i].conj() - s[i]) / v[i].norm()).collect::<Vec<_>>();
+      pvpq.iter().map(|&i| mis[i].re).chain(pq.iter().map(|&i| mis[i].im)).collect::<Vec<_>>()
+    };
+    let mut f = mismatch(v);
+    for iteration in 0.. {
+      if converged(&mut self.mismatch, &f, iteration, options)? {
+        break;
+      }
+      for (&i, d) in pvpq.iter().zip(bp.solve(&f[..n])) {
+        v[i] = Complex64::from_polar(v[i].norm(), v[i].arg() - d);
+      }
+      f = mismatch(v);
+      // Converged after the real power half iteration.
+      if f.iter().all(|x| x.abs() < options.tolerance) {
+        self.iterations += 1;
+        continue;
+      }
+      for (&i, d) in pq.iter().zip(bpp.solve(&f[n..])) {
+        v[i] = Complex64::from_polar(v[i].norm() - d, v[i].arg());
+      }
+      self.iterations += 1;
+      f = mismatch(v);
+    }
+    Ok(())
+  }
+
+  // The case with the voltages `v`, and the generator outputs and branch flows they give, as MATPOWER's `pfsoln`.
+  fn solution(&self, v: &[Complex64], pv: &[usize]) -> Case {
+    let base = self.case.base_mva;
+    let mut solved = self.case.clone();
+    for &i in self.reference.iter().chain(self.others.iter()) {
+      solved.bus[i].voltage_mag = v[i].norm();
+      solved.bus[i].voltage_ang = v[i].arg().to_degrees();
+    }
+    for (g, q) in self.fixed.iter().enumerate() {
+      if let Some(q) = q {
+        solved.gen[g].qg = *q;
+      }
+    }
+    let current = self.y.ybus.mul_vec(v);
+    // PV buses have a regulating generator by `bus_types`, and reference buses by `new`.
+    for &i in pv.iter().chain(self.reference.iter()) {
+      let rows = self.gens_at[i].iter().copied().filter(|&g| self.regulating(g)).collect::<Vec<_>>();
+      let b = &self.network.bus[i];
+      let generation = v[i] * current[i].conj() * base + Complex64::new(b.pd, b.qd);
+      let fixed = self.gens_at[i].iter().filter_map(|&g| self.fixed[g]).sum::<f64>();
+      share_q(&mut solved.gen, &rows, generation.im - fixed);
+      if b.bus_type == BusType::Ref {
+        let others = self.gens_at[i]
+          .iter()
+          .filter(|&&g| g != rows[0] && solved.gen[g].in_service())
+          .map(|&g| solved.gen[g].pg)
+          .sum::<f64>();
+        solved.gen[rows[0]].pg = generation.re - others;
+      }
+    }
+    let (from, to) = (self.y.yf.mul_vec(v), self.y.yt.mul_vec(v));
+    for (l, br) in solved.branch.iter_mut().enumerate() {
+      let (f, t) = self.branch_bus[l];
+      let (sf, st) = if self.network.branch[l].in_service() {
+        (v[f] * from[l].conj() * base, v[t] * to[l].conj() * base)
+      } else {
+        Default::default()
+      };
+      br.pf = Some(sf.re);
+      br.qf = Some(sf.im);
+      br.pt = Some(st.re);
+      br.qt = Some(st.im);
+    }
+    solved
+  }
+}
+
+// Shares the reactive power `q` of a bus among its generators `rows`, in proportion to their reactive power ranges,
+// or equally if the ranges are all zero or not finite.
+fn share_q(gen: &mut [Gen], rows: &[usize], q: f64) {
+  let (qmin, range) =
+    rows.iter().fold((0.0, 0.0), |(qmin, range), &g| (qmin + gen[g].qmin, range + gen[g].qmax - gen[g].qmin));
+  for &g in rows {
+    gen[g].qg = if rows.len() > 1 && range > 0.0 && range.is_finite() {
+      gen[g].qmin + (q - qmin) * (gen[g].qmax - gen[g].qmin) / range
+    } else {
+      q / rows.len() as f64
+    };
+  }
+}
+
+// Adds the largest of the mismatches `f` after `iteration` iterations to `history`, and tells if they are within the
+// tolerance. Fails if they are not finite or the iterations are used up.
+fn converged(history: &mut Vec<f64>, f: &[f64], iteration: usize, options: &PowerFlowOptions) -> Result<bool> {
+  let largest = f.iter().fold(0f64, |a, x| a.max(x.abs()));
+  history.push(largest);
+  if f.iter().any(|x| !x.is_finite()) {
+    return Err(anyhow!("The power flow diverged after {} iterations", iteration));
+  }
+  if largest < options.tolerance {
+    return Ok(true);
+  }
+  if iteration == options.max_iterations() {
+    return Err(anyhow!("The power flow did not converge in {} iterations", iteration));
+  }
+  Ok(false)
+}
+
+// The rows and columns `keep` of `m`, in that order.
+fn submatrix(m: &SparseMatrix<f64>, keep: &[usize]) -> SparseMatrix<f64> {
+  let mut position = vec![None; m.rows()];
+  for (k, &i) in keep.iter().enumerate() {
+    position[i] = Some(k);
+  }
+  let entries = m.iter().filter_map(|(i, j, v)| Some((position[i]?, position[j]?, v)));
+  SparseMatrix::from_triplets(keep.len(), keep.len(), entries)
+}
+
+// The case with branches to isolated buses taken out of service.
+fn connected(case: &Case) -> Case {
+  let isolated = case.bus.iter().filter(|b| b.bus_type == BusType::Isolated).map(|b| b.idx).collect::<HashSet<_>>();
+  let mut c = case.clone();
+  for br in c.branch.iter_mut().filter(|br| isolated.contains(&br.f_bus) || isolated.contains(&br.t_bus)) {
+    br.br_status = 0.0;
+  }
+  c
+}
+
+// Rows of the reference buses, and of the other buses in islands, in order. Fails if an island has no reference bus.
+fn slack(case: &Case) -> Result<(Vec<usize>, Vec<usize>)> {
+  let (mut reference, mut other) = (vec![], vec![]);
+  for island in case.islands() {
+    if !island.iter().any(|&i| case.bus[i].bus_type == BusType::Ref) {
+      return Err(anyhow!("The island of bus {} has no reference bus", case.bus[island[0]].idx));
+    }
+    for i in island {
+      if case.bus[i].bus_type == BusType::Ref {
+        reference.push(i);
+      } else {
+        other.push(i);
+      }
+    }
+  }
+  reference.sort_unstable();
+  other.sort_unstable();
+  Ok((reference, other))
+}
+
+#[test]
+fn test_dc_power_flow() {
+  use crate::{
+    builder::CaseBuilder,
+    case::{Branch, Bus, BusId, Gen},
+  };
+
+  let bus = |n: usize, bus_type: BusType, pd: f64| Bus { idx: BusId(n), bus_type, pd, ..Bus::default() };
+  let branch = |f: usize, t: usize, br_x: f64| Branch { f_bus: BusId(f), t_bus: BusId(t), br_x, ..Branch::default() };
+  let c = CaseBuilder::new("case")
+    .bus(bus(1, BusType::Ref, 0.0))
+    .bus(Bus { voltage_mag: 0.95, ..bus(2, BusType::PQ, 100.0) })
+    .bus(bus(3, BusType::PQ, 50.0))
+    .bus(bus(4, BusType::Ref, 0.0))
+    .bus(bus(5, BusType::PQ, 10.0))
+    .bus(bus(6, BusType::Isolated, 0.0))
+    .gen(Gen { gen: BusId(1), ..Gen::default() })
+    .gen(Gen { gen: BusId(4), pg: 5.0, ..Gen::default() })
+    .branch(branch(1, 2, 0.1))
+    .branch(branch(1, 3, 0.1))
+    .branch(branch(2, 3, 0.1))
+    .branch(Branch { shift: 10.0, ..branch(4, 5, 0.2) })
+    .branch(branch(3, 6, 0.1))
+    .build()
+    .unwrap();
+  let r = dc_power_flow(&c);
+  assert!(r.success, "{:?}", r.message);
+  let close = |a: f64, b: f64| (a - b).abs() < 1e-9;
+  let angles = r.case.bus.iter().map(|b| b.voltage_ang).collect::<Vec<_>>();
+  assert!(close(angles[1], -(1.0f64 / 12.0).to_degrees()) && close(angles[2], -(1.0f64 / 15.0).to_degrees()));
+  assert!(close(angles[4], -0.02f64.to_degrees() - 10.0));
+  let flows = r.case.branch.iter().map(|br| br.pf.unwrap()).collect::<Vec<_>>();
+  assert!(close(flows[0], 250.0 / 3.0) && close(flows[1], 200.0 / 3.0) && close(flows[2], -50.0 / 3.0));
+  assert!(close(flows[3], 10.0) && flows[4] == 0.0);
+  assert_eq!(r.case.branch[0].pt, Some(-flows[0]));
+  assert!(close(r.case.gen[0].pg, 150.0) && close(r.case.gen[1].pg, 10.0));
+  assert!(r.case.bus[..5].iter().all(|b| b.voltage_mag == 1.0));
+
+  let mut c = c;
+  c.gen[1].gen_status = 0;
+  let r = dc_power_flow(&c);
+  assert_eq!((r.success, r.message.as_deref()), (false, Some("Reference bus 4 has no in-service generator")));
+  c.bus[3].bus_type = BusType::PV;
+  let r = dc_power_flow(&c);
+  assert_eq!((r.success, r.message.as_deref()), (false, Some("The island of bus 4 has no reference bus")));
+}
+
+#[cfg(test)]
+const CASE9: &str = r#"function mpc = case9
+mpc.version = '2';
+mpc.baseMVA = 100;
+mpc.bus = [
+	1	3	0	0	0	0	1	1	0	345	1	1.1	0.9;
+	2	2	0	0	0	0	1	1	0	345	1	1.1	0.9;
+	3	2	0	0	0	0	1	1	0	345	1	1.1	0.9;
+	4	1	0	0	0	0	1	1	0	345	1	1.1	0.9;
+	5	1	90	30	0	0	1	1	0	345	1	1.1	0.9;
+	6	1	0	0	0	0	1	1	0	345	1	1.1	0.9;
+	7	1	100	35	0	0	1	1	0	345	1	1.1	0.9;
+	8	1	0	0	0	0	1	1	0	345	1	1.1	0.9;
+	9	1	125	50	0	0	1	1	0	345	1	1.1	0.9;
+];
+mpc.gen = [
+	1	72.3	27.03	300	-300	1.04	100	1	250	10	0	0	0	0	0	0	0	0	0	0	0;
+	2	163	6.54	300	-300	1.025	100	1	300	10	0	0	0	0	0	0	0	0	0	0	0;
+	3	85	-10.95	300	-300	1.025	100	1	270	10	0	0	0	0	0	0	0	0	0	0	0;
+];
+mpc.branch = [
+	1	4	0	0.0576	0	250	250	250	0	0	1	-360	360;
+	4	5	0.017	0.092	0.158	250	250	250	0	0	1	-360	360;
+	5	6	0.039	0.17	0.358	150	150	150	0	0	1	-360	360;
+	3	6	0	0.0586	0	300	300	300	0	0	1	-360	360;
+	6	7	0.0119	0.1008	0.209	150	150	150	0	0	1	-360	360;
+	7	8	0.0085	0.072	0.149	250	250	250	0	0	1	-360	360;
+	8	2	0	0.0625	0	250	250	250	0	0	1	-360	360;
+	8	9	0.032	0.161	0.306	250	250	250	0	0	1	-360	360;
+	9	4	0.01	0.085	0.176	250	250	250	0	0	1	-360	360;
+];
+"#;
+
+#[test]
+fn test_ac_power_flow() {
+  let c = crate::case::case(CASE9).unwrap();
+  let r = ac_power_flow(&c, &PowerFlowOptions::default());
+  assert!(r.success, "{:?}", r.message);
+  assert_eq!((r.iterations, r.mismatch.len()), (4, 5));
+  assert!(r.mismatch[4] < 1e-8);
+  let close = |a: f64, b: f64, tol: f64| (a - b).abs() < tol;
+  let s = &r.case;
+  assert!(close(s.gen[0].pg, 71.64, 0.005) && close(s.gen[0].qg, 27.05, 0.005));
+  assert!(close(s.gen[1].qg, 6.65, 0.005) && close(s.gen[2].qg, -10.86, 0.005));
+  assert!(close(s.bus[1].voltage_mag, 1.025, 1e-12) && close(s.bus[1].voltage_ang, 9.280, 0.0005));
+  assert!(close(s.bus[4].voltage_mag, 1.0127, 0.00005) && close(s.bus[4].voltage_ang, -3.687, 0.0005));
+  // Power balance at bus 5, from the branch flows.
+  let (p, q) = (s.branch[1].pt.unwrap() + s.branch[2].pf.unwrap(), s.branch[1].qt.unwrap() + s.branch[2].qf.unwrap());
+  assert!(close(p, -90.0, 1e-6) && close(q, -30.0, 1e-6));
+
+  let mut limited = c.clone();
+  limited.gen[2].qmin = -5.0;
+  let options = PowerFlowOptions { enforce_q_limits: true, ..PowerFlowOptions::default() };
+  let r = ac_power_flow(&limited, &options);
+  assert!(r.success, "{:?}", r.message);
+  assert_eq!(r.case.gen[2].qg, -5.0);
+  assert!(r.case.bus[2].voltage_mag > 1.025 && r.case.gen[1].qg < 300.0);
+  assert_eq!(r.case.bus[2].bus_type, BusType::PV);
+
+  let mut no_slack = c.clone();
+  no_slack.gen[0].gen_status = 0;
+  let r = ac_power_flow(&no_slack, &PowerFlowOptions::default());
+  assert_eq!((r.success, r.message.as_deref()), (false, Some("Reference bus 1 has no in-service generator")));
+
+  let mut heavy = c;
+  for b in heavy.bus.iter_mut() {
+    b.pd *= 10.0;
+  }
+  let r = ac_power_flow(&heavy, &PowerFlowOptions::default());
+  assert!(!r.success && r.mismatch.len() == r.iterations + 1, "{:?}", r);
+  assert_eq!(r.case, heavy);
+}
+
+#[test]
+fn test_fast_decoupled_power_flow() {
+  let c = crate::case::case(CASE9).unwrap();
+  let newton = ac_power_flow(&c, &PowerFlowOptions::default()).case;
+  for algorithm in [Algorithm::FastDecoupledXB, Algorithm::FastDecoupledBX].iter() {
+    let r = ac_power_flow(&c, &PowerFlowOptions { algorithm: *algorithm, ..PowerFlowOptions::default() });
+    assert!(r.success, "{:?}", r.message);
+    assert!(r.iterations > 4 && r.mismatch.len() == r.iterations + 1);
+    for (a, b) in r.case.bus.iter().zip(newton.bus.iter()) {
+      assert!((a.voltage_mag - b.voltage_mag).abs() < 1e-8 && (a.voltage_ang - b.voltage_ang).abs() < 1e-6);
+    }
+    assert!((r.case.gen[0].pg - newton.gen[0].pg).abs() < 1e-5);
+  }
+
+  let options: PowerFlowOptions = serde_json::from_str(r#"{"algorithm": "FDBX", "max_iterations": 2}"#).unwrap();
+  assert_eq!((options.algorithm, options.tolerance), (Algorithm::FastDecoupledBX, 1e-8));
+  let r = ac_power_flow(&c, &options);
+  assert_eq!((r.success, r.iterations), (false, 2));
+  assert_eq!(r.message.as_deref(), Some("The power flow did not converge in 2 iterations"));
+}
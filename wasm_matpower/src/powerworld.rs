@@ -0,0 +1,462 @@
+// PowerWorld auxiliary (`.aux`) file reader
+
+// The module is not called `aux`, which is a reserved file name on Windows.
+
+// Aux files hold `DATA (ObjectType, [Field1, Field2, ...]) { ... }` blocks (or the older `ObjectType (Field1, ...)
+// { ... }` form), with one value per field for each record. Bus, Gen, Load, Shunt and Branch records are assembled
+// into a case; Area and other object types, and fields without a MATPOWER column, are kept in the case's extra data.
+// Impedances are per unit on PowerWorld's fixed 100 MVA system base.
+
+use std::collections::{BTreeMap, HashMap};
+
+use anyhow::{anyhow, Result};
+
+use crate::case::{Branch, Bus, BusId, BusType, Case, Gen, Version};
+
+const BASE_MVA: f64 = 100.0;
+
+// Tokens with their line numbers. Quoted strings keep their quotes so they are never taken for punctuation.
+fn tokens(s: &str) -> Result<Vec<(&str, usize)>> {
+  let mut v = vec![];
+  let mut subdata = false;
+  for (n, line) in s.lines().enumerate() {
+    let trimmed = line.trim();
+    // SUBDATA sections (bid curves, contingency elements, ...) are not needed for the case.
+    if subdata || trimmed.to_ascii_uppercase().starts_with("<SUBDATA") {
+      subdata = !trimmed.to_ascii_uppercase().starts_with("</SUBDATA");
+      continue;
+    }
+    let mut rest = line.trim_start();
+    while !rest.is_empty() && !rest.starts_with("//") {
+      let end = match rest.chars().next() {
+        Some('"') => rest[1..].find('"').map(|i| i + 2).ok_or_else(|| anyhow!("Unterminated string on line {}", n + 1))?,
+        Some('(' | ')' | '[' | ']' | '{' | '}' | ',') => 1,
+        _ => rest.find(|c: char| c.is_whitespace() || "()[]{},\"".contains(c)).unwrap_or(rest.len()),
+      };
+      v.push((&rest[..end], n + 1));
+      rest = rest[end..].trim_start();
+    }
+  }
+  Ok(v)
+}
+
+#[test]
+fn test_tokens() {
+  let t = tokens("DATA (Bus, [BusNum,BusName]) // buses\n{\n1 \"One (N)\"\n<SUBDATA X>\n1 2\n</SUBDATA>\n}").unwrap();
+  let t = t.iter().map(|(t, _)| *t).collect::<Vec<_>>();
+  assert_eq!(t, vec!["DATA", "(", "Bus", ",", "[", "BusNum", ",", "BusName", "]", ")", "{", "1", "\"One (N)\"", "}"]);
+  assert!(tokens("1 \"One").is_err());
+}
+
+fn unquote(s: &str) -> &str {
+  s.strip_prefix('"').and_then(|s| s.strip_suffix('"')).unwrap_or(s)
+}
+
+// One record of a DATA block
+struct Record<'a> {
+  object: &'a str,
+  fields: &'a [String],
+  values: Vec<&'a str>,
+  line: usize,
+}
+
+impl<'a> Record<'a> {
+  fn get(&self, field: &str) -> Option<&'a str> {
+    let i = self.fields.iter().position(|f| f.eq_ignore_ascii_case(field))?;
+    Some(unquote(self.values[i]).trim()).filter(|v| !v.is_empty())
+  }
+
+  fn f64(&self, field: &str) -> Result<Option<f64>> {
+    self
+      .get(field)
+      .map(|v| v.parse().map_err(|_| anyhow!("Invalid {} {:?} in {} record on line {}", field, v, self.object, self.line)))
+      .transpose()
+  }
+
+  fn f64_or(&self, field: &str, default: f64) -> Result<f64> {
+    Ok(self.f64(field)?.unwrap_or(default))
+  }
+
+  fn bus(&self, field: &str) -> Result<usize> {
+    let v = self.get(field).ok_or_else(|| anyhow!("Missing {} in {} record on line {}", field, self.object, self.line))?;
+    v.parse().map_err(|_| anyhow!("Invalid {} {:?} in {} record on line {}", field, v, self.object, self.line))
+  }
+
+  // Closed, Connected, YES and nonzero numbers are in service. Missing status means in service.
+  fn in_service(&self, field: &str) -> bool {
+    match self.get(field).map(|v| v.to_ascii_lowercase()) {
+      None => true,
+      Some(v) => match v.as_str() {
+        "closed" | "connected" | "yes" => true,
+        "open" | "disconnected" | "no" => false,
+        v => v.parse::<f64>().ok().is_none_or(|v| v != 0.0),
+      },
+    }
+  }
+
+  // Key fields plus those not in `known`, for the case's extra data
+  fn extra(&self, keys: &[&str], known: &[&str]) -> Option<BTreeMap<String, String>> {
+    let is = |f: &str, list: &[&str]| list.iter().any(|k| k.eq_ignore_ascii_case(f));
+    if self.fields.iter().all(|f| is(f, known) || is(f, keys)) {
+      return None;
+    }
+    let kept = self.fields.iter().zip(self.values.iter()).filter(|(f, _)| !is(f, known) || is(f, keys));
+    Some(kept.map(|(f, v)| (f.clone(), unquote(v).to_string())).collect())
+  }
+}
+
+struct Block {
+  object: String,
+  fields: Vec<String>,
+  values: Vec<(String, usize)>,
+}
+
+impl Block {
+  fn records(&self) -> impl Iterator<Item = Record<'_>> {
+    self.values.chunks(self.fields.len()).map(move |v| Record {
+      object: &self.object,
+      fields: &self.fields,
+      values: v.iter().map(|(v, _)| v.as_str()).collect(),
+      line: v[0].1,
+    })
+  }
+}
+
+fn blocks(s: &str) -> Result<Vec<Block>> {
+  let t = tokens(s)?;
+  let mut blocks = vec![];
+  let mut i = 0;
+  let expect = |i: usize, s: &str| -> Result<usize> {
+    match t.get(i) {
+      Some((tok, _)) if *tok == s => Ok(i + 1),
+      Some((tok, n)) => Err(anyhow!("Expected `{}` but found {:?} on line {}", s, tok, n)),
+      None => Err(anyhow!("Expected `{}` at end of file", s)),
+    }
+  };
+  // Tokens up to the closing `close`, skipping commas
+  let list = |mut i: usize, close: &str| -> Result<(Vec<(String, usize)>, usize)> {
+    let mut v = vec![];
+    loop {
+      match t.get(i) {
+        Some((tok, _)) if *tok == close => return Ok((v, i + 1)),
+        Some((",", _)) => (),
+        Some((tok, n)) => v.push((tok.to_string(), *n)),
+        None => return Err(anyhow!("Missing `{}` at end of file", close)),
+      }
+      i += 1;
+    }
+  };
+  while let Some((tok, n)) = t.get(i) {
+    if tok.eq_ignore_ascii_case("SCRIPT") {
+      // Script commands are not data; skip to the end of the block.
+      let open = (i..t.len()).find(|j| t[*j].0 == "{").ok_or_else(|| anyhow!("Missing `{{` for SCRIPT on line {}", n))?;
+      i = list(open + 1, "}")?.1;
+      continue;
+    }
+    let (object, fields, after) = if tok.eq_ignore_ascii_case("DATA") {
+      let j = expect(i + 1, "(")?;
+      let object = t.get(j).ok_or_else(|| anyhow!("Missing object type on line {}", n))?.0;
+      let j = expect(expect(j + 1, ",")?, "[")?;
+      let (fields, j) = list(j, "]")?;
+      // Anything else in the header (e.g. a filter name) is not needed.
+      let (_, j) = list(j, ")")?;
+      (object, fields, j)
+    } else {
+      let (fields, j) = list(expect(i + 1, "(")?, ")")?;
+      (*tok, fields, j)
+    };
+    let (values, j) = list(expect(after, "{")?, "}")?;
+    let fields = fields.into_iter().map(|(f, _)| f).collect::<Vec<_>>();
+    if fields.is_empty() {
+      return Err(anyhow!("No fields for {} on line {}", object, n));
+    }
+    if values.len() % fields.len() != 0 {
+      return Err(anyhow!(
+        "{} values in the {} block on line {} do not fill records of {} fields",
+        values.len(),
+        object,
+        n,
+        fields.len()
+      ));
+    }
+    blocks.push(Block { object: object.to_string(), fields, values });
+    i = j;
+  }
+  Ok(blocks)
+}
+
+const BUS: &[&str] = &[
+  "BusNum",
+  "BusName",
+  "BusNomVolt",
+  "BusPUVolt",
+  "BusAngle",
+  "AreaNum",
+  "ZoneNum",
+  "BusSlack",
+  "BusStatus",
+  "BusVoltLimHigh",
+  "BusVoltLimLow",
+];
+const GEN: &[&str] = &[
+  "BusNum",
+  "GenID",
+  "GenStatus",
+  "GenMW",
+  "GenMVR",
+  "GenMWMax",
+  "GenMWMin",
+  "GenMVRMax",
+  "GenMVRMin",
+  "GenVoltSet",
+  "GenMVABase",
+  "GenAVRAble",
+];
+const LOAD: &[&str] = &["BusNum", "LoadID", "LoadStatus", "LoadSMW", "LoadSMVR", "LoadIMW", "LoadIMVR", "LoadZMW", "LoadZMVR"];
+const SHUNT: &[&str] = &["BusNum", "ShuntID", "SSStatus", "SSNMW", "SSNMVR"];
+const BRANCH: &[&str] = &[
+  "BusNum",
+  "BusNum:1",
+  "LineCircuit",
+  "LineStatus",
+  "LineR",
+  "LineX",
+  "LineC",
+  "LineAMVA",
+  "LineBMVA",
+  "LineCMVA",
+  "LineTap",
+  "LinePhase",
+  "BranchDeviceType",
+];
+
+/// Build a case from the contents of a PowerWorld `.aux` file.
+pub fn case(s: &str, name: &str) -> Result<Case> {
+  let blocks = blocks(s)?;
+  let mut c = Case {
+    name: name.to_string(),
+    version: Version::Version2,
+    base_mva: BASE_MVA,
+    bus: vec![],
+    gen: vec![],
+    gencost: vec![],
+    branch: vec![],
+    dcline: vec![],
+    bus_name: vec![],
+    extra: Default::default(),
+  };
+  let records = |object: &'static str| blocks.iter().filter(move |b| b.object.eq_ignore_ascii_case(object)).flat_map(Block::records);
+  let mut extra_data = BTreeMap::<String, Vec<_>>::new();
+  let mut extra = |object: &str, e: Option<BTreeMap<String, String>>| {
+    if let Some(e) = e {
+      extra_data.entry(object.to_string()).or_default().push(e);
+    }
+  };
+
+  let mut buses = HashMap::new();
+  let mut bus = vec![];
+  let mut names = vec![];
+  for r in records("Bus") {
+    // A repeated bus number is kept for validation to report; records at it go to its first row.
+    let idx = r.bus("BusNum")?;
+    buses.entry(idx).or_insert(bus.len());
+    let bus_type = if !r.in_service("BusStatus") {
+      BusType::Isolated
+    } else if r.get("BusSlack").is_some_and(|v| v.eq_ignore_ascii_case("YES")) {
+      BusType::Ref
+    } else {
+      BusType::PQ
+    };
+    bus.push(Bus {
+      idx: BusId(idx),
+      bus_type,
+      area: r.f64_or("AreaNum", 1.0)? as usize,
+      zone: r.f64_or("ZoneNum", 1.0)? as usize,
+      voltage_mag: r.f64_or("BusPUVolt", 1.0)?,
+      voltage_ang: r.f64_or("BusAngle", 0.0)?,
+      base_kv: r.f64_or("BusNomVolt", 0.0)?,
+      v_max: r.f64_or("BusVoltLimHigh", 1.1)?,
+      v_min: r.f64_or("BusVoltLimLow", 0.9)?,
+      ..Bus::default()
+    });
+    names.push(r.get("BusName").unwrap_or("").to_string());
+    extra("Bus", r.extra(&["BusNum"], BUS));
+  }
+  if bus.is_empty() {
+    return Err(anyhow!("No Bus records found"));
+  }
+  let index = |r: &Record, field: &str| -> Result<usize> {
+    let b = r.bus(field)?;
+    buses.get(&b).copied().ok_or_else(|| anyhow!("Unknown bus {} in {} record on line {}", b, r.object, r.line))
+  };
+
+  // Constant current and impedance parts are taken at 1 p.u.
+  for r in records("Load") {
+    let b = index(&r, "BusNum")?;
+    if r.in_service("LoadStatus") {
+      bus[b].pd += r.f64_or("LoadSMW", 0.0)? + r.f64_or("LoadIMW", 0.0)? + r.f64_or("LoadZMW", 0.0)?;
+      bus[b].qd += r.f64_or("LoadSMVR", 0.0)? + r.f64_or("LoadIMVR", 0.0)? + r.f64_or("LoadZMVR", 0.0)?;
+    }
+    extra("Load", r.extra(&["BusNum", "LoadID"], LOAD));
+  }
+
+  for r in records("Shunt") {
+    let b = index(&r, "BusNum")?;
+    if r.in_service("SSStatus") {
+      bus[b].shunt_conductance += r.f64_or("SSNMW", 0.0)?;
+      bus[b].shunt_susceptance += r.f64_or("SSNMVR", 0.0)?;
+    }
+    extra("Shunt", r.extra(&["BusNum", "ShuntID"], SHUNT));
+  }
+
+  // Generators and branches may refer to buses that are not in the file, for validation to report.
+  for r in records("Gen") {
+    let b = r.bus("BusNum")?;
+    let gen_status = r.in_service("GenStatus");
+    if let Some(&i) = buses.get(&b) {
+      if gen_status && bus[i].bus_type == BusType::PQ && r.in_service("GenAVRAble") {
+        bus[i].bus_type = BusType::PV;
+      }
+    }
+    c.gen.push(Gen {
+      gen: BusId(b),
+      pg: r.f64_or("GenMW", 0.0)?,
+      qg: r.f64_or("GenMVR", 0.0)?,
+      qmax: r.f64_or("GenMVRMax", 0.0)?,
+      qmin: r.f64_or("GenMVRMin", 0.0)?,
+      vg: r.f64_or("GenVoltSet", 1.0)?,
+      mbase: r.f64_or("GenMVABase", BASE_MVA)?,
+      gen_status: gen_status as usize,
+      pmax: r.f64_or("GenMWMax", 0.0)?,
+      pmin: r.f64_or("GenMWMin", 0.0)?,
+      ..Gen::default()
+    });
+    extra("Gen", r.extra(&["BusNum", "GenID"], GEN));
+  }
+
+  for r in records("Branch") {
+    let (tap, shift) = (r.f64_or("LineTap", 1.0)?, r.f64_or("LinePhase", 0.0)?);
+    let transformer = r.get("BranchDeviceType").is_some_and(|v| v.eq_ignore_ascii_case("Transformer"));
+    c.branch.push(Branch {
+      f_bus: BusId(r.bus("BusNum")?),
+      t_bus: BusId(r.bus("BusNum:1")?),
+      br_r: r.f64_or("LineR", 0.0)?,
+      br_x: r.f64_or("LineX", 0.0)?,
+      br_b: r.f64_or("LineC", 0.0)?,
+      rate_a: r.f64_or("LineAMVA", 0.0)?,
+      rate_b: r.f64_or("LineBMVA", 0.0)?,
+      rate_c: r.f64_or("LineCMVA", 0.0)?,
+      tap: if transformer || tap != 1.0 || shift != 0.0 { tap } else { 0.0 },
+      shift,
+      br_status: r.in_service("LineStatus") as usize as f64,
+      ..Branch::default()
+    });
+    extra("Branch", r.extra(&["BusNum", "BusNum:1", "LineCircuit"], BRANCH));
+  }
+
+  // Areas and anything else are kept whole.
+  for b in blocks.iter() {
+    if !["Bus", "Load", "Shunt", "Gen", "Branch"].iter().any(|o| b.object.eq_ignore_ascii_case(o)) {
+      for r in b.records() {
+        extra(&b.object, r.extra(&[], &[]));
+      }
+    }
+  }
+
+  c.bus = bus;
+  c.extra = extra_data;
+  if names.iter().any(|n| !n.is_empty()) {
+    c.bus_name = names;
+  }
+  Ok(c)
+}
+
+/// True if `s` looks like a PowerWorld auxiliary file.
+pub fn is_aux(s: &str) -> bool {
+  s.lines().any(|l| {
+    let l = l.trim_start().to_ascii_uppercase();
+    l.starts_with("DATA (") || l.starts_with("DATA(")
+  })
+}
+
+#[cfg(test)]
+const AUX: &str = r#"// Exported from a made up case
+SCRIPT
+{
+  EnterMode(EDIT);
+}
+DATA (Area, [AreaNum,AreaName])
+{
+  1 "North"
+}
+DATA (Bus, [BusNum,BusName,BusNomVolt,AreaNum,ZoneNum,BusPUVolt,BusAngle,BusSlack,SubNum])
+{
+  1 "One"   230.0 1 1 1.04  0.0 "YES" 10
+  2 "Two"   230.0 1 1 1.01 -2.5 "NO"  11
+  3 "Three" 115.0 1 2 1.02 -4.0 "NO"  11
+}
+DATA (Load, [BusNum,LoadID,LoadStatus,LoadSMW,LoadSMVR,LoadIMW])
+{
+  2 "1" "Closed" 100.0 30.0 10.0
+  2 "2" "Open"   100.0 30.0  0.0
+}
+DATA (Shunt, [BusNum,ShuntID,SSStatus,SSNMVR])
+{
+  3 "1" "Closed" 50.0
+}
+DATA (Gen, [GenMW,BusNum,GenID,GenStatus,GenMWMax,GenMWMin,GenMVRMax,GenMVRMin,GenVoltSet,GenRegNum,GenFuelType])
+{
+  150.0 1 "1" "Closed" 300.0 10.0 100.0 -100.0 1.04 1 "Coal"
+   50.0 3 "1" "Closed" 100.0  0.0  50.0  -50.0 1.02 2 "Gas"
+<SUBDATA BidCurve>
+  0 10
+</SUBDATA>
+}
+DATA (Branch, [BusNum,BusNum:1,LineCircuit,BranchDeviceType,LineStatus,LineR,LineX,LineC,LineAMVA,
+  LineTap,LinePhase])
+{
+  1 2 "1" "Line"        "Closed" 0.001 0.01 0.02 100.0 1.0    0.0
+  1 2 "2" "Line"        "Open"   0.001 0.01 0.02 100.0 1.0    0.0
+  2 3 "1" "Transformer" "Closed" 0.0   0.04 0.0  150.0 1.05 -30.0
+}
+"#;
+
+#[test]
+fn test_case() {
+  let c = case(AUX, "test").unwrap();
+  assert_eq!(c.bus_name, vec!["One", "Two", "Three"]);
+  assert_eq!(c.bus.iter().map(|b| b.bus_type).collect::<Vec<_>>(), vec![BusType::Ref, BusType::PQ, BusType::PV]);
+  assert_eq!((c.bus[1].voltage_mag, c.bus[1].voltage_ang, c.bus[2].zone), (1.01, -2.5, 2));
+  assert_eq!((c.bus[1].pd, c.bus[1].qd), (110.0, 30.0));
+  assert_eq!(c.bus[2].shunt_susceptance, 50.0);
+
+  assert_eq!(c.gen.len(), 2);
+  assert_eq!((c.gen[0].gen.0, c.gen[0].pg, c.gen[0].pmax, c.gen[0].vg), (1, 150.0, 300.0, 1.04));
+  assert_eq!((c.gen[1].gen.0, c.gen[1].qmin, c.gen[1].mbase), (3, -50.0, 100.0));
+
+  assert_eq!(c.branch.len(), 3);
+  assert_eq!((c.branch[0].tap, c.branch[0].br_b, c.branch[0].br_status), (0.0, 0.02, 1.0));
+  assert_eq!(c.branch[1].br_status, 0.0);
+  assert_eq!((c.branch[2].f_bus.0, c.branch[2].t_bus.0, c.branch[2].tap, c.branch[2].shift), (2, 3, 1.05, -30.0));
+
+  let entry = |pairs: &[(&str, &str)]| pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect::<BTreeMap<_, _>>();
+  assert_eq!(c.extra["Area"], vec![entry(&[("AreaNum", "1"), ("AreaName", "North")])]);
+  assert_eq!(c.extra["Bus"][2], entry(&[("BusNum", "3"), ("SubNum", "11")]));
+  let gen = entry(&[("BusNum", "1"), ("GenID", "1"), ("GenRegNum", "1"), ("GenFuelType", "Coal")]);
+  assert_eq!(c.extra["Gen"][0], gen);
+  // MATPOWER has no remotely regulated bus.
+  assert_eq!(c.extra["Gen"][1]["GenRegNum"], "2");
+  assert!(!c.extra.contains_key("Branch") && !c.extra.contains_key("Load"));
+}
+
+#[test]
+fn test_case_errors() {
+  assert_eq!(case("DATA (Area, [AreaNum])\n{\n1\n}\n", "t").unwrap_err().to_string(), "No Bus records found");
+  let e = case("DATA (Bus, [BusNum, BusName])\n{\n1 \"One\"\n2\n}\n", "t").unwrap_err();
+  assert_eq!(e.to_string(), "3 values in the Bus block on line 1 do not fill records of 2 fields");
+  let e = case("DATA (Bus, [BusNum])\n{\n1\n}\nDATA (Load, [BusNum, LoadSMW])\n{\n9 10\n}\n", "t").unwrap_err();
+  assert_eq!(e.to_string(), "Unknown bus 9 in Load record on line 7");
+  let e = case("DATA (Bus, [BusNum, BusPUVolt])\n{\n1 high\n}\n", "t").unwrap_err();
+  assert_eq!(e.to_string(), "Invalid BusPUVolt \"high\" in Bus record on line 3");
+  assert!(case("DATA (Bus, [BusNum])\n{\n1\n", "t").unwrap_err().to_string().starts_with("Missing `}` at end of file"));
+}
@@ -0,0 +1,255 @@
+// PyPSA CSV folder export
+
+// PyPSA's `import_from_csv_folder` layout: one CSV per component with a `name` column. Lines take ohms and siemens,
+// transformers per unit on their own rating, and powers stay in MW. Out of service elements are left out, as PyPSA
+// has no status for them. Buses are named by bus number.
+
+use std::{
+  collections::HashMap,
+  io::{Cursor, Write},
+};
+
+use anyhow::{anyhow, Result};
+
+use crate::{
+  case::{BusType, Case, CostModel, GenCost, ServiceStatus},
+  units::Base,
+};
+
+fn table(header: &[&str], rows: Vec<Vec<String>>) -> Result<String> {
+  let mut w = csv::Writer::from_writer(vec![]);
+  w.write_record(header)?;
+  for row in rows {
+    w.write_record(row)?;
+  }
+  Ok(String::from_utf8(w.into_inner().map_err(|e| anyhow!("{}", e))?)?)
+}
+
+// Linear and quadratic coefficients of a generator's cost. Piecewise linear costs use the average slope over the
+// curve, and higher order polynomials are left at zero.
+fn marginal_cost(c: &GenCost) -> (f64, f64) {
+  match c.model {
+    CostModel::Polynomial => match c.cost.as_slice() {
+      [c1, _] => (*c1, 0.0),
+      [c2, c1, _] => (*c1, *c2),
+      _ => (0.0, 0.0),
+    },
+    CostModel::PiecewiseLinear => match (c.cost.first().zip(c.cost.get(1)), c.cost.len()) {
+      (Some((p0, f0)), n) if n >= 4 => {
+        let (p, f) = (c.cost[n - 2], c.cost[n - 1]);
+        if p != *p0 {
+          ((f - f0) / (p - p0), 0.0)
+        } else {
+          (0.0, 0.0)
+        }
+      },
+      _ => (0.0, 0.0),
+    },
+  }
+}
+
+#[test]
+fn test_marginal_cost() {
+  use crate::case::CostModel::*;
+  let cost = |model, cost: &[f64]| GenCost { model, startup: 0.0, shutdown: 0.0, ncost: 0, cost: cost.to_vec() };
+  assert_eq!(marginal_cost(&cost(Polynomial, &[0.11, 5.0, 150.0])), (5.0, 0.11));
+  assert_eq!(marginal_cost(&cost(Polynomial, &[20.0, 0.0])), (20.0, 0.0));
+  assert_eq!(marginal_cost(&cost(Polynomial, &[1.0, 0.11, 5.0, 150.0])), (0.0, 0.0));
+  assert_eq!(marginal_cost(&cost(PiecewiseLinear, &[0.0, 0.0, 50.0, 1000.0, 100.0, 3000.0])), (30.0, 0.0));
+}
+
+impl Case {
+  /// PyPSA CSV files by file name: `buses.csv`, `lines.csv`, `transformers.csv`, `generators.csv`, `loads.csv`,
+  /// `shunt_impedances.csv` and `links.csv`.
+  pub fn to_pypsa(&self) -> Result<Vec<(&'static str, String)>> {
+    let units = self.units()?;
+    let control = |t: BusType| match t {
+      BusType::Ref => "Slack",
+      BusType::PV => "PV",
+      _ => "PQ",
+    };
+    let bus_type = self.bus.iter().map(|b| (b.idx, b.bus_type)).collect::<HashMap<_, _>>();
+
+    let buses = self
+      .bus
+      .iter()
+      .map(|b| {
+        let (x, y) = b.coords.map_or((String::new(), String::new()), |(lat, lon)| (lon.to_string(), lat.to_string()));
+        vec![
+          b.idx.to_string(),
+          b.base_kv.to_string(),
+          x,
+          y,
+          "AC".to_string(),
+          control(b.bus_type).to_string(),
+          b.voltage_mag.to_string(),
+          b.v_min.to_string(),
+          b.v_max.to_string(),
+        ]
+      })
+      .collect();
+
+    let mut lines = vec![];
+    let mut transformers = vec![];
+    for (i, br) in self.branch.iter().enumerate().filter(|(_, br)| br.br_status > 0.0) {
+      let (f, t) = (br.f_bus, br.t_bus);
+      let (kv_f, kv_t) = (units.base_kv(f)?, units.base_kv(t)?);
+      if br.tap == 0.0 && br.shift == 0.0 && kv_f == kv_t {
+        let si = units.branch_si(br)?;
+        lines.push(vec![
+          format!("L{}", i + 1),
+          f.to_string(),
+          t.to_string(),
+          si.r.to_string(),
+          si.x.to_string(),
+          (si.b * 1e-6).to_string(),
+          br.rate_a.to_string(),
+        ]);
+      } else {
+        // Per unit on s_nom, which has to be positive here.
+        let s_nom = if br.rate_a > 0.0 { br.rate_a } else { self.base_mva };
+        let pu = units.branch_pu(br, Base::new(s_nom, kv_f))?;
+        transformers.push(vec![
+          format!("T{}", i + 1),
+          f.to_string(),
+          t.to_string(),
+          pu.r.to_string(),
+          pu.x.to_string(),
+          pu.b.to_string(),
+          s_nom.to_string(),
+          br.ratio().to_string(),
+          br.shift.to_string(),
+        ]);
+      }
+    }
+
+    let mut generators = vec![];
+    for (i, g) in self.gen.iter().enumerate().filter(|(_, g)| g.gen_status > 0) {
+      let (c1, c2) = self.gencost.get(i).map_or((0.0, 0.0), marginal_cost);
+      let t = bus_type.get(&g.gen).copied().ok_or_else(|| anyhow!("Unknown bus {}", g.gen))?;
+      let p_min_pu = if g.pmax != 0.0 { g.pmin / g.pmax } else { 0.0 };
+      generators.push(vec![
+        format!("G{}", i + 1),
+        g.gen.to_string(),
+        control(t).to_string(),
+        g.pmax.to_string(),
+        p_min_pu.to_string(),
+        g.pg.to_string(),
+        g.qg.to_string(),
+        c1.to_string(),
+        c2.to_string(),
+      ]);
+    }
+
+    let loads = self
+      .bus
+      .iter()
+      .filter(|b| b.pd != 0.0 || b.qd != 0.0)
+      .map(|b| vec![format!("D{}", b.idx), b.idx.to_string(), b.pd.to_string(), b.qd.to_string()])
+      .collect();
+
+    let mut shunts = vec![];
+    for b in self.bus.iter().filter(|b| b.shunt_conductance != 0.0 || b.shunt_susceptance != 0.0) {
+      let (g, s) = units.shunt_si(b)?;
+      shunts.push(vec![format!("S{}", b.idx), b.idx.to_string(), g.to_string(), s.to_string()]);
+    }
+
+    // pt = pf - (loss0 + loss1 pf), so the constant loss has nowhere to go.
+    let links = self
+      .dcline
+      .iter()
+      .enumerate()
+      .filter(|(_, d)| d.br_status == ServiceStatus::InService)
+      .map(|(i, d)| {
+        let p_nom = d.pmax.abs().max(d.pmin.abs());
+        let p_min_pu = if p_nom > 0.0 { d.pmin / p_nom } else { 0.0 };
+        vec![
+          format!("DC{}", i + 1),
+          d.f_bus.to_string(),
+          d.t_bus.to_string(),
+          p_nom.to_string(),
+          p_min_pu.to_string(),
+          d.pf.to_string(),
+          (1.0 - d.loss1).to_string(),
+        ]
+      })
+      .collect();
+
+    Ok(vec![
+      ("buses.csv", table(&["name", "v_nom", "x", "y", "carrier", "control", "v_mag_pu_set", "v_mag_pu_min", "v_mag_pu_max"], buses)?),
+      ("lines.csv", table(&["name", "bus0", "bus1", "r", "x", "b", "s_nom"], lines)?),
+      (
+        "transformers.csv",
+        table(&["name", "bus0", "bus1", "r", "x", "b", "s_nom", "tap_ratio", "phase_shift"], transformers)?,
+      ),
+      (
+        "generators.csv",
+        table(
+          &["name", "bus", "control", "p_nom", "p_min_pu", "p_set", "q_set", "marginal_cost", "marginal_cost_quadratic"],
+          generators,
+        )?,
+      ),
+      ("loads.csv", table(&["name", "bus", "p_set", "q_set"], loads)?),
+      ("shunt_impedances.csv", table(&["name", "bus", "g", "b"], shunts)?),
+      ("links.csv", table(&["name", "bus0", "bus1", "p_nom", "p_min_pu", "p_set", "efficiency"], links)?),
+    ])
+  }
+
+  /// The PyPSA CSV files zipped into a folder named after the case.
+  pub fn to_pypsa_zip(&self) -> Result<Vec<u8>> {
+    let mut z = zip::ZipWriter::new(Cursor::new(vec![]));
+    for (file, csv) in self.to_pypsa()? {
+      z.start_file(format!("{}/{}", self.name, file), zip::write::FileOptions::default())?;
+      z.write_all(csv.as_bytes())?;
+    }
+    Ok(z.finish()?.into_inner())
+  }
+}
+
+#[test]
+fn test_to_pypsa() {
+  let c = crate::case::case(
+    r#"function mpc = case3
+mpc.version = '2';
+mpc.baseMVA = 100;
+mpc.bus = [
+	1	3	0	0	0	0	1	1.02	0	230	1	1.1	0.9;
+	2	2	90	30	0	19	1	1	0	230	1	1.1	0.9;
+	3	1	100	35	0	0	1	1	0	115	1	1.1	0.9;
+];
+mpc.gen = [
+	1	0	0	300	-300	1.02	100	1	250	10	0	0	0	0	0	0	0	0	0	0	0;
+	2	163	0	300	-300	1	100	0	300	10	0	0	0	0	0	0	0	0	0	0	0;
+];
+mpc.branch = [
+	1	2	0.01	0.1	0.2	250	250	300	0	0	1	-360	360;
+	2	3	0	0.05	0	200	200	200	1.05	0	1	-360	360;
+];
+mpc.gencost = [
+	2	0	0	3	0.11	5	150;
+	2	0	0	3	0.085	1.2	600;
+];
+mpc.dcline = [
+	1	3	1	10	8.9	0	0	1.01	1	1	100	-100	100	-100	100	0.1	0.01;
+];
+"#,
+  )
+  .unwrap();
+  let files = c.to_pypsa().unwrap().into_iter().collect::<HashMap<_, _>>();
+  assert_eq!(files.len(), 7);
+  assert!(files["buses.csv"].contains("\n2,230,,,AC,PV,1,0.9,1.1\n"));
+  assert!(files["lines.csv"].ends_with("\nL1,1,2,5.29,52.900000000000006,0.0003780718336483932,250\n"));
+  assert!(files["transformers.csv"].ends_with("\nT2,2,3,0,0.1,0,200,1.05,0\n"));
+  assert_eq!(files["generators.csv"].lines().collect::<Vec<_>>(), vec![
+    "name,bus,control,p_nom,p_min_pu,p_set,q_set,marginal_cost,marginal_cost_quadratic",
+    "G1,1,Slack,250,0.04,0,0,5,0.11",
+  ]);
+  assert!(files["loads.csv"].contains("\nD2,2,90,30\n"));
+  assert!(files["shunt_impedances.csv"].ends_with("\nS2,2,0,0.00035916824196597356\n"));
+  assert!(files["links.csv"].ends_with("\nDC1,1,3,100,0.01,10,0.99\n"));
+
+  let z = c.to_pypsa_zip().unwrap();
+  let mut archive = zip::ZipArchive::new(Cursor::new(z)).unwrap();
+  assert_eq!(archive.len(), 7);
+  assert!(archive.by_name("case3/links.csv").is_ok());
+}
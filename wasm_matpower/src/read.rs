@@ -0,0 +1,61 @@
+// Case files by content: MATPOWER `.m` text, MATPOWER-JSON, MAT-files, CGMES, UCTE-DEF, PSLF EPC or PowerWorld aux
+
+use std::collections::BTreeMap;
+
+use anyhow::{anyhow, Result};
+
+use crate::{case, case::Case, cgmes, cgmes::Import, epc, json, matfile, powerworld, ucte};
+
+/// Read a case from the raw contents of a file, whatever its format. `name` is the file name, used when the format
+/// has no case name of its own.
+pub fn read(b: &[u8], name: &str) -> Result<Case> {
+  import(b, name).map(|i| i.case)
+}
+
+/// Read a case as `read`, with the CIM classes that were skipped if it came from CGMES. Other formats skip nothing.
+pub fn import(b: &[u8], name: &str) -> Result<Import> {
+  if cgmes::is_zip(b) {
+    return cgmes::case_from_zip(b, name);
+  }
+  if let Ok(s) = std::str::from_utf8(b) {
+    if cgmes::is_cim(s) {
+      return cgmes::case(&[s], name.strip_suffix(".xml").unwrap_or(name));
+    }
+  }
+  Ok(Import { case: read_other(b, name)?, skipped: BTreeMap::new() })
+}
+
+fn read_other(b: &[u8], name: &str) -> Result<Case> {
+  if matfile::is_matfile(b) {
+    return matfile::case(b, name);
+  }
+  // UCTE-DEF files are usually Latin-1.
+  if b.starts_with(b"##") {
+    let s = b.iter().map(|c| *c as char).collect::<String>();
+    if ucte::is_ucte(&s) {
+      let name = name.rsplit(['/', '\\']).next().unwrap_or(name);
+      return ucte::case(&s, name.strip_suffix(".uct").unwrap_or(name));
+    }
+  }
+  let s = std::str::from_utf8(b).map_err(|e| anyhow!("Unrecognized case file {:?}: {}", name, e))?;
+  if json::is_matpower_json(s) {
+    let name = name.rsplit(['/', '\\']).next().unwrap_or(name);
+    return json::case(s, name.strip_suffix(".json").unwrap_or(name));
+  }
+  if powerworld::is_aux(s) {
+    let name = name.rsplit(['/', '\\']).next().unwrap_or(name);
+    return powerworld::case(s, name.strip_suffix(".aux").unwrap_or(name));
+  }
+  if epc::is_epc(s) {
+    let name = name.rsplit(['/', '\\']).next().unwrap_or(name);
+    return epc::case(s, name.strip_suffix(".epc").unwrap_or(name));
+  }
+  case::case(s)
+}
+
+#[test]
+fn test_read() {
+  let s = "function mpc = case1\nmpc.version = '2';\nmpc.baseMVA = 100;\nmpc.bus = [\n\t1\t3\t0\t0\t0\t0\t1\t1\t0\t230\t1\t1.1\t0.9;\n];\nmpc.gen = [\n\t1\t0\t0\t300\t-300\t1\t100\t1\t250\t10\t0\t0\t0\t0\t0\t0\t0\t0\t0\t0\t0;\n];\nmpc.branch = [\n\t1\t1\t0\t0.0576\t0\t250\t250\t250\t0\t0\t1\t-360\t360;\n];\n";
+  assert_eq!(read(s.as_bytes(), "case1.m").unwrap().name, "case1");
+  assert!(read(&[0xff, 0xfe, 0x00], "case1.raw").is_err());
+}
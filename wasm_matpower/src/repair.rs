@@ -0,0 +1,285 @@
+// Repairing common defects
+
+// Files from third-party converters often come with the same defects: no reference bus, generators at PQ buses,
+// inverted limits, zero reactance branches, a `bus_name` or `gencost` of the wrong length and repeated rows. `repair`
+// fixes those it is asked to and reports every change, so nothing is changed behind the user's back. Nothing else in
+// the crate repairs cases on its own.
+
+use std::{collections::HashSet, fmt};
+
+use serde::Serialize;
+
+use crate::{
+  case::{BusType, Case, GenCost},
+  index::Table,
+  validate::RowRef,
+};
+
+/// Which repairs `Case::repair` makes. All are on by default.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RepairOptions {
+  /// remove buses repeating an earlier bus number, and branches and DC lines identical to an earlier row
+  pub duplicates: bool,
+  /// drop extra bus names and name unnamed buses by number
+  pub bus_names: bool,
+  /// drop extra cost rows and give generators without one a zero cost
+  pub gencost: bool,
+  /// swap inverted voltage, power and angle limits, and clamp angle limits to ±360 degrees
+  pub limits: bool,
+  /// set zero branch reactances to `min_reactance`
+  pub zero_reactance: bool,
+  pub min_reactance: f64,
+  /// make PQ buses with in-service generators PV buses
+  pub pv_buses: bool,
+  /// make the bus with the most generating capacity the reference bus of each island without one
+  pub reference_buses: bool,
+}
+
+impl Default for RepairOptions {
+  fn default() -> RepairOptions {
+    RepairOptions {
+      duplicates: true,
+      bus_names: true,
+      gencost: true,
+      limits: true,
+      zero_reactance: true,
+      min_reactance: 1e-4,
+      pv_buses: true,
+      reference_buses: true,
+    }
+  }
+}
+
+/// One change made by `Case::repair`. `element` is the row changed, numbered as it was at the time.
+#[derive(Serialize, Debug, PartialEq, Clone)]
+pub struct Repair {
+  pub action: &'static str,
+  pub message: String,
+  pub element: Option<RowRef>,
+}
+
+impl fmt::Display for Repair {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "[{}] {}", self.action, self.message)
+  }
+}
+
+fn at(table: Table, row: usize) -> Option<RowRef> {
+  Some(RowRef { table, row: row + 1 })
+}
+
+// Swaps `min` and `max` if they are inverted.
+fn order(min: &mut f64, max: &mut f64) -> bool {
+  if *min > *max {
+    std::mem::swap(min, max);
+    true
+  } else {
+    false
+  }
+}
+
+impl Case {
+  /// Fix the defects `options` asks for, in the order of its fields, and report each change.
+  pub fn repair(&mut self, options: &RepairOptions) -> Vec<Repair> {
+    let mut v = vec![];
+    if options.duplicates {
+      self.remove_duplicates(&mut v);
+    }
+    if options.bus_names && !self.bus_name.is_empty() && self.bus_name.len() != self.bus.len() {
+      let n = self.bus_name.len();
+      let numbers = self.bus.iter().skip(n).map(|b| b.idx.to_string()).collect::<Vec<_>>();
+      self.bus_name.truncate(self.bus.len());
+      self.bus_name.extend(numbers);
+      let message = format!("Changed the number of bus names from {} to {}", n, self.bus.len());
+      v.push(Repair { action: "bus_names", message, element: None });
+    }
+    if options.gencost {
+      let (n, ng) = (self.gencost.len(), self.gen.len());
+      if n != 0 && ng == 0 {
+        self.gencost.clear();
+        let message = format!("Removed {} cost rows from a case without generators", n);
+        v.push(Repair { action: "gencost", message, element: None });
+      } else if n != 0 && n != ng && n != 2 * ng {
+        let rows = if n > ng { ng * (n / ng).min(2) } else { ng };
+        self.gencost.resize(rows, GenCost::default());
+        let message = format!("Changed the number of cost rows from {} to {} for {} generators", n, rows, ng);
+        v.push(Repair { action: "gencost", message, element: None });
+      }
+    }
+    if options.limits {
+      self.repair_limits(&mut v);
+    }
+    if options.zero_reactance {
+      for (i, br) in self.branch.iter_mut().enumerate().filter(|(_, br)| br.br_x == 0.0) {
+        br.br_x = options.min_reactance;
+        let message = format!("Set the reactance of branch {}-{} to {}", br.f_bus, br.t_bus, options.min_reactance);
+        v.push(Repair { action: "zero_reactance", message, element: at(Table::Branch, i) });
+      }
+    }
+    if options.pv_buses {
+      let at_gens = self.in_service_gens().map(|g| g.gen).collect::<HashSet<_>>();
+      for (i, b) in self.bus.iter_mut().enumerate() {
+        if b.bus_type == BusType::PQ && at_gens.contains(&b.idx) {
+          b.bus_type = BusType::PV;
+          let message = format!("Made bus {} a PV bus", b.idx);
+          v.push(Repair { action: "pv_buses", message, element: at(Table::Bus, i) });
+        }
+      }
+    }
+    if options.reference_buses {
+      self.repair_reference_buses(&mut v);
+    }
+    v
+  }
+
+  fn remove_duplicates(&mut self, v: &mut Vec<Repair>) {
+    let mut seen = HashSet::new();
+    let names = self.bus_name.len() == self.bus.len();
+    let mut i = 0;
+    let mut row = 0;
+    while i < self.bus.len() {
+      if seen.insert(self.bus[i].idx) {
+        i += 1;
+      } else {
+        let message = format!("Removed bus {}, which repeats an earlier bus number", self.bus[i].idx);
+        v.push(Repair { action: "duplicates", message, element: at(Table::Bus, row) });
+        self.bus.remove(i);
+        if names {
+          self.bus_name.remove(i);
+        }
+      }
+      row += 1;
+    }
+    // Rows are compared as written, so NaN solution values never match.
+    let mut kept = vec![];
+    for (row, br) in std::mem::take(&mut self.branch).into_iter().enumerate() {
+      if kept.contains(&br) {
+        let message = format!("Removed branch {}-{}, which repeats an earlier row", br.f_bus, br.t_bus);
+        v.push(Repair { action: "duplicates", message, element: at(Table::Branch, row) });
+      } else {
+        kept.push(br);
+      }
+    }
+    self.branch = kept;
+    let mut kept = vec![];
+    for (row, d) in std::mem::take(&mut self.dcline).into_iter().enumerate() {
+      if kept.contains(&d) {
+        let message = format!("Removed DC line {}-{}, which repeats an earlier row", d.f_bus, d.t_bus);
+        v.push(Repair { action: "duplicates", message, element: at(Table::DcLine, row) });
+      } else {
+        kept.push(d);
+      }
+    }
+    self.dcline = kept;
+  }
+
+  fn repair_limits(&mut self, v: &mut Vec<Repair>) {
+    for (i, b) in self.bus.iter_mut().enumerate() {
+      if order(&mut b.v_min, &mut b.v_max) {
+        let message = format!("Swapped the inverted voltage limits of bus {}", b.idx);
+        v.push(Repair { action: "limits", message, element: at(Table::Bus, i) });
+      }
+    }
+    for (i, g) in self.gen.iter_mut().enumerate() {
+      if order(&mut g.pmin, &mut g.pmax) {
+        let message = format!("Swapped the inverted PMIN and PMAX of the generator at bus {}", g.gen);
+        v.push(Repair { action: "limits", message, element: at(Table::Gen, i) });
+      }
+      if order(&mut g.qmin, &mut g.qmax) {
+        let message = format!("Swapped the inverted QMIN and QMAX of the generator at bus {}", g.gen);
+        v.push(Repair { action: "limits", message, element: at(Table::Gen, i) });
+      }
+    }
+    for (i, br) in self.branch.iter_mut().enumerate() {
+      let swapped = order(&mut br.angmin, &mut br.angmax);
+      let (angmin, angmax) = (br.angmin.max(-360.0), br.angmax.min(360.0));
+      if swapped || (angmin, angmax) != (br.angmin, br.angmax) {
+        let message = format!(
+          "Changed the angle limits of branch {}-{} from [{}, {}] to [{}, {}]",
+          br.f_bus,
+          br.t_bus,
+          if swapped { br.angmax } else { br.angmin },
+          if swapped { br.angmin } else { br.angmax },
+          angmin,
+          angmax
+        );
+        br.angmin = angmin;
+        br.angmax = angmax;
+        v.push(Repair { action: "limits", message, element: at(Table::Branch, i) });
+      }
+    }
+  }
+
+  // The reference bus of an island is its bus with the highest total PMAX of in-service generators. Islands without
+  // generators are left alone.
+  fn repair_reference_buses(&mut self, v: &mut Vec<Repair>) {
+    for island in self.islands() {
+      if island.iter().any(|i| self.bus[*i].bus_type == BusType::Ref) {
+        continue;
+      }
+      let capacity = |i: &usize| self.gens_at(self.bus[*i].idx).filter(|g| g.in_service()).map(|g| g.pmax).sum::<f64>();
+      let best = island
+        .iter()
+        .filter(|i| self.gens_at(self.bus[**i].idx).any(|g| g.in_service()))
+        .max_by(|a, b| capacity(a).partial_cmp(&capacity(b)).unwrap_or(std::cmp::Ordering::Equal).then(b.cmp(a)))
+        .copied();
+      if let Some(i) = best {
+        self.bus[i].bus_type = BusType::Ref;
+        let message = format!("Made bus {} the reference bus of its island", self.bus[i].idx);
+        v.push(Repair { action: "reference_buses", message, element: at(Table::Bus, i) });
+      }
+    }
+  }
+}
+
+#[test]
+fn test_repair() {
+  use crate::case::{Branch, Bus, BusId, Gen};
+
+  let bus = |n: usize| Bus { idx: BusId(n), ..Bus::default() };
+  let mut c = crate::builder::CaseBuilder::new("case")
+    .bus(bus(1))
+    .bus(bus(2))
+    .bus(Bus { v_min: 1.1, v_max: 0.9, ..bus(3) })
+    .gen(Gen { gen: BusId(1), pmax: 100.0, ..Gen::default() })
+    .gen(Gen { gen: BusId(2), pmax: 200.0, pmin: 250.0, ..Gen::default() })
+    .branch(Branch { f_bus: BusId(1), t_bus: BusId(2), br_x: 0.1, ..Branch::default() })
+    .branch(Branch { f_bus: BusId(2), t_bus: BusId(3), angmin: 30.0, angmax: -400.0, ..Branch::default() })
+    .branch(Branch { f_bus: BusId(1), t_bus: BusId(2), br_x: 0.1, ..Branch::default() })
+    .build()
+    .unwrap();
+  c.bus.push(bus(2));
+  c.bus_name = vec!["A".to_string()];
+  c.gencost = vec![GenCost::default(); 3];
+
+  let report = c.repair(&RepairOptions::default());
+  assert_eq!(
+    report.iter().map(ToString::to_string).collect::<Vec<_>>(),
+    vec![
+      "[duplicates] Removed bus 2, which repeats an earlier bus number",
+      "[duplicates] Removed branch 1-2, which repeats an earlier row",
+      "[bus_names] Changed the number of bus names from 1 to 3",
+      "[gencost] Changed the number of cost rows from 3 to 2 for 2 generators",
+      "[limits] Swapped the inverted voltage limits of bus 3",
+      "[limits] Swapped the inverted PMIN and PMAX of the generator at bus 2",
+      "[limits] Changed the angle limits of branch 2-3 from [30, -400] to [-360, 30]",
+      "[zero_reactance] Set the reactance of branch 2-3 to 0.0001",
+      "[pv_buses] Made bus 1 a PV bus",
+      "[pv_buses] Made bus 2 a PV bus",
+      "[reference_buses] Made bus 2 the reference bus of its island",
+    ]
+  );
+  assert_eq!(report[0].element, Some(RowRef { table: Table::Bus, row: 4 }));
+  assert_eq!((c.bus.len(), c.branch.len(), c.gencost.len()), (3, 2, 2));
+  assert_eq!(c.bus_name, vec!["A", "2", "3"]);
+  assert_eq!((c.bus[2].v_min, c.gen[1].pmin, c.branch[1].angmin, c.branch[1].angmax), (0.9, 200.0, -360.0, 30.0));
+  assert_eq!(c.bus[1].bus_type, BusType::Ref);
+  assert!(crate::validate::validate(&c, &Default::default()).is_empty());
+  assert!(c.repair(&RepairOptions::default()).is_empty());
+
+  c.gen.clear();
+  c.gencost = vec![GenCost::default(); 2];
+  let report = c.repair(&RepairOptions::default());
+  assert_eq!(report[0].to_string(), "[gencost] Removed 2 cost rows from a case without generators");
+  assert!(c.gencost.is_empty());
+}
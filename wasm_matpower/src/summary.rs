@@ -0,0 +1,216 @@
+// System summary
+
+// Totals and counts for a case, along the lines of MATPOWER's `case_info`: demand, generating capacity and dispatch,
+// shunts, branches by kind, voltage levels, bus types, per area and per zone totals and DC line transfers. Powers are
+// in MW and MVAr. Generators, branches and DC lines count towards capacity and transfers only when in service.
+
+use std::collections::{BTreeMap, HashMap};
+
+use serde::Serialize;
+
+use crate::case::{BusType, Case};
+
+/// A range of power, such as the sum of generator limits.
+#[derive(Serialize, Debug, PartialEq, Clone, Copy, Default)]
+pub struct Range {
+  pub min: f64,
+  pub max: f64,
+}
+
+/// Real and reactive power.
+#[derive(Serialize, Debug, PartialEq, Clone, Copy, Default)]
+pub struct Power {
+  pub p: f64,
+  pub q: f64,
+}
+
+/// Numbers of elements, all and in service.
+#[derive(Serialize, Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub struct Count {
+  pub total: usize,
+  pub in_service: usize,
+}
+
+/// Branches by kind. Transformers are branches with a tap ratio or a phase shift.
+#[derive(Serialize, Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub struct BranchCounts {
+  pub lines: Count,
+  pub transformers: Count,
+}
+
+/// Numbers of buses of each type.
+#[derive(Serialize, Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub struct BusTypeCounts {
+  pub pq: usize,
+  pub pv: usize,
+  pub reference: usize,
+  pub isolated: usize,
+}
+
+/// Number of buses at a base voltage.
+#[derive(Serialize, Debug, PartialEq, Clone, Copy)]
+pub struct VoltageLevel {
+  pub base_kv: f64,
+  pub buses: usize,
+}
+
+/// Totals for the buses of one area or zone and the generators at them.
+#[derive(Serialize, Debug, PartialEq, Clone, Copy, Default)]
+pub struct GroupSummary {
+  pub id: usize,
+  pub buses: usize,
+  pub load: Power,
+  pub capacity: Range,
+  pub dispatch: Power,
+}
+
+/// Total DC line transfers, at the from and to ends.
+#[derive(Serialize, Debug, PartialEq, Clone, Copy, Default)]
+pub struct DcLineSummary {
+  pub count: Count,
+  pub from: f64,
+  pub to: f64,
+  pub losses: f64,
+}
+
+/// What `Case::summary` finds in a case.
+#[derive(Serialize, Debug, PartialEq, Clone, Default)]
+pub struct Summary {
+  pub buses: usize,
+  pub gens: Count,
+  pub branches: BranchCounts,
+  pub bus_types: BusTypeCounts,
+  /// total demand
+  pub load: Power,
+  /// total shunt conductance and susceptance, in MW and MVAr at 1 p.u.
+  pub shunt: Power,
+  /// sum of PMIN to sum of PMAX
+  pub p_capacity: Range,
+  /// sum of QMIN to sum of QMAX
+  pub q_capacity: Range,
+  /// total PG and QG
+  pub dispatch: Power,
+  /// buses at each base voltage, lowest first
+  pub voltage_levels: Vec<VoltageLevel>,
+  pub areas: Vec<GroupSummary>,
+  pub zones: Vec<GroupSummary>,
+  pub dcline: DcLineSummary,
+}
+
+impl Case {
+  /// Totals and counts for the case, as MATPOWER's `case_info`.
+  pub fn summary(&self) -> Summary {
+    let mut s = Summary { buses: self.bus.len(), ..Summary::default() };
+    let mut levels = Vec::<VoltageLevel>::new();
+    let mut areas = BTreeMap::<usize, GroupSummary>::new();
+    let mut zones = BTreeMap::<usize, GroupSummary>::new();
+    let mut bus_groups = HashMap::new();
+    for b in self.bus.iter() {
+      match b.bus_type {
+        BusType::PQ => s.bus_types.pq += 1,
+        BusType::PV => s.bus_types.pv += 1,
+        BusType::Ref => s.bus_types.reference += 1,
+        BusType::Isolated => s.bus_types.isolated += 1,
+      }
+      s.load.p += b.pd;
+      s.load.q += b.qd;
+      s.shunt.p += b.shunt_conductance;
+      s.shunt.q += b.shunt_susceptance;
+      match levels.iter_mut().find(|l| l.base_kv == b.base_kv) {
+        Some(l) => l.buses += 1,
+        None => levels.push(VoltageLevel { base_kv: b.base_kv, buses: 1 }),
+      }
+      for (groups, id) in [(&mut areas, b.area), (&mut zones, b.zone)].iter_mut() {
+        let g = groups.entry(*id).or_insert(GroupSummary { id: *id, ..GroupSummary::default() });
+        g.buses += 1;
+        g.load.p += b.pd;
+        g.load.q += b.qd;
+      }
+      bus_groups.entry(b.idx).or_insert((b.area, b.zone));
+    }
+    levels.sort_by(|a, b| a.base_kv.partial_cmp(&b.base_kv).unwrap_or(std::cmp::Ordering::Equal));
+    s.voltage_levels = levels;
+
+    s.gens.total = self.gen.len();
+    for g in self.in_service_gens() {
+      s.gens.in_service += 1;
+      s.p_capacity.min += g.pmin;
+      s.p_capacity.max += g.pmax;
+      s.q_capacity.min += g.qmin;
+      s.q_capacity.max += g.qmax;
+      s.dispatch.p += g.pg;
+      s.dispatch.q += g.qg;
+      if let Some((area, zone)) = bus_groups.get(&g.gen) {
+        for group in [areas.get_mut(area), zones.get_mut(zone)].iter_mut().flatten() {
+          group.capacity.min += g.pmin;
+          group.capacity.max += g.pmax;
+          group.dispatch.p += g.pg;
+          group.dispatch.q += g.qg;
+        }
+      }
+    }
+    s.areas = areas.into_values().collect();
+    s.zones = zones.into_values().collect();
+
+    for br in self.branch.iter() {
+      let count = if br.tap != 0.0 || br.shift != 0.0 { &mut s.branches.transformers } else { &mut s.branches.lines };
+      count.total += 1;
+      count.in_service += br.in_service() as usize;
+    }
+    s.dcline.count.total = self.dcline.len();
+    for d in self.dcline.iter().filter(|d| d.in_service()) {
+      s.dcline.count.in_service += 1;
+      s.dcline.from += d.pf;
+      s.dcline.to += d.pt;
+    }
+    s.dcline.losses = s.dcline.from - s.dcline.to;
+    s
+  }
+}
+
+#[test]
+fn test_summary() {
+  let c = crate::case::case(
+    r#"function mpc = case3
+mpc.version = '2';
+mpc.baseMVA = 100;
+mpc.bus = [
+	1	3	0	0	0	0	1	1	0	345	1	1.1	0.9;
+	2	1	50	10	0	20	1	1	0	345	2	1.1	0.9;
+	3	2	90	30	5	0	2	1	0	138	2	1.1	0.9;
+];
+mpc.gen = [
+	1	60	5	300	-300	1	100	1	250	10	0	0	0	0	0	0	0	0	0	0	0;
+	3	50	-5	100	-100	1	100	1	150	0	0	0	0	0	0	0	0	0	0	0	0;
+	3	0	0	100	-100	1	100	0	150	0	0	0	0	0	0	0	0	0	0	0	0;
+];
+mpc.branch = [
+	1	2	0.01	0.1	0	250	250	300	0	0	1	-360	360;
+	2	3	0.01	0.1	0	250	250	300	0.98	0	1	-360	360;
+	1	3	0.01	0.1	0	250	250	300	0	0	0	-360	360;
+];
+mpc.dcline = [
+	1	3	1	10	8.9	0	0	1.01	1	1	100	-10	10	-10	10	1	0.01;
+];
+"#,
+  )
+  .unwrap();
+  let s = c.summary();
+  assert_eq!((s.buses, s.gens, s.bus_types.pv), (3, Count { total: 3, in_service: 2 }, 1));
+  assert_eq!((s.load, s.shunt), (Power { p: 140.0, q: 40.0 }, Power { p: 5.0, q: 20.0 }));
+  assert_eq!((s.p_capacity, s.q_capacity), (Range { min: 10.0, max: 400.0 }, Range { min: -400.0, max: 400.0 }));
+  assert_eq!(s.dispatch, Power { p: 110.0, q: 0.0 });
+  assert_eq!(s.branches.lines, Count { total: 2, in_service: 1 });
+  assert_eq!(s.branches.transformers, Count { total: 1, in_service: 1 });
+  assert_eq!(
+    s.voltage_levels,
+    vec![VoltageLevel { base_kv: 138.0, buses: 1 }, VoltageLevel { base_kv: 345.0, buses: 2 }]
+  );
+  assert_eq!(
+    s.areas.iter().map(|a| (a.id, a.buses, a.capacity.max)).collect::<Vec<_>>(),
+    vec![(1, 2, 250.0), (2, 1, 150.0)]
+  );
+  assert_eq!(s.zones[1].load, Power { p: 140.0, q: 40.0 });
+  assert!((s.dcline.losses - 1.1).abs() < 1e-12);
+  assert_eq!(serde_json::to_value(&s).unwrap()["bus_types"]["reference"], 1);
+}
@@ -0,0 +1,333 @@
+// UCTE data exchange format (UCTE-DEF) reader
+
+// Fixed column records in `##N` (nodes), `##L` (lines), `##T` (transformers) and `##R` (regulation) blocks. Other
+// blocks (`##TT`, `##E`, ...) are ignored. Impedances are given in ohms and converted to per-unit on 100 MVA and the
+// voltage level encoded in the 7th character of each 8 character node code.
+
+use std::collections::HashMap;
+
+use anyhow::{anyhow, Result};
+
+use crate::{
+  case::{Branch, Bus, BusId, BusType, Case, Gen, Version},
+  units::Base,
+};
+
+const BASE_MVA: f64 = 100.0;
+
+fn base_kv(code: &str) -> Result<f64> {
+  match code.chars().nth(6) {
+    Some('0') => Ok(750.0),
+    Some('1') => Ok(380.0),
+    Some('2') => Ok(220.0),
+    Some('3') => Ok(150.0),
+    Some('4') => Ok(120.0),
+    Some('5') => Ok(110.0),
+    Some('6') => Ok(70.0),
+    Some('7') => Ok(27.0),
+    Some('8') => Ok(330.0),
+    Some('9') => Ok(500.0),
+    _ => Err(anyhow!("Invalid voltage level code in node {:?}", code)),
+  }
+}
+
+#[test]
+fn test_base_kv() {
+  assert_eq!(base_kv("BNODE111").unwrap(), 380.0);
+  assert_eq!(base_kv("DABCDE51").unwrap(), 110.0);
+  assert!(base_kv("DABCDEX1").is_err());
+  assert!(base_kv("D").is_err());
+}
+
+// Columns are 1-based and inclusive, as in the format description. Short lines have empty trailing fields.
+fn field(line: &str, start: usize, end: usize) -> &str {
+  let begin = line.char_indices().nth(start - 1).map_or(line.len(), |(i, _)| i);
+  let finish = line.char_indices().nth(end).map_or(line.len(), |(i, _)| i);
+  line[begin..finish].trim()
+}
+
+#[test]
+fn test_field() {
+  assert_eq!(field("BNODE111 NORTH        0 3", 1, 8), "BNODE111");
+  assert_eq!(field("BNODE111 NORTH        0 3", 10, 21), "NORTH");
+  assert_eq!(field("BNODE111 NORTH        0 3", 27, 32), "");
+}
+
+struct Line<'a> {
+  text: &'a str,
+  number: usize,
+}
+
+impl<'a> Line<'a> {
+  fn str(&self, start: usize, end: usize) -> &'a str {
+    field(self.text, start, end)
+  }
+
+  fn f64(&self, start: usize, end: usize) -> Result<f64> {
+    match self.str(start, end) {
+      "" => Ok(0.0),
+      s => s.parse().map_err(|_| anyhow!("Invalid number {:?} in columns {}-{} on line {}", s, start, end, self.number)),
+    }
+  }
+
+  fn node(&self, start: usize) -> &'a str {
+    self.str(start, start + 7)
+  }
+
+  // Node codes, with the order code telling parallel elements apart
+  fn element(&self) -> (String, String, String) {
+    (self.node(1).to_string(), self.node(10).to_string(), self.str(19, 19).to_string())
+  }
+}
+
+// Tap ratio and phase shift (degrees) of a regulated winding
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+struct Regulation {
+  ratio: f64,
+  shift: f64,
+}
+
+fn regulation(l: &Line) -> Result<Regulation> {
+  let mut ratio = 1.0;
+  let mut shift = 0.0;
+  // Phase (voltage magnitude) regulation
+  if !l.str(21, 25).is_empty() {
+    ratio += l.f64(30, 32)? * l.f64(21, 25)? / 100.0;
+  }
+  // Angle regulation
+  if !l.str(40, 44).is_empty() {
+    let step = l.f64(55, 57)? * l.f64(40, 44)? / 100.0;
+    let theta = l.f64(46, 50)?.to_radians();
+    match l.str(65, 68) {
+      "SYMM" => shift += 2.0 * (step / 2.0).atan().to_degrees(),
+      _ => {
+        let (re, im) = (ratio + step * theta.cos(), step * theta.sin());
+        shift += im.atan2(re).to_degrees();
+        ratio = (re * re + im * im).sqrt();
+      },
+    }
+  }
+  Ok(Regulation { ratio, shift })
+}
+
+/// Build a case from the contents of a UCTE-DEF file.
+pub fn case(s: &str, name: &str) -> Result<Case> {
+  let mut c = Case {
+    name: name.to_string(),
+    version: Version::Version2,
+    base_mva: BASE_MVA,
+    bus: vec![],
+    gen: vec![],
+    gencost: vec![],
+    branch: vec![],
+    dcline: vec![],
+    bus_name: vec![],
+    extra: Default::default(),
+  };
+  let mut nodes = HashMap::new();
+  let mut areas: Vec<String> = vec![];
+  let mut lines = vec![];
+  let mut transformers = vec![];
+  let mut regulations = HashMap::new();
+
+  let mut block = "";
+  for (i, text) in s.lines().enumerate() {
+    let l = Line { text: text.trim_end(), number: i + 1 };
+    if let Some(b) = l.text.strip_prefix("##") {
+      // `##Z<country>` starts the nodes of one country within `##N`.
+      if let Some(country) = b.strip_prefix('Z') {
+        areas.push(country.trim().to_string());
+      } else {
+        block = b;
+      }
+      continue;
+    }
+    if l.text.is_empty() {
+      continue;
+    }
+    match block {
+      "N" => {
+        let code = l.node(1);
+        let kv = base_kv(code).map_err(|e| anyhow!("{} on line {}", e, l.number))?;
+        let bus_type = match l.str(25, 25) {
+          "2" => BusType::PV,
+          "3" => BusType::Ref,
+          _ => BusType::PQ,
+        };
+        let u = l.f64(27, 32)?;
+        let bus = Bus {
+          idx: BusId(c.bus.len() + 1),
+          bus_type,
+          pd: l.f64(34, 40)?,
+          qd: l.f64(42, 48)?,
+          area: areas.len().max(1),
+          voltage_mag: if u > 0.0 { u / kv } else { 1.0 },
+          base_kv: kv,
+          ..Bus::default()
+        };
+        // Generation is negative in UCTE-DEF, and so are the limits.
+        let (pg, qg) = (-l.f64(50, 56)?, -l.f64(58, 64)?);
+        let (pmin, pmax) = (-l.f64(66, 72)?, -l.f64(74, 80)?);
+        let (qmin, qmax) = (-l.f64(82, 88)?, -l.f64(90, 96)?);
+        if bus_type != BusType::PQ || [pg, qg, pmin, pmax].iter().any(|v| *v != 0.0) {
+          c.gen.push(Gen {
+            gen: bus.idx,
+            pg,
+            qg,
+            qmax: qmax.max(qmin),
+            qmin: qmin.min(qmax),
+            vg: bus.voltage_mag,
+            mbase: BASE_MVA,
+            pmax: pmax.max(pmin),
+            pmin: pmin.min(pmax),
+            ..Gen::default()
+          });
+        }
+        if nodes.insert(code.to_string(), c.bus.len()).is_some() {
+          return Err(anyhow!("Duplicate node {:?} on line {}", code, l.number));
+        }
+        c.bus_name.push(code.to_string());
+        c.bus.push(bus);
+      },
+      "L" => lines.push(l),
+      "T" => transformers.push(l),
+      "R" => {
+        regulations.insert(l.element(), regulation(&l)?);
+      },
+      _ => (),
+    }
+  }
+  if c.bus.is_empty() {
+    return Err(anyhow!("No nodes found, expected a ##N block"));
+  }
+
+  let bus = |code: &str, l: &Line| -> Result<usize> {
+    nodes.get(code).copied().ok_or_else(|| anyhow!("Unknown node {:?} on line {}", code, l.number))
+  };
+  // Status 0, 1 and 2 are in operation; 7, 8 and 9 are out of operation.
+  let status = |l: &Line| if matches!(l.str(21, 21), "7" | "8" | "9") { 0.0 } else { 1.0 };
+
+  for l in lines.iter() {
+    let (f, t) = (bus(l.node(1), l)?, bus(l.node(10), l)?);
+    let kv = c.bus[f].base_kv;
+    let z = Base::new(BASE_MVA, kv).z();
+    let rate = 3f64.sqrt() * kv * l.f64(46, 51)? / 1000.0;
+    c.branch.push(Branch {
+      f_bus: BusId(f + 1),
+      t_bus: BusId(t + 1),
+      br_r: l.f64(23, 28)? / z,
+      br_x: l.f64(30, 35)? / z,
+      br_b: l.f64(37, 44)? * 1e-6 * z,
+      rate_a: rate,
+      rate_b: rate,
+      rate_c: rate,
+      br_status: status(l),
+      ..Branch::default()
+    });
+  }
+
+  // Node 1 is the non-regulated winding, node 2 the regulated one, and R, X and B refer to rated voltage 1. The
+  // branch goes from node 2 so that the tap sits on the regulated winding and the impedance on node 1's side, in per
+  // unit on its base; the ratio of both rated voltages to the base kV is in the tap. The magnetizing conductance has no
+  // place in a MATPOWER branch and is dropped.
+  for l in transformers.iter() {
+    let (n1, n2) = (bus(l.node(1), l)?, bus(l.node(10), l)?);
+    let (u1, u2) = (l.f64(23, 27)?, l.f64(29, 33)?);
+    let (kv1, kv2) = (c.bus[n1].base_kv, c.bus[n2].base_kv);
+    let z = Base::new(BASE_MVA, kv1).z();
+    let r = regulations.get(&l.element()).copied().unwrap_or(Regulation { ratio: 1.0, shift: 0.0 });
+    let s = l.f64(35, 39)?;
+    c.branch.push(Branch {
+      f_bus: BusId(n2 + 1),
+      t_bus: BusId(n1 + 1),
+      br_r: l.f64(41, 46)? / z,
+      br_x: l.f64(48, 53)? / z,
+      br_b: l.f64(55, 62)? * 1e-6 * z,
+      rate_a: s,
+      rate_b: s,
+      rate_c: s,
+      tap: (u2 * r.ratio / kv2) / (u1 / kv1),
+      shift: r.shift,
+      br_status: status(l),
+      ..Branch::default()
+    });
+  }
+  Ok(c)
+}
+
+/// True if `s` looks like a UCTE-DEF file.
+pub fn is_ucte(s: &str) -> bool {
+  s.lines().any(|l| l.starts_with("##N")) && s.lines().all(|l| !l.starts_with("function"))
+}
+
+#[cfg(test)]
+const UCTE: &str = "##C 2007.05.01
+Test network
+##N
+##ZBE
+BNODE111 NORTH        0 3 400.00  10.000  5.0000 -400.00 -50.000 0.00000 -1000.0 500.000 -500.00
+BNODE211 SOUTH        0 0 0.0000  300.00  100.00 0.00000 0.00000 0.00000 0.00000 0.00000 0.00000
+##ZNL
+NNODE351 SOUTH 110    0 0 0.0000  50.000  10.000 0.00000 0.00000 0.00000 0.00000 0.00000 0.00000
+##L
+BNODE111 BNODE211 1 0 2.8880 28.880  100.000   1000 L1
+BNODE111 BNODE211 2 8 2.8880 28.880  100.000   1000
+##T
+BNODE211 NNODE351 1 0 380.0 115.0 500.0 0.5776 57.760  -10.000 1.0000    760 T1
+##R
+BNODE211 NNODE351 1 1.250 16   2
+";
+
+#[test]
+fn test_case() {
+  let c = case(UCTE, "test").unwrap();
+  assert_eq!(c.bus_name, vec!["BNODE111", "BNODE211", "NNODE351"]);
+  assert_eq!(c.bus.iter().map(|b| b.base_kv).collect::<Vec<_>>(), vec![380.0, 380.0, 110.0]);
+  assert_eq!(c.bus.iter().map(|b| b.area).collect::<Vec<_>>(), vec![1, 1, 2]);
+  assert_eq!(c.bus[0].bus_type, BusType::Ref);
+  assert!((c.bus[0].voltage_mag - 400.0 / 380.0).abs() < 1e-12);
+  assert_eq!((c.bus[1].pd, c.bus[1].qd), (300.0, 100.0));
+
+  assert_eq!(c.gen.len(), 1);
+  assert_eq!((c.gen[0].gen.0, c.gen[0].pg, c.gen[0].qg), (1, 400.0, 50.0));
+  assert_eq!((c.gen[0].pmin, c.gen[0].pmax), (0.0, 1000.0));
+  assert_eq!((c.gen[0].qmin, c.gen[0].qmax), (-500.0, 500.0));
+
+  assert_eq!(c.branch.len(), 3);
+  let l = &c.branch[0];
+  assert!((l.br_r - 0.002).abs() < 1e-12 && (l.br_x - 0.02).abs() < 1e-12 && (l.br_b - 0.1444).abs() < 1e-12);
+  assert!((l.rate_a - 3f64.sqrt() * 380.0).abs() < 1e-9);
+  assert_eq!(c.branch[1].br_status, 0.0);
+
+  let t = &c.branch[2];
+  assert_eq!((t.f_bus, t.t_bus), (BusId(3), BusId(2)));
+  assert!((t.br_x - 57.76 / 1444.0).abs() < 1e-12);
+  assert!((t.tap - 115.0 * 1.025 / 110.0).abs() < 1e-12);
+  assert_eq!(t.rate_a, 500.0);
+
+  // A 400 kV winding at a 380 kV node
+  let c = case(&UCTE.replace(" 380.0 115.0 ", " 400.0 115.0 "), "test").unwrap();
+  let t = &c.branch[2];
+  assert!((t.br_r - 0.5776 / 1444.0).abs() < 1e-12 && (t.br_x - 57.76 / 1444.0).abs() < 1e-12);
+  assert!((t.br_b + 10e-6 * 1444.0).abs() < 1e-12);
+  assert!((t.tap - (115.0 * 1.025 / 110.0) / (400.0 / 380.0)).abs() < 1e-12);
+}
+
+#[test]
+fn test_regulation() {
+  let l = Line { text: "BNODE211 NNODE351 1                    1.000 90.00 16   4   0.0 SYMM", number: 1 };
+  let r = regulation(&l).unwrap();
+  assert_eq!(r.ratio, 1.0);
+  assert!((r.shift - 2.0 * 0.02f64.atan().to_degrees()).abs() < 1e-12);
+  let l = Line { text: "BNODE211 NNODE351 1                    1.000 90.00 16   4   0.0 ASYM", number: 1 };
+  let r = regulation(&l).unwrap();
+  assert!((r.ratio - (1.0f64 + 0.04 * 0.04).sqrt()).abs() < 1e-12);
+  assert!((r.shift - 0.04f64.atan().to_degrees()).abs() < 1e-12);
+}
+
+#[test]
+fn test_case_errors() {
+  assert!(case("##N\nBNODE1X1 NORTH        0 3\n", "test").is_err());
+  assert!(case("##N\nBNODE111 NORTH        0 3\n##L\nBNODE111 BNODE211 1 0 2.8880\n", "test").is_err());
+  assert!(case("##C\n", "test").is_err());
+}
@@ -0,0 +1,229 @@
+// Unit conversions
+
+// MATPOWER keeps powers in MW and MVAr, angles in degrees, and branch parameters in per unit on the system MVA base
+// and the base kV of the branch's from bus, which for a transformer is the tap side. Generator capability stays in MW
+// on the system base, with `mbase` as the machine's own rating. Exporters and analyses get SI and per unit values
+// from here rather than from their own formulas. Conversions that need a bus's base kV go through `Units`, which
+// looks buses up by number.
+
+use anyhow::{anyhow, Result};
+
+use crate::{
+  case::{Branch, Bus, BusId, Case, Gen},
+  index::BusIndex,
+};
+
+/// A per unit base, a three phase power in MVA and a line to line voltage in kV.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Base {
+  pub mva: f64,
+  pub kv: f64,
+}
+
+impl Base {
+  pub fn new(mva: f64, kv: f64) -> Base {
+    Base { mva, kv }
+  }
+
+  /// Base impedance in ohms.
+  pub fn z(&self) -> f64 {
+    self.kv * self.kv / self.mva
+  }
+
+  /// Base admittance in siemens.
+  pub fn y(&self) -> f64 {
+    self.mva / (self.kv * self.kv)
+  }
+
+  /// Base current in kA.
+  pub fn i(&self) -> f64 {
+    self.mva / (3f64.sqrt() * self.kv)
+  }
+}
+
+/// A per unit impedance on base `from` expressed on base `to`.
+pub fn rebase_impedance(z: f64, from: Base, to: Base) -> f64 {
+  z * from.z() / to.z()
+}
+
+/// A per unit admittance on base `from` expressed on base `to`.
+pub fn rebase_admittance(y: f64, from: Base, to: Base) -> f64 {
+  y * from.y() / to.y()
+}
+
+/// A per unit power on `from_mva` expressed on `to_mva`.
+pub fn rebase_power(s: f64, from_mva: f64, to_mva: f64) -> f64 {
+  s * from_mva / to_mva
+}
+
+#[test]
+fn test_rebase() {
+  let (system, own) = (Base::new(100.0, 230.0), Base::new(250.0, 230.0));
+  assert!((system.z() - 529.0).abs() < 1e-9);
+  assert!((system.i() - 0.251021856).abs() < 1e-9);
+  assert!((rebase_impedance(0.1, system, own) - 0.25).abs() < 1e-12);
+  assert!((rebase_admittance(0.25, own, system) - 0.625).abs() < 1e-12);
+  assert!((rebase_impedance(0.1, Base::new(100.0, 115.0), Base::new(100.0, 230.0)) - 0.025).abs() < 1e-12);
+  assert_eq!(rebase_power(0.9, 100.0, 300.0), 0.3);
+}
+
+/// Series impedance and total line charging of a branch in SI units.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct BranchSi {
+  /// resistance (Ω)
+  pub r: f64,
+  /// reactance (Ω)
+  pub x: f64,
+  /// total line charging susceptance (µS)
+  pub b: f64,
+}
+
+/// Series impedance and total line charging of a branch in per unit.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct BranchPu {
+  pub r: f64,
+  pub x: f64,
+  pub b: f64,
+}
+
+/// SI and per unit values of a case's elements.
+pub struct Units<'a> {
+  case: &'a Case,
+  index: BusIndex,
+}
+
+impl<'a> Units<'a> {
+  /// Base kV of bus `id`. Fails if there is no such bus or it has no base kV, as SI values can't be had without one.
+  pub fn base_kv(&self, id: BusId) -> Result<f64> {
+    match self.index.position(id).map(|i| self.case.bus[i].base_kv) {
+      Some(kv) if kv > 0.0 => Ok(kv),
+      Some(_) => Err(anyhow!("Bus {} has no base kV", id)),
+      None => Err(anyhow!("Unknown bus {}", id)),
+    }
+  }
+
+  /// The base `branch` is given in per unit on: the system MVA base and the base kV of its from bus.
+  pub fn branch_base(&self, branch: &Branch) -> Result<Base> {
+    Ok(Base::new(self.case.base_mva, self.base_kv(branch.f_bus)?))
+  }
+
+  /// Impedance in ohms and line charging in µS of `branch`.
+  pub fn branch_si(&self, branch: &Branch) -> Result<BranchSi> {
+    let base = self.branch_base(branch)?;
+    Ok(BranchSi { r: branch.br_r * base.z(), x: branch.br_x * base.z(), b: branch.br_b * base.y() * 1e6 })
+  }
+
+  /// Impedance and line charging of `branch` in per unit on `base`, such as a transformer's own rating.
+  pub fn branch_pu(&self, branch: &Branch, base: Base) -> Result<BranchPu> {
+    let from = self.branch_base(branch)?;
+    Ok(BranchPu {
+      r: rebase_impedance(branch.br_r, from, base),
+      x: rebase_impedance(branch.br_x, from, base),
+      b: rebase_admittance(branch.br_b, from, base),
+    })
+  }
+
+  /// Shunt conductance and susceptance of `bus` in siemens. MATPOWER gives them as MW and MVAr at 1 p.u.
+  pub fn shunt_si(&self, bus: &Bus) -> Result<(f64, f64)> {
+    let kv2 = self.base_kv(bus.idx)?.powi(2);
+    Ok((bus.shunt_conductance / kv2, bus.shunt_susceptance / kv2))
+  }
+}
+
+impl Case {
+  /// Unit conversions for this case. Fails on duplicate bus numbers.
+  pub fn units(&self) -> Result<Units<'_>> {
+    Ok(Units { case: self, index: self.bus_index()? })
+  }
+
+  /// Real and reactive demand of `bus` in per unit on the system base.
+  pub fn load_pu(&self, bus: &Bus) -> (f64, f64) {
+    (bus.pd / self.base_mva, bus.qd / self.base_mva)
+  }
+
+  /// Shunt conductance and susceptance of `bus` in per unit on the system base.
+  pub fn shunt_pu(&self, bus: &Bus) -> (f64, f64) {
+    (bus.shunt_conductance / self.base_mva, bus.shunt_susceptance / self.base_mva)
+  }
+
+  /// Real and reactive output of `gen` in per unit on the system base.
+  pub fn gen_pu(&self, gen: &Gen) -> (f64, f64) {
+    (gen.pg / self.base_mva, gen.qg / self.base_mva)
+  }
+}
+
+impl Bus {
+  /// Voltage angle in radians.
+  pub fn voltage_ang_rad(&self) -> f64 {
+    self.voltage_ang.to_radians()
+  }
+}
+
+impl Branch {
+  /// Phase shift in radians.
+  pub fn shift_rad(&self) -> f64 {
+    self.shift.to_radians()
+  }
+
+  /// Minimum and maximum angle difference in radians.
+  pub fn angle_limits_rad(&self) -> (f64, f64) {
+    (self.angmin.to_radians(), self.angmax.to_radians())
+  }
+
+  /// Off-nominal turns ratio, with MATPOWER's 0 for lines read as 1.
+  pub fn ratio(&self) -> f64 {
+    if self.tap == 0.0 {
+      1.0
+    } else {
+      self.tap
+    }
+  }
+}
+
+impl Gen {
+  /// Real and reactive output in per unit on the machine's own `mbase`.
+  pub fn output_on_mbase(&self) -> (f64, f64) {
+    (self.pg / self.mbase, self.qg / self.mbase)
+  }
+
+  /// A per unit value on `mbase`, such as a machine impedance, expressed on the system base `base_mva`.
+  pub fn impedance_on_system(&self, z: f64, base_mva: f64) -> f64 {
+    z * base_mva / self.mbase
+  }
+}
+
+#[test]
+fn test_case_units() {
+  let c = crate::case::case(
+    r#"function mpc = case2
+mpc.version = '2';
+mpc.baseMVA = 100;
+mpc.bus = [
+	1	3	0	0	0	0	1	1	0	230	1	1.1	0.9;
+	2	1	90	30	0	19	1	1	-30	230	1	1.1	0.9;
+];
+mpc.gen = [
+	1	90	20	300	-300	1	250	1	250	10	0	0	0	0	0	0	0	0	0	0	0;
+];
+mpc.branch = [
+	1	2	0.01	0.1	0.2	250	250	300	0	0	1	-360	360;
+];
+"#,
+  )
+  .unwrap();
+  let (u, br) = (c.units().unwrap(), &c.branch[0]);
+  let si = u.branch_si(br).unwrap();
+  assert!((si.r - 5.29).abs() < 1e-9 && (si.x - 52.9).abs() < 1e-9 && (si.b - 378.0718336).abs() < 1e-6);
+  let pu = u.branch_pu(br, Base::new(250.0, 230.0)).unwrap();
+  assert!((pu.x - 0.25).abs() < 1e-12 && (pu.b - 0.08).abs() < 1e-12);
+  assert_eq!(c.load_pu(&c.bus[1]), (0.9, 0.3));
+  let (g, b) = u.shunt_si(&c.bus[1]).unwrap();
+  assert!(g == 0.0 && (b - 19.0 / 230.0 / 230.0).abs() < 1e-15);
+  assert!((c.bus[1].voltage_ang_rad() + std::f64::consts::PI / 6.0).abs() < 1e-12);
+  assert_eq!(br.ratio(), 1.0);
+  assert_eq!(c.gen_pu(&c.gen[0]), (0.9, 0.2));
+  assert_eq!(c.gen[0].output_on_mbase(), (0.36, 0.08));
+  assert_eq!(c.gen[0].impedance_on_system(0.25, 100.0), 0.1);
+
+  assert_eq!(u.base_kv(BusId(3)).unwrap_err().to_string(), "Unknown bus 3");
+}
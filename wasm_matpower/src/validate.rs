@@ -0,0 +1,479 @@
+// Case validation
+
+// A lint for cases: named rules each look for one kind of problem and report findings against table rows, with the
+// row's place in the source text when the case was parsed from a `.m` file. Every rule has a default severity that a
+// `Config` can change, or turn the rule off. Duplicate buses and dangling references are findings rather than import
+// errors, in every file format, so a broken case can be shown with all of its problems at once.
+
+use std::{
+  collections::{HashMap, HashSet},
+  fmt,
+};
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+  case::{case_with_spans, BusType, Case, CostModel},
+  index::{RowSpans, SourceSpan, Table},
+  read,
+};
+
+/// How serious a finding is.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+  Info,
+  Warning,
+  Error,
+}
+
+impl fmt::Display for Severity {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Severity::Info => write!(f, "info"),
+      Severity::Warning => write!(f, "warning"),
+      Severity::Error => write!(f, "error"),
+    }
+  }
+}
+
+/// A row a finding is about. `row` is 1-based, as in MATLAB.
+#[derive(Serialize, Debug, PartialEq, Eq, Clone, Copy)]
+pub struct RowRef {
+  pub table: Table,
+  pub row: usize,
+}
+
+/// One problem found by a rule.
+#[derive(Serialize, Debug, PartialEq, Clone)]
+pub struct Finding {
+  pub rule: &'static str,
+  pub severity: Severity,
+  pub message: String,
+  /// the row the problem is in, if it is in one
+  pub element: Option<RowRef>,
+  /// where that row is in the source text
+  pub span: Option<SourceSpan>,
+}
+
+impl fmt::Display for Finding {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "{} [{}] {}", self.severity, self.rule, self.message)?;
+    if let Some(span) = self.span {
+      write!(f, " ({})", span)?;
+    }
+    Ok(())
+  }
+}
+
+/// A named check with a default severity.
+#[derive(Serialize, Clone, Copy)]
+pub struct Rule {
+  pub name: &'static str,
+  pub severity: Severity,
+  pub description: &'static str,
+  #[serde(skip)]
+  check: fn(&Case) -> Problems,
+}
+
+impl fmt::Debug for Rule {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    f.debug_struct("Rule").field("name", &self.name).field("severity", &self.severity).finish()
+  }
+}
+
+/// What a `Config` does with a rule.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum Level {
+  Off,
+  Info,
+  Warning,
+  Error,
+}
+
+/// Rule settings by rule name. Rules not in it run at their default severity.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Default)]
+#[serde(transparent)]
+pub struct Config(pub HashMap<String, Level>);
+
+impl Config {
+  pub fn set(mut self, rule: &str, level: Level) -> Config {
+    self.0.insert(rule.to_string(), level);
+    self
+  }
+
+  // The severity `rule` runs at, or None if it is off.
+  fn severity(&self, rule: &Rule) -> Option<Severity> {
+    match self.0.get(rule.name) {
+      None => Some(rule.severity),
+      Some(Level::Off) => None,
+      Some(Level::Info) => Some(Severity::Info),
+      Some(Level::Warning) => Some(Severity::Warning),
+      Some(Level::Error) => Some(Severity::Error),
+    }
+  }
+}
+
+// What a rule finds: messages, each with the row it is about.
+type Problems = Vec<(Option<RowRef>, String)>;
+
+fn at(table: Table, row: usize) -> Option<RowRef> {
+  Some(RowRef { table, row: row + 1 })
+}
+
+fn duplicate_bus(case: &Case) -> Problems {
+  let mut first = HashMap::new();
+  let mut v = vec![];
+  for (i, b) in case.bus.iter().enumerate() {
+    if let Some(j) = first.get(&b.idx) {
+      v.push((at(Table::Bus, i), format!("Bus {} is also in mpc.bus row {}", b.idx, j + 1)));
+    } else {
+      first.insert(b.idx, i);
+    }
+  }
+  v
+}
+
+fn dangling_reference(case: &Case) -> Problems {
+  let buses = case.bus.iter().map(|b| b.idx).collect::<HashSet<_>>();
+  let mut v = vec![];
+  let mut check = |table: Table, row: usize, bus| {
+    if !buses.contains(&bus) {
+      v.push((at(table, row), format!("{} row {} refers to bus {}, which is not in mpc.bus", table, row + 1, bus)));
+    }
+  };
+  for (i, g) in case.gen.iter().enumerate() {
+    check(Table::Gen, i, g.gen);
+  }
+  for (i, br) in case.branch.iter().enumerate() {
+    check(Table::Branch, i, br.f_bus);
+    check(Table::Branch, i, br.t_bus);
+  }
+  for (i, d) in case.dcline.iter().enumerate() {
+    check(Table::DcLine, i, d.f_bus);
+    check(Table::DcLine, i, d.t_bus);
+  }
+  v
+}
+
+fn island_without_ref(case: &Case) -> Problems {
+  let islands = case.islands();
+  let several = islands.len() > 1;
+  islands
+    .into_iter()
+    .filter(|island| island.iter().all(|i| case.bus[*i].bus_type != BusType::Ref))
+    .map(|island| {
+      let message = if several {
+        let buses = if island.len() == 1 { "bus" } else { "buses" };
+        format!("The island of {} {} with bus {} has no reference bus", island.len(), buses, case.bus[island[0]].idx)
+      } else {
+        "The case has no reference bus".to_string()
+      };
+      (at(Table::Bus, island[0]), message)
+    })
+    .collect()
+}
+
+fn voltage_limits(case: &Case) -> Problems {
+  let mut v = vec![];
+  for (i, b) in case.bus.iter().enumerate().filter(|(_, b)| b.v_min > b.v_max) {
+    v.push((at(Table::Bus, i), format!("Bus {} has VMIN {} above VMAX {}", b.idx, b.v_min, b.v_max)));
+  }
+  v
+}
+
+fn gen_p_limits(case: &Case) -> Problems {
+  let mut v = vec![];
+  for (i, g) in case.gen.iter().enumerate().filter(|(_, g)| g.pmin > g.pmax) {
+    v.push((at(Table::Gen, i), format!("Generator at bus {} has PMIN {} above PMAX {}", g.gen, g.pmin, g.pmax)));
+  }
+  v
+}
+
+fn gen_q_limits(case: &Case) -> Problems {
+  let mut v = vec![];
+  for (i, g) in case.gen.iter().enumerate().filter(|(_, g)| g.qmin > g.qmax) {
+    v.push((at(Table::Gen, i), format!("Generator at bus {} has QMIN {} above QMAX {}", g.gen, g.qmin, g.qmax)));
+  }
+  v
+}
+
+fn zero_impedance(case: &Case) -> Problems {
+  let mut v = vec![];
+  for (i, br) in case.branch.iter().enumerate().filter(|(_, br)| br.br_r == 0.0 && br.br_x == 0.0) {
+    v.push((at(Table::Branch, i), format!("Branch {}-{} has zero impedance", br.f_bus, br.t_bus)));
+  }
+  v
+}
+
+fn gencost_count(case: &Case) -> Problems {
+  let (n, ng) = (case.gencost.len(), case.gen.len());
+  if n == 0 || n == ng || n == 2 * ng {
+    vec![]
+  } else {
+    vec![(None, format!("Expected {} or {} cost rows for {} generators, found {}", ng, 2 * ng, ng, n))]
+  }
+}
+
+fn gencost_ncost(case: &Case) -> Problems {
+  let mut v = vec![];
+  for (i, c) in case.gencost.iter().enumerate() {
+    let n = match c.model {
+      CostModel::PiecewiseLinear => c.ncost * 2,
+      CostModel::Polynomial => c.ncost,
+    };
+    if n != c.cost.len() {
+      v.push((None, format!("Cost row {} has NCOST {} but {} values", i + 1, c.ncost, c.cost.len())));
+    }
+  }
+  v
+}
+
+fn bus_name_count(case: &Case) -> Problems {
+  let (n, nb) = (case.bus_name.len(), case.bus.len());
+  if n == 0 || n == nb {
+    vec![]
+  } else {
+    vec![(None, format!("Expected {} bus names, found {}", nb, n))]
+  }
+}
+
+fn gen_bus_type(case: &Case) -> Problems {
+  let types = case.bus.iter().map(|b| (b.idx, b.bus_type)).collect::<HashMap<_, _>>();
+  let mut v = vec![];
+  for (i, g) in case.gen.iter().enumerate().filter(|(_, g)| g.in_service()) {
+    match types.get(&g.gen) {
+      Some(BusType::PQ) => v.push((at(Table::Gen, i), format!("Generator at PQ bus {} can't hold its voltage", g.gen))),
+      Some(BusType::Isolated) => {
+        v.push((at(Table::Gen, i), format!("Generator at isolated bus {} is in service", g.gen)))
+      },
+      _ => {},
+    }
+  }
+  v
+}
+
+fn negative_rate_a(case: &Case) -> Problems {
+  let mut v = vec![];
+  for (i, br) in case.branch.iter().enumerate().filter(|(_, br)| br.rate_a < 0.0) {
+    v.push((at(Table::Branch, i), format!("Branch {}-{} has negative RATE_A {}", br.f_bus, br.t_bus, br.rate_a)));
+  }
+  v
+}
+
+fn angle_limits(case: &Case) -> Problems {
+  let mut v = vec![];
+  for (i, br) in case.branch.iter().enumerate() {
+    if br.angmin < -360.0 || br.angmax > 360.0 {
+      let message =
+        format!("Branch {}-{} has angle limits {} to {} outside ±360", br.f_bus, br.t_bus, br.angmin, br.angmax);
+      v.push((at(Table::Branch, i), message));
+    }
+  }
+  v
+}
+
+/// All rules, in the order they run.
+pub const RULES: [Rule; 13] = [
+  Rule {
+    name: "duplicate_bus",
+    severity: Severity::Error,
+    description: "bus numbers are unique",
+    check: duplicate_bus,
+  },
+  Rule {
+    name: "dangling_reference",
+    severity: Severity::Error,
+    description: "generators, branches and DC lines connect buses in mpc.bus",
+    check: dangling_reference,
+  },
+  Rule {
+    name: "island_without_ref",
+    severity: Severity::Error,
+    description: "every island has a reference bus",
+    check: island_without_ref,
+  },
+  Rule { name: "voltage_limits", severity: Severity::Error, description: "VMIN <= VMAX", check: voltage_limits },
+  Rule { name: "gen_p_limits", severity: Severity::Error, description: "PMIN <= PMAX", check: gen_p_limits },
+  Rule { name: "gen_q_limits", severity: Severity::Error, description: "QMIN <= QMAX", check: gen_q_limits },
+  Rule {
+    name: "zero_impedance",
+    severity: Severity::Warning,
+    description: "branches have a non-zero impedance",
+    check: zero_impedance,
+  },
+  Rule {
+    name: "gencost_count",
+    severity: Severity::Error,
+    description: "one or two cost rows per generator",
+    check: gencost_count,
+  },
+  Rule {
+    name: "gencost_ncost",
+    severity: Severity::Error,
+    description: "cost rows have as many values as NCOST says",
+    check: gencost_ncost,
+  },
+  Rule { name: "bus_name_count", severity: Severity::Error, description: "one name per bus", check: bus_name_count },
+  Rule {
+    name: "gen_bus_type",
+    severity: Severity::Warning,
+    description: "in-service generators are at PV or reference buses",
+    check: gen_bus_type,
+  },
+  Rule {
+    name: "negative_rate_a",
+    severity: Severity::Error,
+    description: "RATE_A is not negative",
+    check: negative_rate_a,
+  },
+  Rule {
+    name: "angle_limits",
+    severity: Severity::Warning,
+    description: "ANGMIN and ANGMAX are within ±360 degrees",
+    check: angle_limits,
+  },
+];
+
+fn run(case: &Case, spans: &RowSpans, config: &Config) -> Vec<Finding> {
+  let span = |r: &RowRef| {
+    let spans = match r.table {
+      Table::Bus => &spans.bus,
+      Table::Gen => &spans.gen,
+      Table::Branch => &spans.branch,
+      Table::DcLine => &spans.dcline,
+    };
+    spans.get(r.row - 1).copied()
+  };
+  let mut findings = vec![];
+  for rule in RULES.iter() {
+    let severity = match config.severity(rule) {
+      Some(severity) => severity,
+      None => continue,
+    };
+    for (element, message) in (rule.check)(case) {
+      findings.push(Finding { rule: rule.name, severity, message, span: element.as_ref().and_then(span), element });
+    }
+  }
+  findings
+}
+
+/// Findings of the rules `config` leaves on, rule by rule.
+pub fn validate(case: &Case, config: &Config) -> Vec<Finding> {
+  run(case, &RowSpans::default(), config)
+}
+
+/// Parse a MATPOWER case file and validate it, with findings pointing into `text`. Fails only if `text` can't be
+/// parsed.
+pub fn validate_text(text: &str, config: &Config) -> Result<Vec<Finding>> {
+  let (case, spans) = case_with_spans(text)?;
+  Ok(run(&case, &spans, config))
+}
+
+/// Read a case file of any format and validate it. Findings in `.m` files point to the rows they are about. Fails
+/// only if the file can't be read.
+pub fn validate_file(b: &[u8], name: &str, config: &Config) -> Result<Vec<Finding>> {
+  match std::str::from_utf8(b) {
+    Ok(text) if name.ends_with(".m") => validate_text(text, config),
+    _ => read::read(b, name).map(|c| validate(&c, config)),
+  }
+}
+
+#[cfg(test)]
+const CASE4: &str = r#"function mpc = case4
+mpc.version = '2';
+mpc.baseMVA = 100;
+mpc.bus = [
+	1	3	0	0	0	0	1	1	0	230	1	1.1	0.9;
+	2	1	50	10	0	0	1	1	0	230	1	0.9	1.1;
+	3	1	90	30	0	0	1	1	0	230	1	1.1	0.9;
+	3	2	0	0	0	0	1	1	0	230	1	1.1	0.9;
+];
+mpc.gen = [
+	1	60	0	300	-300	1	100	1	250	10	0	0	0	0	0	0	0	0	0	0	0;
+	2	50	0	300	-300	1	100	1	5	10	0	0	0	0	0	0	0	0	0	0	0;
+];
+mpc.branch = [
+	1	2	0	0	0	-1	250	300	0	0	1	-360	360;
+	3	7	0.01	0.1	0	250	250	300	0	0	1	-360	400;
+];
+"#;
+
+#[test]
+fn test_validate_text() {
+  let findings = validate_text(CASE4, &Config::default()).unwrap();
+  assert_eq!(
+    findings.iter().map(ToString::to_string).collect::<Vec<_>>(),
+    vec![
+      "error [duplicate_bus] Bus 3 is also in mpc.bus row 3 (line 8, column 2)",
+      "error [dangling_reference] mpc.branch row 2 refers to bus 7, which is not in mpc.bus (line 16, column 2)",
+      "error [island_without_ref] The island of 1 bus with bus 3 has no reference bus (line 7, column 2)",
+      "error [voltage_limits] Bus 2 has VMIN 1.1 above VMAX 0.9 (line 6, column 2)",
+      "error [gen_p_limits] Generator at bus 2 has PMIN 10 above PMAX 5 (line 12, column 2)",
+      "warning [zero_impedance] Branch 1-2 has zero impedance (line 15, column 2)",
+      "warning [gen_bus_type] Generator at PQ bus 2 can't hold its voltage (line 12, column 2)",
+      "error [negative_rate_a] Branch 1-2 has negative RATE_A -1 (line 15, column 2)",
+      "warning [angle_limits] Branch 3-7 has angle limits -360 to 400 outside ±360 (line 16, column 2)",
+    ]
+  );
+  assert_eq!(findings[0].element, Some(RowRef { table: Table::Bus, row: 4 }));
+
+  let config = Config::default().set("zero_impedance", Level::Off).set("angle_limits", Level::Error);
+  let findings = validate_text(CASE4, &config).unwrap();
+  assert!(findings.iter().all(|f| f.rule != "zero_impedance"));
+  assert_eq!(findings.last().unwrap().severity, Severity::Error);
+  let config: Config = serde_json::from_str(r#"{"duplicate_bus": "off", "gen_bus_type": "info"}"#).unwrap();
+  assert_eq!(config.severity(&RULES[0]), None);
+  assert_eq!(config.severity(&RULES[10]), Some(Severity::Info));
+}
+
+#[test]
+fn test_validate_file() {
+  let aux = "DATA (Bus, [BusNum, BusSlack])\n{\n1 YES\n2 NO\n2 NO\n}\nDATA (Gen, [BusNum])\n{\n1\n7\n}\n\
+    DATA (Branch, [BusNum, BusNum:1, LineX])\n{\n1 2 0.1\n}\n";
+  let findings = validate_file(aux.as_bytes(), "case.aux", &Config::default()).unwrap();
+  assert_eq!(
+    findings.iter().filter(|f| f.severity == Severity::Error).map(ToString::to_string).collect::<Vec<_>>(),
+    vec![
+      "error [duplicate_bus] Bus 2 is also in mpc.bus row 2",
+      "error [dangling_reference] mpc.gen row 2 refers to bus 7, which is not in mpc.bus",
+    ]
+  );
+  assert!(validate_file(b"not a case", "case.m", &Config::default()).is_err());
+}
+
+#[test]
+fn test_validate() {
+  use crate::case::{Branch, Bus, BusId, Gen, GenCost};
+
+  let cost = GenCost { model: CostModel::Polynomial, startup: 0.0, shutdown: 0.0, ncost: 2, cost: vec![20.0, 0.0] };
+  let mut c = crate::builder::CaseBuilder::new("case3")
+    .bus(Bus { idx: BusId(1), bus_type: BusType::Ref, ..Bus::default() })
+    .bus(Bus { idx: BusId(2), ..Bus::default() })
+    .bus(Bus { idx: BusId(3), bus_type: BusType::PV, ..Bus::default() })
+    .gen(Gen { gen: BusId(1), ..Gen::default() })
+    .gen(Gen { gen: BusId(3), ..Gen::default() })
+    .branch(Branch { f_bus: BusId(1), t_bus: BusId(2), br_x: 0.1, ..Branch::default() })
+    .branch(Branch { f_bus: BusId(2), t_bus: BusId(3), br_x: 0.1, ..Branch::default() })
+    .gencost(cost.clone())
+    .gencost(cost)
+    .build()
+    .unwrap();
+  assert!(validate(&c, &Config::default()).is_empty());
+  c.gencost.pop();
+  c.bus_name = vec!["A".to_string()];
+  c.branch.retain(|br| br.t_bus.0 != 3);
+  c.bus[2].bus_type = BusType::PQ;
+  let findings = validate(&c, &Config::default());
+  assert_eq!(
+    findings.iter().map(ToString::to_string).collect::<Vec<_>>(),
+    vec![
+      "error [island_without_ref] The island of 1 bus with bus 3 has no reference bus",
+      "error [gencost_count] Expected 2 or 4 cost rows for 2 generators, found 1",
+      "error [bus_name_count] Expected 3 bus names, found 1",
+      "warning [gen_bus_type] Generator at PQ bus 3 can't hold its voltage",
+    ]
+  );
+}
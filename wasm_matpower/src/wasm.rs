@@ -0,0 +1,358 @@
+// wasm-bindgen exports and web worker glue for the viewer
+
+use std::{cell::RefCell, rc::Rc};
+
+use js_sys::Promise;
+use wasm_bindgen::{prelude::*, JsCast};
+use wasm_bindgen_futures::{future_to_promise, spawn_local};
+use web_sys::{console, HtmlElement, HtmlInputElement, MessageEvent, Worker};
+
+use crate::{case, cgmes, edit, index::Table, powerflow, read, repair, validate};
+
+#[wasm_bindgen]
+extern "C" {
+  fn alert(s: &str);
+  #[wasm_bindgen(js_namespace = console)]
+  fn log(s: &str);
+}
+
+#[wasm_bindgen]
+extern "C" {
+  type Buffer;
+}
+
+#[wasm_bindgen(module = "fs")]
+extern "C" {
+  #[wasm_bindgen(js_name = readFileSync, catch)]
+  pub fn read_file(path: &str, encoding: &str) -> Result<String, JsValue>;
+
+  #[wasm_bindgen(js_name = writeFileSync, catch)]
+  pub fn write_file(path: &str, content: &str) -> Result<(), JsValue>;
+}
+
+#[wasm_bindgen]
+pub fn startup() {
+  // Here, we create our worker. In a larger app, multiple callbacks should be
+  // able to interact with the code in the worker. Therefore, we wrap it in
+  // `Rc<RefCell>` following the interior mutability pattern. Here, it would
+  // not be needed but we include the wrapping anyway as example.
+  let worker_handle = Rc::new(RefCell::new(Worker::new("./matpower.js").unwrap()));
+  console::log_1(&"Created a new worker from within WASM".into());
+
+  // Pass the worker to the function which sets up the `oninput` callback.
+  setup_input_oninput_callback(worker_handle.clone());
+}
+
+fn setup_input_oninput_callback(worker: Rc<RefCell<web_sys::Worker>>) {
+  let document = web_sys::window().unwrap().document().unwrap();
+
+  // If our `onmessage` callback should stay valid after exiting from the
+  // `oninput` closure scope, we need to either forget it (so it is not
+  // destroyed) or store it somewhere. To avoid leaking memory every time we
+  // want to receive a response from the worker, we move a handle into the
+  // `oninput` closure to which we will always attach the last `onmessage`
+  // callback. The initial value will not be used and we silence the warning.
+  #[allow(unused_assignments)]
+  let mut persistent_callback_handle = get_on_msg_callback();
+
+  let callback = Closure::wrap(Box::new(move || {
+    console::log_1(&"oninput callback triggered".into());
+    let document = web_sys::window().unwrap().document().unwrap();
+
+    let input_field = document.get_element_by_id("inputNumber").expect("#inputNumber should exist");
+    let input_field = input_field.dyn_ref::<HtmlInputElement>().expect("#inputNumber should be a HtmlInputElement");
+
+    // If the value in the field can be parsed to a `i32`, send it to the
+    // worker. Otherwise clear the result field.
+    match input_field.value().parse::<i32>() {
+      Ok(number) => {
+        // Access worker behind shared handle, following the interior
+        // mutability pattern.
+        let worker_handle = &*worker.borrow();
+        let _ = worker_handle.post_message(&number.into());
+        persistent_callback_handle = get_on_msg_callback();
+
+        // Since the worker returns the message asynchronously, we
+        // attach a callback to be triggered when the worker returns.
+        worker_handle.set_onmessage(Some(persistent_callback_handle.as_ref().unchecked_ref()));
+      },
+      Err(_) => {
+        document
+          .get_element_by_id("resultField")
+          .expect("#resultField should exist")
+          .dyn_ref::<HtmlElement>()
+          .expect("#resultField should be a HtmlInputElement")
+          .set_inner_text("");
+      },
+    }
+  }) as Box<dyn FnMut()>);
+
+  // Attach the closure as `oninput` callback to the input field.
+  document
+    .get_element_by_id("inputNumber")
+    .expect("#inputNumber should exist")
+    .dyn_ref::<HtmlInputElement>()
+    .expect("#inputNumber should be a HtmlInputElement")
+    .set_oninput(Some(callback.as_ref().unchecked_ref()));
+
+  // Leaks memory.
+  callback.forget();
+}
+
+/// Create a closure to act on the message returned by the worker
+fn get_on_msg_callback() -> Closure<dyn FnMut(MessageEvent)> {
+  let callback = Closure::wrap(Box::new(move |event: MessageEvent| {
+    console::log_2(&"Received response: ".into(), &event.data().into());
+
+    let result = match event.data().as_bool().unwrap() {
+      true => "even",
+      false => "odd",
+    };
+
+    let document = web_sys::window().unwrap().document().unwrap();
+    document
+      .get_element_by_id("resultField")
+      .expect("#resultField should exist")
+      .dyn_ref::<HtmlElement>()
+      .expect("#resultField should be a HtmlInputElement")
+      .set_inner_text(result);
+  }) as Box<dyn FnMut(_)>);
+
+  callback
+}
+
+#[wasm_bindgen]
+pub fn parse_case(s: String) -> Result<JsValue, JsValue> {
+  let r = case::case(&s);
+  if let Ok(c) = r {
+    Ok(JsValue::from_serde(&c).unwrap())
+  } else {
+    Ok(JsValue::NULL)
+  }
+}
+
+/// Read a case from the contents of a file, whatever its format. CIM classes skipped in a CGMES import are logged to
+/// the console; `import_cgmes` returns them.
+#[wasm_bindgen]
+pub fn parse_file(data: &[u8], name: String) -> Result<JsValue, JsValue> {
+  let i = read::import(data, &name).map_err(js_error)?;
+  for (class, n) in i.skipped.iter() {
+    console::warn_1(&format!("{}: skipped {} CIM {} object(s)", name, n, class).into());
+  }
+  Ok(JsValue::from_serde(&i.case).unwrap())
+}
+
+#[wasm_bindgen]
+pub fn import_cgmes(data: &[u8], name: String) -> Result<JsValue, JsValue> {
+  let i = cgmes::case_from_zip(data, &name).map_err(js_error)?;
+  Ok(JsValue::from_serde(&i).unwrap())
+}
+
+#[wasm_bindgen]
+pub fn attach_coordinates(c: JsValue, coords: String) -> Result<JsValue, JsValue> {
+  let mut c: case::Case = c.into_serde().map_err(js_error)?;
+  c.attach_coordinates(&coords).map_err(js_error)?;
+  Ok(JsValue::from_serde(&c).unwrap())
+}
+
+#[wasm_bindgen]
+pub fn to_geojson(c: JsValue) -> Result<String, JsValue> {
+  let c: case::Case = c.into_serde().map_err(js_error)?;
+  Ok(c.to_geojson().to_string())
+}
+
+#[wasm_bindgen]
+pub fn to_opendss(c: JsValue) -> Result<String, JsValue> {
+  let c: case::Case = c.into_serde().map_err(js_error)?;
+  c.to_opendss().map_err(js_error)
+}
+
+#[wasm_bindgen]
+pub fn to_pypsa(c: JsValue) -> Result<Vec<u8>, JsValue> {
+  let c: case::Case = c.into_serde().map_err(js_error)?;
+  c.to_pypsa_zip().map_err(js_error)
+}
+
+#[wasm_bindgen]
+pub fn to_xlsx(c: JsValue) -> Result<Vec<u8>, JsValue> {
+  let c: case::Case = c.into_serde().map_err(js_error)?;
+  c.to_xlsx().map_err(js_error)
+}
+
+#[wasm_bindgen]
+pub fn to_matpower_json(c: JsValue) -> Result<String, JsValue> {
+  let c: case::Case = c.into_serde().map_err(js_error)?;
+  Ok(c.to_matpower_json().to_string())
+}
+
+/// Differences from case `a` to case `b` as JSON, ignoring value changes of at most `tolerance`.
+#[wasm_bindgen]
+pub fn diff_cases(a: JsValue, b: JsValue, tolerance: f64) -> Result<String, JsValue> {
+  let a: case::Case = a.into_serde().map_err(js_error)?;
+  let b: case::Case = b.into_serde().map_err(js_error)?;
+  Ok(case::diff(&a, &b, tolerance).to_json().to_string())
+}
+
+/// Findings of the validation rules for a case file, as JSON. `config` maps rule names to "off", "info", "warning"
+/// or "error", and may be null. Findings in `.m` files point to the rows they are about.
+#[wasm_bindgen]
+pub fn validate_file(data: &[u8], name: String, config: JsValue) -> Result<String, JsValue> {
+  let config: Option<validate::Config> = config.into_serde().map_err(js_error)?;
+  let findings = validate::validate_file(data, &name, &config.unwrap_or_default()).map_err(js_error)?;
+  serde_json::to_string(&findings).map_err(js_error)
+}
+
+/// The validation rules with their default severities, as JSON.
+#[wasm_bindgen]
+pub fn validation_rules() -> Result<String, JsValue> {
+  serde_json::to_string(&validate::RULES[..]).map_err(js_error)
+}
+
+/// Totals and counts for a case, as `Case::summary`.
+#[wasm_bindgen]
+pub fn case_summary(c: JsValue) -> Result<JsValue, JsValue> {
+  let c: case::Case = c.into_serde().map_err(js_error)?;
+  Ok(JsValue::from_serde(&c.summary()).unwrap())
+}
+
+/// Make every repair to a case. Returns `{case, repairs}` with the repaired case and the changes made.
+#[wasm_bindgen]
+pub fn repair_case(c: JsValue) -> Result<JsValue, JsValue> {
+  let mut c: case::Case = c.into_serde().map_err(js_error)?;
+  let repairs = c.repair(&repair::RepairOptions::default());
+  Ok(JsValue::from_serde(&serde_json::json!({ "case": c, "repairs": repairs })).unwrap())
+}
+
+/// The admittance matrices of a case as Matrix Market text. Returns `{ybus, yf, yt}`.
+#[wasm_bindgen]
+pub fn admittance_matrices(c: JsValue) -> Result<JsValue, JsValue> {
+  let c: case::Case = c.into_serde().map_err(js_error)?;
+  let y = c.admittance().map_err(js_error)?;
+  let matrices = serde_json::json!({
+    "ybus": y.ybus.to_matrix_market(),
+    "yf": y.yf.to_matrix_market(),
+    "yt": y.yt.to_matrix_market(),
+  });
+  Ok(JsValue::from_serde(&matrices).unwrap())
+}
+
+/// Solve a case with the DC power flow. Returns a `PowerFlowResult`, with `success` false and a `message` if it failed.
+#[wasm_bindgen]
+pub fn dc_power_flow(c: JsValue) -> Result<JsValue, JsValue> {
+  let c: case::Case = c.into_serde().map_err(js_error)?;
+  Ok(JsValue::from_serde(&powerflow::dc_power_flow(&c)).unwrap())
+}
+
+/// Solve a case with the AC power flow. `options` has the fields of `PowerFlowOptions`, each optional, and may be
+/// null. Returns a `PowerFlowResult`.
+#[wasm_bindgen]
+pub fn ac_power_flow(c: JsValue, options: JsValue) -> Result<JsValue, JsValue> {
+  let c: case::Case = c.into_serde().map_err(js_error)?;
+  let options: Option<powerflow::PowerFlowOptions> = options.into_serde().map_err(js_error)?;
+  let options = options.unwrap_or_default();
+  Ok(JsValue::from_serde(&powerflow::ac_power_flow(&c, &options)).unwrap())
+}
+
+fn js_error(e: impl std::fmt::Display) -> JsValue {
+  JsValue::from(e.to_string())
+}
+
+fn table(name: &str) -> Result<Table, JsValue> {
+  name.parse().map_err(js_error)
+}
+
+/// A case kept in wasm memory and edited in place, with undo and redo. Rows are 0-based; tables are named "bus",
+/// "gen", "branch" or "dcline".
+#[wasm_bindgen]
+pub struct CaseHandle {
+  editor: edit::CaseEditor,
+}
+
+#[wasm_bindgen]
+impl CaseHandle {
+  /// Read a case from the contents of a file, as `parse_file`.
+  #[wasm_bindgen(constructor)]
+  pub fn new(data: &[u8], name: String) -> Result<CaseHandle, JsValue> {
+    let c = read::read(data, &name).map_err(js_error)?;
+    Ok(CaseHandle { editor: edit::CaseEditor::new(c).map_err(js_error)? })
+  }
+
+  pub fn from_case(c: JsValue) -> Result<CaseHandle, JsValue> {
+    let c: case::Case = c.into_serde().map_err(js_error)?;
+    Ok(CaseHandle { editor: edit::CaseEditor::new(c).map_err(js_error)? })
+  }
+
+  /// A copy of the case as it is now.
+  pub fn case(&self) -> JsValue {
+    JsValue::from_serde(self.editor.case()).unwrap()
+  }
+
+  pub fn set_field(&mut self, table_name: &str, row: usize, column: &str, value: f64) -> Result<(), JsValue> {
+    self.editor.set_field(table(table_name)?, row, column, value).map_err(js_error)
+  }
+
+  pub fn set_status(&mut self, table_name: &str, row: usize, in_service: bool) -> Result<(), JsValue> {
+    self.editor.set_status(table(table_name)?, row, in_service).map_err(js_error)
+  }
+
+  pub fn toggle_status(&mut self, table_name: &str, row: usize) -> Result<bool, JsValue> {
+    self.editor.toggle_status(table(table_name)?, row).map_err(js_error)
+  }
+
+  pub fn add_bus(&mut self, bus: JsValue, name: Option<String>) -> Result<(), JsValue> {
+    let bus: case::Bus = bus.into_serde().map_err(js_error)?;
+    self.editor.add_bus(bus, name).map_err(js_error)
+  }
+
+  /// Remove a bus by number. Elements at the bus are removed with it if `cascade` is set, otherwise they are listed
+  /// in the error.
+  pub fn remove_bus(&mut self, bus: usize, cascade: bool) -> Result<(), JsValue> {
+    self.editor.remove_bus(case::BusId(bus), cascade).map_err(js_error)
+  }
+
+  /// Add a generator, with its active power cost if the case has costs. `cost` may be null or undefined.
+  pub fn add_gen(&mut self, gen: JsValue, cost: JsValue) -> Result<(), JsValue> {
+    let gen: case::Gen = gen.into_serde().map_err(js_error)?;
+    let cost: Option<case::GenCost> = cost.into_serde().map_err(js_error)?;
+    self.editor.add_gen(gen, cost).map_err(js_error)
+  }
+
+  pub fn remove_gen(&mut self, row: usize) -> Result<(), JsValue> {
+    self.editor.remove_gen(row).map_err(js_error)
+  }
+
+  pub fn add_branch(&mut self, branch: JsValue) -> Result<(), JsValue> {
+    let branch: case::Branch = branch.into_serde().map_err(js_error)?;
+    self.editor.add_branch(branch).map_err(js_error)
+  }
+
+  pub fn remove_branch(&mut self, row: usize) -> Result<(), JsValue> {
+    self.editor.remove_branch(row).map_err(js_error)
+  }
+
+  pub fn can_undo(&self) -> bool {
+    self.editor.can_undo()
+  }
+
+  pub fn can_redo(&self) -> bool {
+    self.editor.can_redo()
+  }
+
+  pub fn undo(&mut self) -> Result<bool, JsValue> {
+    self.editor.undo().map_err(js_error)
+  }
+
+  pub fn redo(&mut self) -> Result<bool, JsValue> {
+    self.editor.redo().map_err(js_error)
+  }
+
+  /// Descriptions of the edits in effect, oldest first.
+  pub fn history(&self) -> JsValue {
+    JsValue::from_serde(&self.editor.history()).unwrap()
+  }
+
+  /// The edits in effect as a MATLAB function `name(mpc)`, in the style of a `modcase` script.
+  pub fn change_script(&self, name: &str) -> String {
+    self.editor.to_script(name)
+  }
+}
@@ -0,0 +1,130 @@
+// Excel workbook export
+
+// One sheet per MATPOWER table, with MATPOWER column names in the first row, units in the second and the header
+// rows frozen. Values are written as numbers so they can be summed and charted.
+
+use anyhow::Result;
+use rust_xlsxwriter::{Format, Workbook, Worksheet};
+
+use crate::case::{Case, BRANCH_COLUMNS, BUS_COLUMNS, DCLINE_COLUMNS, GENCOST_COLUMNS, GEN_COLUMNS};
+
+fn header(sheet: &mut Worksheet, columns: &[(String, String)], bold: &Format) -> Result<()> {
+  for (c, (name, unit)) in columns.iter().enumerate() {
+    sheet.write_string_with_format(0, c as u16, name, bold)?;
+    sheet.write_string(1, c as u16, unit)?;
+  }
+  sheet.set_freeze_panes(2, 0)?;
+  Ok(())
+}
+
+// Rows below the two header rows. Columns past the named ones are the cost values of `GenCost` rows.
+fn table(workbook: &mut Workbook, name: &str, columns: &[(&str, &str)], rows: &[Vec<f64>], bold: &Format) -> Result<()> {
+  let sheet = workbook.add_worksheet().set_name(name)?;
+  let width = rows.iter().map(Vec::len).max().unwrap_or(0).max(columns.len());
+  let mut names = columns.iter().map(|(n, u)| (n.to_string(), u.to_string())).collect::<Vec<_>>();
+  names.extend((columns.len()..width).map(|i| (format!("COST{}", i - columns.len() + 1), String::new())));
+  header(sheet, &names, bold)?;
+  for (r, row) in rows.iter().enumerate() {
+    for (c, v) in row.iter().enumerate() {
+      sheet.write_number(r as u32 + 2, c as u16, *v)?;
+    }
+  }
+  sheet.autofit();
+  Ok(())
+}
+
+impl Case {
+  /// Excel workbook with `Summary`, `Bus`, `Gen`, `Branch`, `GenCost`, `DcLine` and `BusName` sheets.
+  pub fn to_xlsx(&self) -> Result<Vec<u8>> {
+    let mut workbook = Workbook::new();
+    let bold = Format::new().set_bold();
+    let tables = self.to_tables();
+
+    let summary = workbook.add_worksheet().set_name("Summary")?;
+    let load = self.bus.iter().fold((0.0, 0.0), |(p, q), b| (p + b.pd, q + b.qd));
+    let in_service = self.gen.iter().filter(|g| g.gen_status > 0);
+    let capacity = in_service.clone().map(|g| g.pmax).sum::<f64>();
+    let generation = in_service.map(|g| g.pg).sum::<f64>();
+    let rows: [(&str, f64, &str); 10] = [
+      ("Base MVA", self.base_mva, "MVA"),
+      ("Buses", self.bus.len() as f64, ""),
+      ("Generators", self.gen.len() as f64, ""),
+      ("Branches", self.branch.len() as f64, ""),
+      ("In-service branches", self.branch.iter().filter(|b| b.br_status > 0.0).count() as f64, ""),
+      ("DC lines", self.dcline.len() as f64, ""),
+      ("Total load", load.0, "MW"),
+      ("Total reactive load", load.1, "MVAr"),
+      ("Generation", generation, "MW"),
+      ("Generation capacity", capacity, "MW"),
+    ];
+    header(summary, &[("Item", ""), ("Value", ""), ("Unit", "")].map(|(a, b)| (a.to_string(), b.to_string())), &bold)?;
+    summary.write_string(2, 0, "Name")?;
+    summary.write_string(2, 1, &self.name)?;
+    summary.write_string(3, 0, "Version")?;
+    summary.write_number(3, 1, self.version as i64 as f64)?;
+    for (i, (item, value, unit)) in rows.iter().enumerate() {
+      summary.write_string(i as u32 + 4, 0, *item)?;
+      summary.write_number(i as u32 + 4, 1, *value)?;
+      summary.write_string(i as u32 + 4, 2, *unit)?;
+    }
+    summary.autofit();
+
+    table(&mut workbook, "Bus", &BUS_COLUMNS, &tables.bus, &bold)?;
+    table(&mut workbook, "Gen", &GEN_COLUMNS, &tables.gen, &bold)?;
+    table(&mut workbook, "Branch", &BRANCH_COLUMNS, &tables.branch, &bold)?;
+    table(&mut workbook, "GenCost", &GENCOST_COLUMNS, &tables.gencost, &bold)?;
+    table(&mut workbook, "DcLine", &DCLINE_COLUMNS, &tables.dcline, &bold)?;
+
+    let names = workbook.add_worksheet().set_name("BusName")?;
+    header(names, &[("BUS_I", ""), ("NAME", "")].map(|(a, b)| (a.to_string(), b.to_string())), &bold)?;
+    for (i, (bus, name)) in self.bus.iter().zip(self.bus_name.iter()).enumerate() {
+      names.write_number(i as u32 + 2, 0, bus.idx.0 as f64)?;
+      names.write_string(i as u32 + 2, 1, name)?;
+    }
+    names.autofit();
+
+    Ok(workbook.save_to_buffer()?)
+  }
+}
+
+#[test]
+fn test_to_xlsx() {
+  use std::io::{Cursor, Read};
+
+  let c = crate::case::case(
+    r#"function mpc = case2
+mpc.version = '2';
+mpc.baseMVA = 100;
+mpc.bus = [
+	1	3	0	0	0	0	1	1	0	230	1	1.1	0.9;
+	2	1	90	30	0	0	1	1	0	230	1	1.1	0.9;
+];
+mpc.gen = [
+	1	90	0	300	-300	1	100	1	250	10	0	0	0	0	0	0	0	0	0	0	0;
+];
+mpc.branch = [
+	1	2	0.01	0.1	0.2	250	250	300	0	0	1	-360	360;
+];
+mpc.gencost = [
+	2	0	0	3	0.11	5	150;
+];
+mpc.bus_name = {
+	'One';
+	'Two';
+};
+"#,
+  )
+  .unwrap();
+  let b = c.to_xlsx().unwrap();
+  let mut archive = zip::ZipArchive::new(Cursor::new(b)).unwrap();
+  let mut workbook = String::new();
+  archive.by_name("xl/workbook.xml").unwrap().read_to_string(&mut workbook).unwrap();
+  for sheet in ["Summary", "Bus", "Gen", "Branch", "GenCost", "DcLine", "BusName"] {
+    assert!(workbook.contains(&format!("name=\"{}\"", sheet)), "missing sheet {}", sheet);
+  }
+  // Bus is the second sheet; its third row holds bus 1 as numbers.
+  let mut bus = String::new();
+  archive.by_name("xl/worksheets/sheet2.xml").unwrap().read_to_string(&mut bus).unwrap();
+  assert!(bus.contains("<pane ySplit=\"2\""));
+  assert!(bus.contains("<c r=\"J3\"><v>230</v></c>"));
+}